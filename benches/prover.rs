@@ -0,0 +1,91 @@
+use std::fs::read_dir;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use logic_model::graphs::{Adjacency, BitMatrix, Graph};
+use logic_model::graphs::node::World;
+use logic_model::graphs::search::GraphSearch;
+use logic_model::modal_config::ModalOptions;
+use logic_model::model::Model;
+
+fn problems() -> Vec<String> {
+    let mut problems: Vec<String> = read_dir("data/bench").unwrap()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().display().to_string())
+        .filter(|path| !path.ends_with(".ax") && !path.contains("broken"))
+        .collect();
+    problems.sort();
+    problems
+}
+
+/// A ring of `n` worlds, each also seeing the world two steps ahead.
+fn ring<A: Adjacency>(n: usize) -> Graph<World, A> {
+    let mut worlds = Graph::<World, A>::new(n);
+    for w in 0..n {
+        worlds.add_edge(w, (w + 1) % n);
+        worlds.add_edge(w, (w + 2) % n);
+    }
+    worlds
+}
+
+fn tableau(c: &mut Criterion) {
+    let mut group = c.benchmark_group("eval_tableau");
+    for problem in problems() {
+        let name = problem.rsplit('/').next().unwrap().to_string();
+        group.bench_function(name, |b| b.iter(|| {
+            let mut model = Model::from_file(&problem).unwrap();
+            model.eval_tableau().unwrap()
+        }));
+    }
+    group.finish();
+}
+
+fn search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bfs");
+    for n in [16, 128, 1024] {
+        let worlds: Graph<World> = ring(n);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &worlds, |b, worlds| {
+            b.iter(|| GraphSearch::bfs(worlds, 0))
+        });
+    }
+    group.finish();
+}
+
+/// Transitive closure by a breadth-first search from every world, as
+/// `implement_modals` did before the bit matrix.
+fn closure_by_search(worlds: &mut Graph<World>) {
+    for w in worlds.node_ids() {
+        if let Some(reachable) = GraphSearch::bfs(worlds, w).all_marked() {
+            for w_prime in reachable { worlds.add_edge(w, w_prime); }
+        }
+    }
+}
+
+fn modals(c: &mut Criterion) {
+    let mut group = c.benchmark_group("implement_modals");
+    for n in [16, 64, 128, 512] {
+        group.bench_with_input(BenchmarkId::new("search", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut worlds = ring(n);
+                closure_by_search(&mut worlds);
+                worlds
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("list", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut worlds: Graph<World> = ring(n);
+                worlds.implement_modals(&ModalOptions::new_default());
+                worlds
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("matrix", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut worlds: Graph<World, BitMatrix> = ring(n);
+                worlds.implement_modals(&ModalOptions::new_default());
+                worlds
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, tableau, search, modals);
+criterion_main!(benches);
@@ -0,0 +1,6 @@
+%------------------------------------------------------------------------------
+% Problem  : Distribution axiom K
+% Status   : Theorem in all modal systems
+%------------------------------------------------------------------------------
+qmf(distribution, axiom, ( #box: ( p => q ) )).
+qmf(con, conjecture, ( ( #box: p ) => ( #box: q ) )).
@@ -0,0 +1,6 @@
+%------------------------------------------------------------------------------
+% Problem  : Distribution axiom K
+% Status   : Theorem in all modal systems
+%------------------------------------------------------------------------------
+include('k_premise.ax').
+qmf(con, conjecture, ( ( #box: p ) => ( #box: q ) )).
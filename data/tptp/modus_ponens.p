@@ -0,0 +1,7 @@
+%------------------------------------------------------------------------------
+% Problem  : Modus ponens
+% Status   : Theorem
+%------------------------------------------------------------------------------
+fof(implication, axiom, ( p => q )).
+fof(antecedent, axiom, p).
+fof(consequent, conjecture, q).
@@ -0,0 +1,8 @@
+%------------------------------------------------------------------------------
+% Problem  : Socrates is mortal, as clauses
+% Status   : Unsatisfiable
+%------------------------------------------------------------------------------
+cnf(men_are_mortal, axiom, ~ man(X) | mortal(X)).
+cnf(socrates_is_a_man, axiom, man(socrates)).
+cnf(socrates_is_immortal, negated_conjecture, ~ mortal(socrates)).
+cnf(nobody_is_mortal, negated_conjecture, ~ mortal(plato)).
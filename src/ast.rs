@@ -0,0 +1,597 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use crate::parser::is_epistemic;
+
+/// The existence predicate, `E!(a)` being true where a is in the domain.
+pub const EXISTENCE: &str = "E!";
+/// Identity, the predicate of `a = b`.
+pub const IDENTITY: &str = "=";
+/// Lewis's counterfactual conditional, the one operator of two characters.
+pub const COUNTERFACTUAL: &str = "□→";
+/// Obligation, `O φ` being read as ◻φ and `O(ψ/φ)` as conditional
+/// obligation.
+pub const OBLIGATION: &str = "O";
+/// The instruction of conditional obligation, with the obligation and the
+/// condition as its variables.
+pub const CONDITIONAL_OBLIGATION: &str = "O/";
+/// The prefix of `@_i φ`, φ at the world the nominal i names.
+pub const AT: &str = "@_";
+/// The binder of `↓x.φ`, φ with x naming the world of evaluation.
+pub const BINDER: char = '↓';
+/// The constants, true and false at every world.
+pub const VERUM: &str = "⊤";
+pub const FALSUM: &str = "⊥";
+
+/// A formula parsed all the way down to its atoms.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expr {
+    Atom(String),
+    Verum,
+    Falsum,
+    // a predicate applied to names of constants or variables, e.g. `R(a, x)`
+    Predicate(String, Vec<String>),
+    // a quantifier binding a variable, e.g. `∀x P(x)`
+    Quantified(Quantifier, String, Box<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Implies(Box<Expr>, Box<Expr>),
+    Iff(Box<Expr>, Box<Expr>),
+    // exclusive or, true when exactly one side is
+    Xor(Box<Expr>, Box<Expr>),
+    // strict implication, ◻(A ⊃ B)
+    Strict(Box<Expr>, Box<Expr>),
+    // Lewis's counterfactual: B at the closest worlds where A
+    Counterfactual(Box<Expr>, Box<Expr>),
+    // conditional obligation O(ψ/φ): ψ at the best worlds where φ
+    Obligation(Box<Expr>, Box<Expr>),
+    Necessarily(Box<Expr>),
+    Possibly(Box<Expr>),
+    // ◻ and ◇ of the relation named by the index, e.g. `[a]p`, `K_a p`
+    IndexedNecessarily(String, Box<Expr>),
+    IndexedPossibly(String, Box<Expr>),
+    // knowledge of a group of agents, agents sorted, e.g. `C_{a,b} p`
+    Group(GroupModality, Vec<String>, Box<Expr>),
+    // hybrid satisfaction operator @_i φ: φ at the world named i
+    At(String, Box<Expr>),
+    // hybrid binder ↓x.φ: φ with x a nominal for the world of evaluation
+    Bind(String, Box<Expr>),
+    // unary operators of linear time, e.g. `G p`, `Y q`
+    Temporal(TemporalOp, Box<Expr>),
+    // φ U ψ: ψ eventually, φ until then; φ S ψ: ψ once, φ ever since
+    Until(Box<Expr>, Box<Expr>),
+    Since(Box<Expr>, Box<Expr>),
+}
+
+/// The unary temporal operators, future then past.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TemporalOp {
+    // G: at every future time including now
+    Always,
+    // F: at some future time including now
+    Eventually,
+    // X: at the next time
+    Next,
+    // H: at every past time including now
+    Historically,
+    // P: at some past time including now
+    Once,
+    // Y: there is a previous time and it holds there
+    Yesterday,
+    // Z: if there is a previous time it holds there
+    WeakYesterday
+}
+
+impl TemporalOp {
+    pub const ALL: [TemporalOp; 7] = [
+        TemporalOp::Always, TemporalOp::Eventually, TemporalOp::Next,
+        TemporalOp::Historically, TemporalOp::Once, TemporalOp::Yesterday, TemporalOp::WeakYesterday
+    ];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemporalOp::Always => "G",
+            TemporalOp::Eventually => "F",
+            TemporalOp::Next => "X",
+            TemporalOp::Historically => "H",
+            TemporalOp::Once => "P",
+            TemporalOp::Yesterday => "Y",
+            TemporalOp::WeakYesterday => "Z"
+        }
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<TemporalOp> {
+        TemporalOp::ALL.into_iter().find(|op| op.symbol() == symbol)
+    }
+
+    /// The operator o' with ¬o φ equivalent to o' ¬φ.
+    pub fn dual(&self) -> TemporalOp {
+        match self {
+            TemporalOp::Always => TemporalOp::Eventually,
+            TemporalOp::Eventually => TemporalOp::Always,
+            TemporalOp::Next => TemporalOp::Next,
+            TemporalOp::Historically => TemporalOp::Once,
+            TemporalOp::Once => TemporalOp::Historically,
+            TemporalOp::Yesterday => TemporalOp::WeakYesterday,
+            TemporalOp::WeakYesterday => TemporalOp::Yesterday
+        }
+    }
+}
+
+/// The first-order quantifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quantifier {
+    Universal,
+    Existential
+}
+
+impl Quantifier {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Quantifier::Universal => "∀",
+            Quantifier::Existential => "∃"
+        }
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<Quantifier> {
+        [Quantifier::Universal, Quantifier::Existential].into_iter()
+            .find(|quantifier| quantifier.symbol() == symbol)
+    }
+
+    /// The quantifier q' with ¬q x φ equivalent to q' x ¬φ.
+    pub fn dual(&self) -> Quantifier {
+        match self {
+            Quantifier::Universal => Quantifier::Existential,
+            Quantifier::Existential => Quantifier::Universal
+        }
+    }
+}
+
+/// What a group of agents knows, from the agents' `K_a` relations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GroupModality {
+    // E_G: every agent in G knows, over the union of their relations
+    Everyone,
+    // C_G: true wherever the reflexive-transitive closure of the union reaches
+    Common,
+    // D_G: true wherever the intersection of their relations reaches
+    Distributed
+}
+
+impl GroupModality {
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            GroupModality::Everyone => "E_",
+            GroupModality::Common => "C_",
+            GroupModality::Distributed => "D_"
+        }
+    }
+
+    pub fn from_prefix(prefix: &str) -> Option<GroupModality> {
+        [GroupModality::Everyone, GroupModality::Common, GroupModality::Distributed].into_iter()
+            .find(|modality| modality.prefix() == prefix)
+    }
+}
+
+/// Index of the knowledge relation of `agent`.
+pub fn knowledge(agent: &str) -> String {
+    format!("K_{}", agent)
+}
+
+impl Expr {
+    pub fn atom(name: &str) -> Expr {
+        Expr::Atom(name.to_string())
+    }
+
+    pub fn negated(self) -> Expr {
+        Expr::Not(Box::new(self))
+    }
+
+    pub fn and(self, other: Expr) -> Expr {
+        Expr::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Expr) -> Expr {
+        Expr::Or(Box::new(self), Box::new(other))
+    }
+
+    pub fn implies(self, other: Expr) -> Expr {
+        Expr::Implies(Box::new(self), Box::new(other))
+    }
+
+    pub fn iff(self, other: Expr) -> Expr {
+        Expr::Iff(Box::new(self), Box::new(other))
+    }
+
+    pub fn xor(self, other: Expr) -> Expr {
+        Expr::Xor(Box::new(self), Box::new(other))
+    }
+
+    pub fn is_atom(&self) -> bool {
+        matches!(self, Expr::Atom(_) | Expr::Predicate(..))
+    }
+
+    /// An atom or a negated atom.
+    pub fn is_literal(&self) -> bool {
+        match self {
+            Expr::Not(a) => a.is_atom(),
+            _ => self.is_atom()
+        }
+    }
+
+    pub fn is_binary(&self) -> bool {
+        matches!(self, Expr::And(..) | Expr::Or(..) | Expr::Implies(..) | Expr::Iff(..) | Expr::Xor(..)
+            | Expr::Strict(..) | Expr::Counterfactual(..) | Expr::Until(..) | Expr::Since(..))
+    }
+
+    /// Immediate subformulas, left to right.
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Atom(_) | Expr::Predicate(..) | Expr::Verum | Expr::Falsum => vec![],
+            Expr::Quantified(_, _, a) => vec![a],
+            Expr::Not(a) | Expr::Necessarily(a) | Expr::Possibly(a) => vec![a],
+            Expr::IndexedNecessarily(_, a) | Expr::IndexedPossibly(_, a) | Expr::Group(_, _, a) => vec![a],
+            Expr::At(_, a) | Expr::Bind(_, a) => vec![a],
+            Expr::Temporal(_, a) => vec![a],
+            Expr::And(a, b) | Expr::Or(a, b) | Expr::Implies(a, b) => vec![a, b],
+            Expr::Iff(a, b) | Expr::Xor(a, b) => vec![a, b],
+            Expr::Strict(a, b) | Expr::Counterfactual(a, b) | Expr::Obligation(a, b) => vec![a, b],
+            Expr::Until(a, b) | Expr::Since(a, b) => vec![a, b],
+        }
+    }
+
+    pub fn is_modal(&self) -> bool {
+        matches!(self, Expr::Necessarily(_) | Expr::Possibly(_)
+            | Expr::IndexedNecessarily(..) | Expr::IndexedPossibly(..) | Expr::Group(..)
+            | Expr::Strict(..) | Expr::Counterfactual(..) | Expr::Obligation(..)
+            | Expr::At(..) | Expr::Bind(..))
+            || self.children().iter().any(|child| child.is_modal())
+    }
+
+    pub fn is_binding(&self) -> bool {
+        matches!(self, Expr::Bind(..))
+            || self.children().iter().any(|child| child.is_binding())
+    }
+
+    /// Names used as nominals after `@_` and not bound by `↓`.
+    pub fn nominals(&self) -> BTreeSet<String> {
+        let mut nominals: BTreeSet<String> = self.children().iter().flat_map(|child| child.nominals()).collect();
+        match self {
+            Expr::At(nominal, _) => {
+                nominals.insert(nominal.clone());
+            },
+            Expr::Bind(var, _) => {
+                nominals.remove(var);
+            },
+            _ => {}
+        }
+        nominals
+    }
+
+    pub fn is_counterfactual(&self) -> bool {
+        matches!(self, Expr::Counterfactual(..))
+            || self.children().iter().any(|child| child.is_counterfactual())
+    }
+
+    pub fn is_dyadic(&self) -> bool {
+        matches!(self, Expr::Obligation(..))
+            || self.children().iter().any(|child| child.is_dyadic())
+    }
+
+    /// The formula with `P φ` read as permission, ◇φ, rather than as the
+    /// past.
+    pub fn deontic(&self) -> Expr {
+        match self {
+            Expr::Temporal(TemporalOp::Once, a) => Expr::Possibly(Box::new(a.deontic())),
+            _ => self.map_children(|child| child.deontic())
+        }
+    }
+
+    pub fn is_temporal(&self) -> bool {
+        matches!(self, Expr::Temporal(..) | Expr::Until(..) | Expr::Since(..))
+            || self.children().iter().any(|child| child.is_temporal())
+    }
+
+    pub fn is_quantified(&self) -> bool {
+        matches!(self, Expr::Quantified(..))
+            || self.children().iter().any(|child| child.is_quantified())
+    }
+
+    /// Atoms and predications, each as written.
+    pub fn atoms(&self) -> BTreeSet<String> {
+        match self {
+            Expr::Atom(name) => BTreeSet::from([name.clone()]),
+            Expr::Predicate(..) => BTreeSet::from([self.to_string()]),
+            _ => self.children().iter().flat_map(|child| child.atoms()).collect(),
+        }
+    }
+
+    /// Indices of the indexed modalities occurring in the formula, with
+    /// `K_a` for each agent a of a group modality.
+    pub fn indices(&self) -> BTreeSet<String> {
+        let mut indices: BTreeSet<String> = self.children().iter().flat_map(|child| child.indices()).collect();
+        match self {
+            Expr::IndexedNecessarily(index, _) | Expr::IndexedPossibly(index, _) => {
+                indices.insert(index.clone());
+            },
+            Expr::Group(_, agents, _) => indices.extend(agents.iter().map(|agent| knowledge(agent))),
+            _ => {}
+        }
+        indices
+    }
+
+    /// Names occurring free as arguments of predicates: the constants,
+    /// and any variables not bound.
+    pub fn free_names(&self) -> BTreeSet<String> {
+        match self {
+            Expr::Predicate(_, args) => args.iter().cloned().collect(),
+            Expr::Quantified(_, var, a) => {
+                let mut names = a.free_names();
+                names.remove(var);
+                names
+            },
+            _ => self.children().iter().flat_map(|child| child.free_names()).collect()
+        }
+    }
+
+    /// The two sides of an identity `a = b`.
+    pub fn identity(&self) -> Option<(&String, &String)> {
+        match self {
+            Expr::Predicate(name, args) if name == IDENTITY => Some((&args[0], &args[1])),
+            _ => None
+        }
+    }
+
+    /// Names occurring anywhere as arguments or bound variables.
+    pub fn names(&self) -> BTreeSet<String> {
+        let mut names: BTreeSet<String> = self.children().iter().flat_map(|child| child.names()).collect();
+        match self {
+            Expr::Predicate(_, args) => names.extend(args.iter().cloned()),
+            Expr::Quantified(_, var, _) => {
+                names.insert(var.clone());
+            },
+            _ => {}
+        }
+        names
+    }
+
+    /// Replaces the free occurrences of `var` by `name`, renaming bound
+    /// variables that would capture it.
+    pub fn substitute(&self, var: &str, name: &str) -> Expr {
+        match self {
+            Expr::Predicate(predicate, args) => Expr::Predicate(
+                predicate.clone(),
+                args.iter().map(|arg| if arg == var { name.to_string() } else { arg.clone() }).collect()
+            ),
+            Expr::Quantified(_, bound, _) if bound == var => self.clone(),
+            Expr::Quantified(quantifier, bound, a) if bound == name && a.free_names().contains(var) => {
+                let mut renamed = format!("{}_", bound);
+                while renamed == var || a.names().contains(&renamed) {
+                    renamed.push('_');
+                }
+                let a = a.substitute(bound, &renamed);
+                Expr::Quantified(*quantifier, renamed, Box::new(a.substitute(var, name)))
+            },
+            Expr::Quantified(quantifier, bound, a) => Expr::Quantified(*quantifier, bound.clone(), Box::new(a.substitute(var, name))),
+            // state variables of ↓ are nominals, so atoms
+            Expr::Atom(atom) if atom == var => Expr::Atom(name.to_string()),
+            Expr::At(nominal, a) if nominal == var => Expr::At(name.to_string(), Box::new(a.substitute(var, name))),
+            Expr::Bind(bound, _) if bound == var => self.clone(),
+            Expr::Bind(bound, a) if bound == name && (a.atoms().contains(var) || a.nominals().contains(var)) => {
+                let mut renamed = format!("{}_", bound);
+                while renamed == var || a.atoms().contains(&renamed) || a.nominals().contains(&renamed) {
+                    renamed.push('_');
+                }
+                let a = a.substitute(bound, &renamed);
+                Expr::Bind(renamed, Box::new(a.substitute(var, name)))
+            },
+            _ => self.map_children(|child| child.substitute(var, name))
+        }
+    }
+
+    /// Replaces each free name by its image under `names`, if it has one.
+    pub fn rename(&self, names: &HashMap<String, String>) -> Expr {
+        match self {
+            Expr::Predicate(predicate, args) => Expr::Predicate(
+                predicate.clone(),
+                args.iter().map(|arg| names.get(arg).unwrap_or(arg).clone()).collect()
+            ),
+            Expr::Quantified(quantifier, var, a) if names.contains_key(var) => {
+                let mut names = names.clone();
+                names.remove(var);
+                Expr::Quantified(*quantifier, var.clone(), Box::new(a.rename(&names)))
+            },
+            _ => self.map_children(|child| child.rename(names))
+        }
+    }
+
+    /// The same connective applied to `f` of each child.
+    fn map_children(&self, f: impl Fn(&Expr) -> Expr) -> Expr {
+        let unary = |a: &Expr| Box::new(f(a));
+        match self {
+            Expr::Atom(_) | Expr::Predicate(..) | Expr::Verum | Expr::Falsum => self.clone(),
+            Expr::Quantified(quantifier, var, a) => Expr::Quantified(*quantifier, var.clone(), unary(a)),
+            Expr::Not(a) => Expr::Not(unary(a)),
+            Expr::Necessarily(a) => Expr::Necessarily(unary(a)),
+            Expr::Possibly(a) => Expr::Possibly(unary(a)),
+            Expr::IndexedNecessarily(i, a) => Expr::IndexedNecessarily(i.clone(), unary(a)),
+            Expr::IndexedPossibly(i, a) => Expr::IndexedPossibly(i.clone(), unary(a)),
+            Expr::Group(modality, agents, a) => Expr::Group(*modality, agents.clone(), unary(a)),
+            Expr::At(nominal, a) => Expr::At(nominal.clone(), unary(a)),
+            Expr::Bind(var, a) => Expr::Bind(var.clone(), unary(a)),
+            Expr::Temporal(op, a) => Expr::Temporal(*op, unary(a)),
+            Expr::And(a, b) => Expr::And(unary(a), unary(b)),
+            Expr::Or(a, b) => Expr::Or(unary(a), unary(b)),
+            Expr::Implies(a, b) => Expr::Implies(unary(a), unary(b)),
+            Expr::Iff(a, b) => Expr::Iff(unary(a), unary(b)),
+            Expr::Xor(a, b) => Expr::Xor(unary(a), unary(b)),
+            Expr::Strict(a, b) => Expr::Strict(unary(a), unary(b)),
+            Expr::Counterfactual(a, b) => Expr::Counterfactual(unary(a), unary(b)),
+            Expr::Obligation(a, b) => Expr::Obligation(unary(a), unary(b)),
+            Expr::Until(a, b) => Expr::Until(unary(a), unary(b)),
+            Expr::Since(a, b) => Expr::Since(unary(a), unary(b))
+        }
+    }
+
+    /// Every distinct subformula, each listed after its own subformulas.
+    pub fn subformulas(&self) -> Vec<Expr> {
+        let mut output = Vec::new();
+        self.collect_subformulas(&mut output);
+        output
+    }
+
+    fn collect_subformulas(&self, output: &mut Vec<Expr>) {
+        for child in self.children() {
+            child.collect_subformulas(output);
+        }
+        if !output.contains(self) {
+            output.push(self.clone());
+        }
+    }
+
+    /// Classical truth value under a valuation of the atoms.
+    pub fn eval(&self, valuation: &HashMap<String, bool>) -> Result<bool, String> {
+        match self {
+            Expr::Atom(name) => valuation.get(name).copied()
+                .ok_or(format!("No value for {}", name)),
+            Expr::Predicate(..) => valuation.get(&self.to_string()).copied()
+                .ok_or(format!("No value for {}", self)),
+            Expr::Verum => Ok(true),
+            Expr::Falsum => Ok(false),
+            Expr::Not(a) => Ok(!a.eval(valuation)?),
+            Expr::And(a, b) => Ok(a.eval(valuation)? && b.eval(valuation)?),
+            Expr::Or(a, b) => Ok(a.eval(valuation)? || b.eval(valuation)?),
+            Expr::Implies(a, b) => Ok(!a.eval(valuation)? || b.eval(valuation)?),
+            Expr::Iff(a, b) => Ok(a.eval(valuation)? == b.eval(valuation)?),
+            Expr::Xor(a, b) => Ok(a.eval(valuation)? != b.eval(valuation)?),
+            Expr::Necessarily(_) | Expr::Possibly(_)
+            | Expr::IndexedNecessarily(..) | Expr::IndexedPossibly(..) | Expr::Group(..)
+            | Expr::Strict(..) | Expr::Counterfactual(..) | Expr::Obligation(..) | Expr::At(..) | Expr::Bind(..)
+            | Expr::Temporal(..) | Expr::Until(..) | Expr::Since(..) | Expr::Quantified(..) => {
+                Err(format!("{} has no truth-functional value", self))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn wrap(expr: &Expr) -> String {
+            if expr.is_binary() || expr.identity().is_some() {
+                format!("({})", expr)
+            } else {
+                expr.to_string()
+            }
+        }
+
+        match self {
+            Expr::Atom(name) => write!(f, "{}", name),
+            Expr::Verum => write!(f, "{}", VERUM),
+            Expr::Falsum => write!(f, "{}", FALSUM),
+            Expr::Predicate(name, args) if name == IDENTITY => write!(f, "{} = {}", args[0], args[1]),
+            Expr::Predicate(name, args) => write!(f, "{}({})", name, args.join(", ")),
+            Expr::Quantified(quantifier, var, a) => write!(f, "{}{} {}", quantifier.symbol(), var, wrap(a)),
+            Expr::Not(a) => write!(f, "¬{}", wrap(a)),
+            Expr::Necessarily(a) => write!(f, "◻{}", wrap(a)),
+            Expr::Possibly(a) => write!(f, "◇{}", wrap(a)),
+            Expr::IndexedNecessarily(i, a) if is_epistemic(i) => write!(f, "{} {}", i, wrap(a)),
+            Expr::IndexedNecessarily(i, a) => write!(f, "[{}]{}", i, wrap(a)),
+            Expr::IndexedPossibly(i, a) => write!(f, "<{}>{}", i, wrap(a)),
+            Expr::Group(modality, agents, a) if agents.len() == 1 => {
+                write!(f, "{}{} {}", modality.prefix(), agents[0], wrap(a))
+            },
+            Expr::Group(modality, agents, a) => write!(f, "{}{{{}}} {}", modality.prefix(), agents.join(","), wrap(a)),
+            Expr::At(nominal, a) => write!(f, "{}{} {}", AT, nominal, wrap(a)),
+            Expr::Bind(var, a) => write!(f, "{}{}.{}", BINDER, var, wrap(a)),
+            Expr::Temporal(op, a) => write!(f, "{} {}", op.symbol(), wrap(a)),
+            Expr::Until(a, b) => write!(f, "{} U {}", wrap(a), wrap(b)),
+            Expr::Since(a, b) => write!(f, "{} S {}", wrap(a), wrap(b)),
+            // ⋀ and ⋁ associate to the left, so left-nested chains need no
+            // parentheses, and are walked down rather than recursed into
+            Expr::And(..) | Expr::Or(..) => {
+                let (mut first, mut rest) = (self, Vec::new());
+                while let (Expr::And(a, b), Expr::And(..)) | (Expr::Or(a, b), Expr::Or(..)) = (first, self) {
+                    rest.push(b);
+                    first = a;
+                }
+                let op = if matches!(self, Expr::And(..)) { "⋀" } else { "⋁" };
+                write!(f, "{}", wrap(first))?;
+                rest.iter().rev().try_for_each(|b| write!(f, " {} {}", op, wrap(b)))
+            },
+            Expr::Implies(a, b) => write!(f, "{} ⊃ {}", wrap(a), wrap(b)),
+            Expr::Iff(a, b) => write!(f, "{} ≡ {}", wrap(a), wrap(b)),
+            Expr::Xor(a, b) => write!(f, "{} ⊕ {}", wrap(a), wrap(b)),
+            Expr::Strict(a, b) => write!(f, "{} ⥽ {}", wrap(a), wrap(b)),
+            Expr::Counterfactual(a, b) => write!(f, "{} {} {}", wrap(a), COUNTERFACTUAL, wrap(b)),
+            Expr::Obligation(a, b) => write!(f, "{}({}/{})", OBLIGATION, a, b),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let expr = Expr::atom("p").and(Expr::atom("q")).implies(Expr::atom("r").negated());
+        assert_eq!("(p ⋀ q) ⊃ ¬r", expr.to_string());
+        let expr = Expr::Necessarily(Box::new(Expr::atom("p").or(Expr::atom("q"))));
+        assert_eq!("◻(p ⋁ q)", expr.to_string());
+        assert!(expr.is_modal());
+        let expr = Expr::atom("p").or(Expr::atom("q")).or(Expr::atom("r").or(Expr::atom("s")));
+        assert_eq!("p ⋁ q ⋁ (r ⋁ s)", expr.to_string());
+
+        let expr = Expr::IndexedNecessarily("K_a".to_string(), Box::new(
+            Expr::IndexedPossibly("b".to_string(), Box::new(Expr::atom("p")))
+        ));
+        assert_eq!("K_a <b>p", expr.to_string());
+        assert!(expr.is_modal());
+        assert_eq!(BTreeSet::from(["K_a".to_string(), "b".to_string()]), expr.indices());
+
+        let agents = vec!["a".to_string(), "b".to_string()];
+        let expr = Expr::Group(GroupModality::Common, agents, Box::new(Expr::atom("p").negated()));
+        assert_eq!("C_{a,b} ¬p", expr.to_string());
+        assert_eq!(BTreeSet::from(["K_a".to_string(), "K_b".to_string()]), expr.indices());
+
+        let expr = Expr::Temporal(TemporalOp::Always, Box::new(
+            Expr::atom("p").implies(Expr::Until(Box::new(Expr::atom("q")), Box::new(Expr::atom("r"))))
+        ));
+        assert_eq!("G (p ⊃ (q U r))", expr.to_string());
+        assert!(expr.is_temporal());
+        assert!(!expr.is_modal());
+    }
+
+    #[test]
+    fn subformulas() {
+        let expr = Expr::atom("p").implies(Expr::atom("q").and(Expr::atom("p")));
+        let subformulas: Vec<String> = expr.subformulas().iter().map(|s| s.to_string()).collect();
+        assert_eq!(vec!["p", "q", "q ⋀ p", "p ⊃ (q ⋀ p)"], subformulas);
+        assert_eq!(BTreeSet::from(["p".to_string(), "q".to_string()]), expr.atoms());
+    }
+
+    #[test]
+    fn first_order() {
+        let pred = |name: &str, args: &[&str]| Expr::Predicate(name.to_string(), args.iter().map(|a| a.to_string()).collect());
+        // ∀x ∃y R(x, y) ⊃ P(a, x), with the last x free
+        let body = Expr::Quantified(Quantifier::Existential, "y".to_string(), Box::new(pred("R", &["x", "y"])));
+        let expr = Expr::Quantified(Quantifier::Universal, "x".to_string(), Box::new(body)).implies(pred("P", &["a", "x"]));
+        assert_eq!("∀x ∃y R(x, y) ⊃ P(a, x)", expr.to_string());
+        assert!(expr.is_quantified());
+        assert_eq!(BTreeSet::from(["a".to_string(), "x".to_string()]), expr.free_names());
+        assert_eq!("∀x ∃y R(x, y) ⊃ P(a, b)", expr.substitute("x", "b").to_string());
+
+        // substituting y for x under ∃y renames the bound y
+        let expr = Expr::Quantified(Quantifier::Existential, "y".to_string(), Box::new(pred("R", &["x", "y"])));
+        assert_eq!("∃y_ R(y, y_)", expr.substitute("x", "y").to_string());
+        assert_eq!(Quantifier::Existential, Quantifier::Universal.dual());
+
+        let identity = pred(IDENTITY, &["a", "x"]);
+        assert_eq!("¬(a = x)", identity.clone().negated().to_string());
+        assert!(identity.clone().negated().is_literal());
+        let names = HashMap::from([("a".to_string(), "b".to_string()), ("x".to_string(), "c".to_string())]);
+        let expr = Expr::Quantified(Quantifier::Universal, "x".to_string(), Box::new(identity.clone()));
+        assert_eq!("∀x (b = x)", expr.rename(&names).to_string());
+        assert!(!expr.is_literal());
+    }
+}
@@ -0,0 +1,247 @@
+use std::fmt;
+use std::fs::read_dir;
+use std::time::{Duration, Instant};
+use crate::configs::{Config, OutputFormat};
+use crate::model::{Model, TableauStats, Verdict};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Status {
+    Valid,
+    Invalid,
+    // stopped by the time or world limit or the instantiation bound
+    Unknown,
+    Error
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Status::Valid => write!(f, "valid"),
+            Status::Invalid => write!(f, "invalid"),
+            Status::Unknown => write!(f, "unknown"),
+            Status::Error => write!(f, "error")
+        }
+    }
+}
+
+/// Outcome of running the prover on one problem file.
+#[derive(Debug)]
+pub struct BenchResult {
+    problem: String,
+    status: Status,
+    time: Duration,
+    // none unless the modal tableau decided the problem
+    stats: Option<TableauStats>,
+    message: Option<String>
+}
+
+impl BenchResult {
+    pub fn problem(&self) -> &str {
+        &self.problem
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    pub fn stats(&self) -> Option<TableauStats> {
+        self.stats
+    }
+
+    pub fn message(&self) -> Option<&String> {
+        self.message.as_ref()
+    }
+}
+
+/// Runs every file in the directory `config` names, in name order, with its
+/// time limit each.
+pub fn run_dir(config: &Config) -> Result<Vec<BenchResult>, String> {
+    let dir = config.infile();
+    let mut problems: Vec<String> = read_dir(dir)
+        .map_err(|err| format!("{} ({})", err, dir))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| path.extension().is_none_or(|ext| ext != "ax"))
+        .map(|path| path.display().to_string())
+        .collect();
+    problems.sort();
+
+    Ok(problems.iter().map(|problem| run_problem(problem, config)).collect())
+}
+
+/// Decides `problem` with the method and options of `config`.
+pub fn run_problem(problem: &str, config: &Config) -> BenchResult {
+    let start = Instant::now();
+    let mut model = match Model::from_config(problem, config) {
+        Ok(model) => model,
+        Err(err) => return BenchResult {
+            problem: problem.to_string(),
+            status: Status::Error,
+            time: start.elapsed(),
+            stats: None,
+            message: Some(err.to_string())
+        }
+    };
+    model.set_time_limit(config.time_limit());
+
+    let runs_tableau = model.runs_tableau(config.method());
+    let result = model.decide(config.method());
+    let time = start.elapsed();
+    let (status, message) = match result {
+        Ok(Verdict::Valid) => (Status::Valid, None),
+        Ok(Verdict::Invalid(_)) => (Status::Invalid, None),
+        Ok(Verdict::Unknown(err)) => (Status::Unknown, Some(err)),
+        Err(err) => (Status::Error, Some(err))
+    };
+    tracing::info!("{}: {} in {:?}", problem, status, time);
+
+    let stats = runs_tableau.then(|| model.stats());
+    BenchResult { problem: problem.to_string(), status, time, stats, message }
+}
+
+pub fn format_results(results: &[BenchResult], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Csv => to_csv(results),
+        OutputFormat::Json => to_json(results)
+    }
+}
+
+pub fn to_csv(results: &[BenchResult]) -> String {
+    fn quote(field: &str) -> String {
+        if field.contains([',', '"', '\n']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+
+    let mut lines = vec!["problem,status,time_ms,nodes,worlds,branches_closed,max_depth,message".to_string()];
+    for result in results {
+        let stats = result.stats.map_or(",,,".to_string(), |stats| {
+            format!("{},{},{},{}", stats.nodes, stats.worlds, stats.branches_closed, stats.max_depth)
+        });
+        lines.push(format!(
+            "{},{},{:.3},{},{}",
+            quote(&result.problem),
+            result.status,
+            result.time.as_secs_f64() * 1000.0,
+            stats,
+            quote(result.message.as_deref().unwrap_or(""))
+        ));
+    }
+    lines.join("\n") + "\n"
+}
+
+pub fn to_json(results: &[BenchResult]) -> String {
+    fn escape(field: &str) -> String {
+        let mut output = String::with_capacity(field.len() + 2);
+        output.push('"');
+        for ch in field.chars() {
+            match ch {
+                '"' => output.push_str("\\\""),
+                '\\' => output.push_str("\\\\"),
+                '\n' => output.push_str("\\n"),
+                ch if (ch as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", ch as u32)),
+                ch => output.push(ch)
+            }
+        }
+        output.push('"');
+        output
+    }
+
+    let entries: Vec<String> = results.iter()
+        .map(|result| {
+            let stat = |field: fn(&TableauStats) -> usize| result.stats.as_ref().map_or("null".to_string(), |stats| field(stats).to_string());
+            format!(
+                "  {{\"problem\": {}, \"status\": \"{}\", \"time_ms\": {:.3}, \"nodes\": {}, \"worlds\": {}, \"branches_closed\": {}, \"max_depth\": {}, \"message\": {}}}",
+                escape(&result.problem),
+                result.status,
+                result.time.as_secs_f64() * 1000.0,
+                stat(|stats| stats.nodes),
+                stat(|stats| stats.worlds),
+                stat(|stats| stats.branches_closed),
+                stat(|stats| stats.max_depth),
+                result.message.as_deref().map_or("null".to_string(), escape)
+            )
+        })
+        .collect();
+    format!("[\n{}\n]\n", entries.join(",\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(line: &str) -> Config {
+        Config::build(line.split(' ').map(String::from)).unwrap()
+    }
+
+    #[test]
+    fn corpus() {
+        let results = run_dir(&config("logic_model bench --method tableau data/bench")).unwrap();
+        let statuses: Vec<(&str, Status)> = results.iter()
+            .map(|result| (result.problem().rsplit('/').next().unwrap(), result.status()))
+            .collect();
+        assert_eq!(vec![
+            ("b_axiom.txt", Status::Valid),
+            ("broken.txt", Status::Error),
+            ("chain.txt", Status::Invalid),
+            ("k_axiom.p", Status::Valid),
+            ("modus_ponens.p", Status::Valid),
+            ("s5_invalid.txt", Status::Invalid),
+        ], statuses);
+        assert!(results[1].message().is_some());
+        assert!(results[0].stats().is_some_and(|stats| stats.nodes > 0));
+        assert!(results[1].stats().is_none());
+
+        // SAT decides a propositional problem, leaving no tableau to count
+        let result = run_problem("data/bench/modus_ponens.p", &config("logic_model bench data/bench"));
+        assert_eq!(Status::Valid, result.status());
+        assert!(result.stats().is_none());
+
+        let result = run_problem("data/bench/b_axiom.txt", &config("logic_model bench --method tableau --time-limit 0 data/bench"));
+        assert_eq!(Status::Unknown, result.status());
+
+        // the B axiom needs symmetry
+        let result = run_problem("data/bench/b_axiom.txt", &config("logic_model bench --method tableau --logic S4 data/bench"));
+        assert_eq!(Status::Invalid, result.status());
+        // and modus ponens fails in LP
+        let result = run_problem("data/bench/modus_ponens.p", &config("logic_model bench --logic LP data/bench"));
+        assert_eq!(Status::Invalid, result.status());
+    }
+
+    #[test]
+    fn output() {
+        let results = vec![BenchResult {
+            problem: "a,b.txt".to_string(),
+            status: Status::Error,
+            time: Duration::from_millis(2),
+            stats: Some(TableauStats { nodes: 3, worlds: 1, branches_closed: 0, max_depth: 3 }),
+            message: Some("Unable to parse \"x\"".to_string())
+        }, BenchResult {
+            problem: "p.txt".to_string(),
+            status: Status::Valid,
+            time: Duration::from_millis(1),
+            stats: None,
+            message: None
+        }];
+        assert_eq!(
+            "problem,status,time_ms,nodes,worlds,branches_closed,max_depth,message\n\
+             \"a,b.txt\",error,2.000,3,1,0,3,\"Unable to parse \"\"x\"\"\"\n\
+             p.txt,valid,1.000,,,,,\n",
+            to_csv(&results)
+        );
+        assert_eq!(
+            "[\n  {\"problem\": \"a,b.txt\", \"status\": \"error\", \"time_ms\": 2.000, \"nodes\": 3, \"worlds\": 1, \
+             \"branches_closed\": 0, \"max_depth\": 3, \"message\": \"Unable to parse \\\"x\\\"\"},\n  \
+             {\"problem\": \"p.txt\", \"status\": \"valid\", \"time_ms\": 1.000, \"nodes\": null, \"worlds\": null, \
+             \"branches_closed\": null, \"max_depth\": null, \"message\": null}\n]\n",
+            to_json(&results)
+        );
+    }
+}
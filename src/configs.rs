@@ -1,20 +1,411 @@
+use std::time::Duration;
+use crate::graphs::node::Closure;
+use crate::modal_config::ModalOptions;
+use crate::model::MAX_INSTANCES;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Method {
+    // SAT for propositional problems, otherwise the tableau
+    Auto,
+    Tableau,
+    TruthTable,
+    Sat
+}
+
+impl Method {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "auto" => Ok(Method::Auto),
+            "tableau" => Ok(Method::Tableau),
+            "truth-table" => Ok(Method::TruthTable),
+            "sat" => Ok(Method::Sat),
+            _ => Err(format!("Unknown method {}, expected auto, tableau, truth-table or sat", name))
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Logic {
+    // two valued, with modalities as the modal options say
+    Classical,
+    Intuitionistic,
+    // strong Kleene, with truth value gaps
+    K3,
+    // Priest's logic of paradox, with gluts
+    LP,
+    // first degree entailment, with both
+    FDE,
+    // provability logic, over finite transitive irreflexive frames
+    GL,
+    // Grzegorczyk's logic, over finite partial orders
+    Grz
+}
+
+impl Logic {
+    /// The non-classical logic named `name`, `None` for the name of a
+    /// modal logic.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "Int" | "IPC" => Some(Logic::Intuitionistic),
+            "K3" => Some(Logic::K3),
+            "LP" => Some(Logic::LP),
+            "FDE" => Some(Logic::FDE),
+            "GL" => Some(Logic::GL),
+            "Grz" => Some(Logic::Grz),
+            _ => None
+        }
+    }
+
+    /// Whether the logic is GL or Grz, whose ◻ no modal options describe.
+    pub fn is_provability(self) -> bool {
+        matches!(self, Logic::GL | Logic::Grz)
+    }
+
+    /// What closes a branch of the logic's tableau.
+    pub fn closure(self) -> Closure {
+        match self {
+            Logic::LP => Closure::NoGaps,
+            Logic::FDE => Closure::Signs,
+            _ => Closure::NoGluts
+        }
+    }
+}
+
+/// How the domains of quantified modal formulas vary between worlds.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Domains {
+    // one domain shared by every world, validating the Barcan formula
+    Constant,
+    // domains growing along accessibility, validating its converse
+    Increasing,
+    // any domain at each world
+    Varying
+}
+
+impl Domains {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "constant" => Ok(Domains::Constant),
+            "increasing" => Ok(Domains::Increasing),
+            "varying" => Ok(Domains::Varying),
+            _ => Err(format!("Unknown domains {}, expected constant, increasing or varying", name))
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NormalForm {
+    Nnf,
+    Cnf,
+    Dnf
+}
+
+impl NormalForm {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "nnf" => Ok(NormalForm::Nnf),
+            "cnf" => Ok(NormalForm::Cnf),
+            "dnf" => Ok(NormalForm::Dnf),
+            _ => Err(format!("Unknown normal form {}, expected nnf, cnf or dnf", name))
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OutputFormat {
+    Csv,
+    Json
+}
+
+impl OutputFormat {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("Unknown format {}, expected csv or json", name))
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Command {
+    // decide whether the last formula follows from the others
+    Eval,
+    // print each formula in a normal form
+    Normalize,
+    // print the argument as a TPTP problem via the standard translation
+    Tptp,
+    // run every problem in a directory and report statistics
+    Bench
+}
+
 pub struct Config {
-    infile: String
+    infile: String,
+    command: Command,
+    method: Method,
+    form: NormalForm,
+    definitional: bool,
+    format: OutputFormat,
+    time_limit: Duration,
+    // instances of each universal formula allowed on a branch
+    max_instances: usize,
+    domains: Domains,
+    rigid_identity: bool,
+    // whether `P` is permission rather than the past
+    deontic: bool,
+    logic: Logic,
+    modal_options: ModalOptions,
+    // logics of indexed modalities, from `--index a=KD45`
+    index_options: Vec<(String, ModalOptions)>
 }
 
 impl Config {
     pub fn build(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
         args.next();
 
-        let infile = match args.next() {
+        let mut infile = None;
+        let mut command = Command::Eval;
+        let mut method = Method::Auto;
+        let mut form = NormalForm::Nnf;
+        let mut definitional = false;
+        let mut format = OutputFormat::Csv;
+        let mut time_limit = Duration::from_secs(10);
+        let mut max_instances = MAX_INSTANCES;
+        let mut domains = Domains::Constant;
+        let mut rigid_identity = true;
+        let mut deontic = false;
+        let mut logic_given = false;
+        let mut logic = Logic::Classical;
+        let mut modal_options = ModalOptions::new_default();
+        let mut index_options = Vec::new();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "normalize" if infile.is_none() && command == Command::Eval => command = Command::Normalize,
+                "tptp" if infile.is_none() && command == Command::Eval => command = Command::Tptp,
+                "bench" if infile.is_none() && command == Command::Eval => command = Command::Bench,
+                "--method" => {
+                    let name = args.next().ok_or("--method should be followed by a method name".to_string())?;
+                    method = Method::parse(&name)?;
+                },
+                "--form" => {
+                    let name = args.next().ok_or("--form should be followed by nnf, cnf or dnf".to_string())?;
+                    form = NormalForm::parse(&name)?;
+                },
+                "--definitional" => definitional = true,
+                "--logic" => {
+                    let name = args.next().ok_or("--logic should be followed by a logic such as K, S4 or KD45".to_string())?;
+                    logic_given = true;
+                    match Logic::parse(&name) {
+                        Some(non_classical) => logic = non_classical,
+                        None => modal_options = ModalOptions::for_logic(&name)?
+                    }
+                },
+                "--index" => {
+                    let setting = args.next().ok_or("--index should be followed by index=logic, e.g. a=KD45".to_string())?;
+                    let (index, name) = setting.split_once('=')
+                        .ok_or(format!("Expected index=logic but found {}", setting))?;
+                    index_options.push((index.to_string(), ModalOptions::for_logic(name)?));
+                },
+                "--max-instances" => {
+                    let count = args.next().ok_or("--max-instances should be followed by a number".to_string())?;
+                    max_instances = count.parse()
+                        .map_err(|_| format!("Instance bound should be a number, found {}", count))?;
+                },
+                "--domains" => {
+                    let name = args.next().ok_or("--domains should be followed by constant, increasing or varying".to_string())?;
+                    domains = Domains::parse(&name)?;
+                },
+                "--nonrigid-identity" => rigid_identity = false,
+                "--deontic" => deontic = true,
+                "--format" => {
+                    let name = args.next().ok_or("--format should be followed by csv or json".to_string())?;
+                    format = OutputFormat::parse(&name)?;
+                },
+                "--time-limit" => {
+                    let secs = args.next().ok_or("--time-limit should be followed by seconds".to_string())?;
+                    let secs: f64 = secs.parse()
+                        .map_err(|_| format!("Time limit should be a number of seconds, found {}", secs))?;
+                    time_limit = Duration::try_from_secs_f64(secs)
+                        .map_err(|_| format!("Invalid time limit {}", secs))?;
+                },
+                _ if infile.is_none() => infile = Some(arg),
+                _ => return Err(format!("Unexpected argument {}", arg))
+            }
+        }
+
+        let infile = match infile {
             Some(pth) => pth,
+            None if command == Command::Bench => return Err("Should have argument with problem directory".to_string()),
             None => return Err("Should have argument with input file path".to_string())
         };
 
-        Ok(Config{infile})
+        if definitional && form != NormalForm::Cnf {
+            return Err("--definitional only applies to --form cnf".to_string());
+        }
+
+        // obligation is serial unless another logic is asked for
+        if deontic && !logic_given {
+            modal_options = ModalOptions::for_logic("KD")?;
+        }
+
+        Ok(Config{infile, command, method, form, definitional, format, time_limit, max_instances, domains, rigid_identity, deontic, logic, modal_options, index_options})
     }
 
     pub fn infile(&self) -> &str {
         &self.infile
     }
+
+    pub fn command(&self) -> Command {
+        self.command
+    }
+
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    pub fn form(&self) -> NormalForm {
+        self.form
+    }
+
+    pub fn definitional(&self) -> bool {
+        self.definitional
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    pub fn time_limit(&self) -> Duration {
+        self.time_limit
+    }
+
+    pub fn max_instances(&self) -> usize {
+        self.max_instances
+    }
+
+    pub fn domains(&self) -> Domains {
+        self.domains
+    }
+
+    pub fn rigid_identity(&self) -> bool {
+        self.rigid_identity
+    }
+
+    pub fn deontic(&self) -> bool {
+        self.deontic
+    }
+
+    pub fn logic(&self) -> Logic {
+        self.logic
+    }
+
+    pub fn modal_options(&self) -> &ModalOptions {
+        &self.modal_options
+    }
+
+    pub fn index_options(&self) -> &Vec<(String, ModalOptions)> {
+        &self.index_options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> {
+        line.split(' ').map(String::from).collect::<Vec<String>>().into_iter()
+    }
+
+    #[test]
+    fn build() {
+        let config = Config::build(args("logic_model data/basic.txt")).unwrap();
+        assert_eq!("data/basic.txt", config.infile());
+        assert_eq!(Method::Auto, config.method());
+
+        let config = Config::build(args("logic_model --method truth-table data/basic.txt")).unwrap();
+        assert_eq!(Method::TruthTable, config.method());
+
+        let config = Config::build(args("logic_model data/basic.txt --method sat")).unwrap();
+        assert_eq!(Method::Sat, config.method());
+
+        assert!(Config::build(args("logic_model")).is_err());
+        assert!(Config::build(args("logic_model data/basic.txt --method")).is_err());
+        assert!(Config::build(args("logic_model data/basic.txt --method guess")).is_err());
+    }
+
+    #[test]
+    fn normalize() {
+        let config = Config::build(args("logic_model normalize data/basic.txt")).unwrap();
+        assert_eq!(Command::Normalize, config.command());
+        assert_eq!(NormalForm::Nnf, config.form());
+
+        let config = Config::build(args("logic_model normalize --form cnf --definitional data/basic.txt")).unwrap();
+        assert_eq!(NormalForm::Cnf, config.form());
+        assert!(config.definitional());
+
+        assert!(Config::build(args("logic_model normalize --definitional data/basic.txt")).is_err());
+        assert!(Config::build(args("logic_model data/basic.txt normalize")).is_err());
+
+        let config = Config::build(args("logic_model tptp data/basic.txt")).unwrap();
+        assert_eq!(Command::Tptp, config.command());
+    }
+
+    #[test]
+    fn bench() {
+        let config = Config::build(args("logic_model bench --time-limit 0.5 --format json data/bench")).unwrap();
+        assert_eq!(Command::Bench, config.command());
+        assert_eq!(OutputFormat::Json, config.format());
+        assert_eq!(Duration::from_millis(500), config.time_limit());
+        assert_eq!("data/bench", config.infile());
+
+        assert!(Config::build(args("logic_model bench --time-limit soon data/bench")).is_err());
+        assert!(Config::build(args("logic_model bench --time-limit -1 data/bench")).is_err());
+        assert!(Config::build(args("logic_model bench --format xml data/bench")).is_err());
+    }
+
+    #[test]
+    fn instances() {
+        let config = Config::build(args("logic_model data/basic.txt")).unwrap();
+        assert_eq!(MAX_INSTANCES, config.max_instances());
+        let config = Config::build(args("logic_model --max-instances 5 data/basic.txt")).unwrap();
+        assert_eq!(5, config.max_instances());
+        assert!(Config::build(args("logic_model --max-instances many data/basic.txt")).is_err());
+
+        assert_eq!(Domains::Constant, config.domains());
+        let config = Config::build(args("logic_model --domains varying data/basic.txt")).unwrap();
+        assert_eq!(Domains::Varying, config.domains());
+        assert!(Config::build(args("logic_model --domains shrinking data/basic.txt")).is_err());
+        assert!(config.rigid_identity());
+        let config = Config::build(args("logic_model --nonrigid-identity data/basic.txt")).unwrap();
+        assert!(!config.rigid_identity());
+    }
+
+    #[test]
+    fn logic() {
+        let config = Config::build(args("logic_model data/basic.txt")).unwrap();
+        assert_eq!(&ModalOptions::new_default(), config.modal_options());
+        let config = Config::build(args("logic_model --logic S4.3 data/basic.txt")).unwrap();
+        assert_eq!(&ModalOptions::for_logic("S4.3").unwrap(), config.modal_options());
+        assert!(Config::build(args("logic_model --logic Q data/basic.txt")).is_err());
+        assert_eq!(Logic::Classical, config.logic());
+        let config = Config::build(args("logic_model --logic Int data/basic.txt")).unwrap();
+        assert_eq!(Logic::Intuitionistic, config.logic());
+        let config = Config::build(args("logic_model --logic LP data/basic.txt")).unwrap();
+        assert_eq!(Logic::LP, config.logic());
+        assert_eq!(Closure::NoGaps, config.logic().closure());
+        let config = Config::build(args("logic_model --logic GL data/basic.txt")).unwrap();
+        assert_eq!(Logic::GL, config.logic());
+        assert!(config.logic().is_provability());
+
+        let config = Config::build(args("logic_model --index a=KD45 --index K_b=T data/basic.txt")).unwrap();
+        assert_eq!(2, config.index_options().len());
+        assert_eq!(("a".to_string(), ModalOptions::for_logic("KD45").unwrap()), config.index_options()[0]);
+        assert!(Config::build(args("logic_model --index KD45 data/basic.txt")).is_err());
+
+        let config = Config::build(args("logic_model --deontic data/basic.txt")).unwrap();
+        assert!(config.deontic());
+        assert_eq!(&ModalOptions::for_logic("KD").unwrap(), config.modal_options());
+        let config = Config::build(args("logic_model --logic KD45 --deontic data/basic.txt")).unwrap();
+        assert_eq!(&ModalOptions::for_logic("KD45").unwrap(), config.modal_options());
+    }
 }
@@ -0,0 +1,581 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+use crate::ast::{CONDITIONAL_OBLIGATION, COUNTERFACTUAL, Expr, FALSUM, VERUM};
+use crate::countermodel::Countermodel;
+use crate::graphs::{Graph, node::{Closure, Formula, World}};
+use crate::model::MAX_WORLDS;
+use crate::parser::{negate, parse_expr, parse_formula, strict_implication};
+use crate::prover::{agreement, normalize_argument, branch_worlds, check_deadline, open_countermodel, valuation, world_limit};
+
+/// Tableau for Lewis's counterfactual logic VC over sphere models. Each world
+/// accesses itself and the worlds made from it, ordered by closeness to it:
+/// itself strictly closest, the others in a total preorder. A world is placed
+/// in that order when it is made, the tableau branching over every place
+/// among the worlds its parent already accesses on the branch.
+///
+/// A □→ B holds when ◻¬A does, or when B holds with A at the world itself or
+/// at some accessed world k with A ⊃ B at every world at most as far as k.
+/// ¬(A □→ B) needs A and ¬B at the world itself or at some accessed world j
+/// with ¬A at every world closer than j. Rules only put subformulas at the
+/// worlds a world accesses, so chains of worlds are no longer than the
+/// formulas are deep.
+///
+/// Dyadic deontic logic takes the same rules without centering: a world
+/// ranks the worlds it accesses, none of them itself, and ◻ and O(B/A)
+/// look at the best of them, so that ◻A is O(A/⊤). Some world is always
+/// ranked, which makes ◻ serial.
+struct Prover {
+    system: System,
+    tableau: Graph<Formula>,
+    worlds: Graph<World>,
+    // how a world compares in closeness to its parent with each world the
+    // parent accessed on its branch when it was made
+    closeness: HashMap<(usize, usize), Ordering>,
+    requirements: Vec<Requirement>
+}
+
+/// A formula owed, on branches through `node`, to every world `anchor`
+/// accesses, or only to those at most as far from it as the bound, or
+/// strictly closer if the bound is strict.
+struct Requirement {
+    node: usize,
+    anchor: usize,
+    bound: Option<(usize, bool)>,
+    formula: String
+}
+
+/// The logics of conditionals the prover decides.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum System {
+    // Lewis's counterfactuals over centered spheres
+    VC,
+    // conditional obligation over preferences, ◻ and ◇ as O and P
+    Deontic
+}
+
+enum Expansion {
+    Literal,
+    Linear(Vec<String>),
+    Branching(Vec<String>),
+    Branches(Vec<Vec<String>>),
+    // the operand at every world accessed
+    Necessity(String),
+    // the operand at some world accessed
+    Possibility(String),
+    // A □→ B or O(B/A): B at the closest worlds where A
+    Would(String, String),
+    // ¬(A □→ B) or ¬O(B/A), given A and ¬B: ¬B at some closest world where A
+    WouldNot(String, String)
+}
+
+impl Prover {
+    fn new(premises: &[String], conclusion: &str, system: System) -> Result<Prover, String> {
+        let mut lines = premises.to_vec();
+        lines.push(negate(conclusion)?);
+        Ok(Prover {
+            system,
+            tableau: Graph::<Formula>::new(lines),
+            worlds: Graph::<World>::new(1),
+            closeness: HashMap::new(),
+            requirements: Vec::new()
+        })
+    }
+
+    fn run(&mut self, deadline: Option<Instant>) -> Result<(), String> {
+        loop {
+            check_deadline(deadline)?;
+            // the formulas owed to the worlds so far may close branches
+            // before a rule adding worlds multiplies them
+            let next = self.next_node()?;
+            if next.is_none_or(|(cost, _)| cost > 0) && self.apply_requirements() {
+                self.tableau.find_contradictions(Closure::NoGluts);
+                continue;
+            }
+            let Some((_, node_id)) = next else { break };
+            self.expand(node_id)?;
+            self.tableau.find_contradictions_below(node_id, Closure::NoGluts);
+        }
+        Ok(())
+    }
+
+    /// The first active node of least cost, with its cost: rules adding
+    /// worlds wait for the others, and □→, which adds the most branches, for
+    /// them all.
+    fn next_node(&self) -> Result<Option<(usize, usize)>, String> {
+        let mut best: Option<(usize, usize)> = None;
+        for node_id in self.tableau.active_nodes().unwrap_or_default() {
+            let cost = match rules(self.tableau.get_node(node_id).unwrap().formula(), self.system)? {
+                Expansion::Would(..) => 2,
+                Expansion::WouldNot(..) | Expansion::Possibility(_) => 1,
+                _ => return Ok(Some((0, node_id)))
+            };
+            if best.is_none_or(|(lowest, _)| cost < lowest) {
+                best = Some((cost, node_id));
+            }
+        }
+        Ok(best)
+    }
+
+    fn expand(&mut self, node_id: usize) -> Result<(), String> {
+        let node = self.tableau.get_node(node_id)
+            .expect("Calling function should make sure node_id is valid");
+        let world = node.world();
+        let expansion = rules(node.formula(), self.system)?;
+
+        if let Expansion::Necessity(formula) = &expansion {
+            self.requirements.push(Requirement { node: node_id, anchor: world, bound: None, formula: formula.clone() });
+        }
+        let terminals = self.tableau.terminal_unclosed(node_id).unwrap_or_default();
+        let branches: Vec<Vec<usize>> = self.tableau.unclosed_branches().unwrap_or_default()
+            .into_iter()
+            .filter(|branch| terminals.contains(branch.last().unwrap()))
+            .collect();
+        for branch in branches {
+            let terminal = *branch.last().unwrap();
+            let options: Vec<Vec<String>> = match &expansion {
+                Expansion::Literal => continue,
+                Expansion::Necessity(_) => {
+                    self.ensure_successor(world, terminal, &branch)?;
+                    continue;
+                },
+                Expansion::Linear(formulas) => {
+                    let missing: Vec<&String> = formulas.iter()
+                        .filter(|formula| !self.tableau.branch_contains(&branch, formula, world))
+                        .collect();
+                    let mut parent = terminal;
+                    for formula in missing {
+                        parent = self.tableau.new_node_from(parent, formula.clone(), world);
+                    }
+                    continue;
+                },
+                Expansion::Branching(formulas) => formulas.iter().map(|formula| vec![formula.clone()]).collect(),
+                Expansion::Branches(branches) => branches.clone(),
+                Expansion::Possibility(formula) => {
+                    let witnessed = self.accessible(&branch, world).into_iter()
+                        .any(|w| self.tableau.branch_contains(&branch, formula, w));
+                    if !witnessed {
+                        self.branch_new_worlds(world, terminal, &branch, std::slice::from_ref(formula), None)?;
+                    }
+                    continue;
+                },
+                Expansion::Would(antecedent, consequent) => {
+                    let vacuous = self.vacuous(antecedent)?;
+                    let here = facts(&[antecedent, consequent]);
+                    let centered = self.system == System::VC;
+                    if self.tableau.branch_contains(&branch, &vacuous, world)
+                        || (centered && self.tableau.branch_contains_all(&branch, &here, world)) {
+                        continue;
+                    }
+                    self.tableau.new_node_from(terminal, vacuous, world);
+                    if centered {
+                        self.add_chain(terminal, &here, world);
+                    }
+                    let implication = if antecedent == VERUM {
+                        consequent.clone()
+                    } else {
+                        parse_expr(antecedent)?.implies(parse_expr(consequent)?).to_string()
+                    };
+                    self.branch_new_worlds(world, terminal, &branch, &here, Some((implication, false)))?;
+                    continue;
+                },
+                Expansion::WouldNot(antecedent, consequent) => {
+                    let here = facts(&[antecedent, consequent]);
+                    if self.system == System::VC {
+                        if self.tableau.branch_contains_all(&branch, &here, world) { continue; }
+                        self.add_chain(terminal, &here, world);
+                    }
+                    self.branch_new_worlds(world, terminal, &branch, &here, Some((negate(antecedent)?, true)))?;
+                    continue;
+                }
+            };
+            if options.iter().any(|formulas| self.tableau.branch_contains_all(&branch, formulas, world)) { continue; }
+            for formulas in options {
+                self.add_chain(terminal, &formulas, world);
+            }
+        }
+
+        self.tableau.get_node_mut(node_id).unwrap().deactivate();
+        Ok(())
+    }
+
+    /// The formula saying no world accessed makes `antecedent` true, which
+    /// is read as a requirement of its negation.
+    fn vacuous(&self, antecedent: &str) -> Result<String, String> {
+        let antecedent = Box::new(parse_expr(antecedent)?);
+        Ok(match self.system {
+            System::VC => Expr::Counterfactual(antecedent, Box::new(Expr::Falsum)),
+            System::Deontic => Expr::Obligation(Box::new(Expr::Falsum), antecedent)
+        }.to_string())
+    }
+
+    /// Makes the branch serial at `world` in deontic logic, adding a world
+    /// it ranks if it ranks none.
+    fn ensure_successor(&mut self, world: usize, terminal: usize, branch: &[usize]) -> Result<(), String> {
+        if self.system == System::Deontic && self.accessible(branch, world).is_empty() {
+            self.branch_new_worlds(world, terminal, branch, &[VERUM.to_string()], None)?;
+        }
+        Ok(())
+    }
+
+    /// Writes `formulas` at `world` in a chain below `parent`, returning the
+    /// first node.
+    fn add_chain(&mut self, parent: usize, formulas: &[String], world: usize) -> usize {
+        let first = self.tableau.new_node_from(parent, formulas[0].clone(), world);
+        let mut parent = first;
+        for formula in &formulas[1..] {
+            parent = self.tableau.new_node_from(parent, formula.clone(), world);
+        }
+        first
+    }
+
+    /// Adds a branch below `terminal` for each place a new world accessed by
+    /// `anchor` can take in its closeness order, with `formulas` true at the
+    /// new world and, given a bound, a formula owed to the worlds at most as
+    /// far from `anchor`, or strictly closer.
+    fn branch_new_worlds(&mut self, anchor: usize, terminal: usize, branch: &[usize],
+                         formulas: &[String], bound: Option<(String, bool)>) -> Result<(), String> {
+        // the tableau is finite, but each place makes a world of its own, so
+        // only the worlds of one branch count towards the limit
+        if branch_worlds(&self.worlds, branch).len() >= MAX_WORLDS {
+            return Err(world_limit());
+        }
+        for placement in self.placements(branch, anchor) {
+            let new_world = self.worlds.add_world();
+            self.worlds.add_edge(anchor, new_world);
+            let first = self.add_chain(terminal, formulas, new_world);
+            self.worlds.get_node_mut(new_world).unwrap().set_origin(first);
+            for (world, ordering) in placement {
+                self.closeness.insert((new_world, world), ordering);
+            }
+            if let Some((formula, strict)) = &bound {
+                self.requirements.push(Requirement {
+                    node: first,
+                    anchor,
+                    bound: Some((new_world, *strict)),
+                    formula: formula.clone()
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Every way of placing a new world among those `anchor` accesses on the
+    /// branch other than itself: in a class of equally close worlds, or in a
+    /// class of its own before, between or after them. Each is given as how
+    /// the new world compares with each of the others.
+    fn placements(&self, branch: &[usize], anchor: usize) -> Vec<Vec<(usize, Ordering)>> {
+        let classes = self.classes(branch, anchor);
+        let place = |class_of_new: usize| classes.iter().enumerate()
+            .flat_map(|(i, class)| {
+                // classes are at even positions, the gaps around them at odd ones
+                let ordering = (class_of_new).cmp(&(2 * i + 1));
+                class.iter().map(move |world| (*world, ordering))
+            })
+            .collect::<Vec<(usize, Ordering)>>();
+        (0..=2 * classes.len()).map(place).collect()
+    }
+
+    /// The worlds `anchor` accesses on the branch, other than itself, in
+    /// classes of equally close worlds from the closest out.
+    fn classes(&self, branch: &[usize], anchor: usize) -> Vec<Vec<usize>> {
+        let mut others: Vec<usize> = self.accessible(branch, anchor).into_iter()
+            .filter(|world| *world != anchor)
+            .collect();
+        others.sort_by(|v, w| self.compare(anchor, *v, *w));
+        let mut classes: Vec<Vec<usize>> = Vec::new();
+        for world in others {
+            match classes.last_mut() {
+                Some(class) if self.compare(anchor, class[0], world) == Ordering::Equal => class.push(world),
+                _ => classes.push(vec![world])
+            }
+        }
+        classes
+    }
+
+    /// How close `v` is to `anchor` compared with `w`, both accessed by it on
+    /// some branch.
+    fn compare(&self, anchor: usize, v: usize, w: usize) -> Ordering {
+        if v == w {
+            Ordering::Equal
+        } else if v == anchor {
+            Ordering::Less
+        } else if w == anchor {
+            Ordering::Greater
+        } else if let Some(ordering) = self.closeness.get(&(v, w)) {
+            *ordering
+        } else {
+            self.closeness.get(&(w, v)).expect("Worlds on a branch should be compared").reverse()
+        }
+    }
+
+    /// Writes each requirement's formula at the worlds it is owed to on every
+    /// open branch. Returns whether anything was added.
+    fn apply_requirements(&mut self) -> bool {
+        let mut changed = false;
+        for branch in self.tableau.unclosed_branches().unwrap_or_default() {
+            let mut owed = Vec::new();
+            for requirement in self.requirements.iter().filter(|r| branch.contains(&r.node)) {
+                for world in self.accessible(&branch, requirement.anchor) {
+                    let within = match requirement.bound {
+                        None => true,
+                        Some((bound, strict)) => match self.compare(requirement.anchor, world, bound) {
+                            Ordering::Less => true,
+                            Ordering::Equal => !strict,
+                            Ordering::Greater => false
+                        }
+                    };
+                    if within && !self.tableau.branch_contains(&branch, &requirement.formula, world)
+                        && !owed.contains(&(requirement.formula.clone(), world)) {
+                        owed.push((requirement.formula.clone(), world));
+                    }
+                }
+            }
+            let mut parent = *branch.last().unwrap();
+            for (formula, world) in owed {
+                parent = self.tableau.new_node_from(parent, formula, world);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// The worlds `world` accesses on the branch: those made from it, and
+    /// in VC itself.
+    fn accessible(&self, branch: &[usize], world: usize) -> Vec<usize> {
+        let mut accessible = match self.system {
+            System::VC => vec![world],
+            System::Deontic => Vec::new()
+        };
+        let mut made: Vec<usize> = self.worlds.adj_to(world).unwrap_or_default()
+            .into_iter()
+            .filter(|w| branch.contains(&self.worlds.get_node(*w).unwrap().origin()))
+            .collect();
+        made.sort();
+        accessible.extend(made);
+        accessible
+    }
+
+    /// The worlds of an open branch, each with spheres of the worlds it
+    /// accesses by their closeness, or in deontic logic its ranking of them,
+    /// true atoms being those on the branch.
+    fn countermodel(&self, branch: &[usize]) -> Countermodel {
+        let members = branch_worlds(&self.worlds, branch);
+        let local = |world: usize| members.iter().position(|w| *w == world).unwrap();
+        // in deontic logic a world ranking none made from it, so bound by no
+        // modal formula, may as well rank itself alone
+        let first = match self.system {
+            System::VC => 1,
+            System::Deontic => 0
+        };
+        let distances = members.iter()
+            .map(|world| {
+                let mut around = BTreeMap::new();
+                if self.system == System::VC || self.accessible(branch, *world).is_empty() {
+                    around.insert(local(*world), 0);
+                }
+                for (distance, class) in self.classes(branch, *world).iter().enumerate() {
+                    around.extend(class.iter().map(|w| (local(*w), distance + first)));
+                }
+                around
+            })
+            .collect();
+        let valuation = valuation(&self.tableau, branch, &members, |node| node.relation().is_none());
+        match self.system {
+            System::VC => Countermodel::spheres(valuation, distances),
+            System::Deontic => Countermodel::preference(valuation, distances)
+        }
+    }
+}
+
+/// The rules, classical but for the modal operators. A ⥽ B is ◻(A ⊃ B), and
+/// a conditional with ⊥ for consequent says no world accessed makes its
+/// antecedent true.
+fn rules(formula: &str, system: System) -> Result<Expansion, String> {
+    let name = match system {
+        System::VC => "the counterfactual logic VC",
+        System::Deontic => "dyadic deontic logic"
+    };
+    let conditional = |operator: &str| match (operator, system) {
+        (COUNTERFACTUAL, System::VC) | (CONDITIONAL_OBLIGATION, System::Deontic) => Ok(()),
+        (op, _) => Err(format!("{} is not an operator of {}", op, name))
+    };
+    let would = |antecedent: &String, consequent: &String| -> Result<Expansion, String> {
+        Ok(if consequent == FALSUM {
+            Expansion::Necessity(negate(antecedent)?)
+        } else {
+            Expansion::Would(antecedent.clone(), consequent.clone())
+        })
+    };
+    let instructions = parse_formula(formula)?;
+    let variables = instructions.variables();
+    let verum = VERUM.to_string();
+    let expansion = match instructions.operators().as_str() {
+        "" => Expansion::Literal,
+        "⋀" => Expansion::Linear(variables.clone()),
+        "⋁" => Expansion::Branching(variables.clone()),
+        "⊃" => Expansion::Branching(vec![negate(&variables[0])?, variables[1].clone()]),
+        "≡" => Expansion::Branches(agreement(&variables[0], &variables[1], true)?),
+        "⊕" => Expansion::Branches(agreement(&variables[0], &variables[1], false)?),
+        "⥽" => Expansion::Linear(vec![strict_implication(&variables[0], &variables[1])?]),
+        "◻" if system == System::Deontic => would(&verum, &variables[0])?,
+        "◇" if system == System::Deontic => Expansion::WouldNot(verum, variables[0].clone()),
+        "◻" => Expansion::Necessity(variables[0].clone()),
+        "◇" => Expansion::Possibility(variables[0].clone()),
+        op @ COUNTERFACTUAL => {
+            conditional(op)?;
+            would(&variables[0], &variables[1])?
+        },
+        // O(B/A) has B first
+        op @ CONDITIONAL_OBLIGATION => {
+            conditional(op)?;
+            would(&variables[1], &variables[0])?
+        },
+        "¬" => {
+            let negated = parse_formula(&variables[0])?;
+            let inner = negated.variables();
+            match negated.operators().as_str() {
+                "" => Expansion::Literal,
+                "¬" => Expansion::Linear(vec![inner[0].clone()]),
+                "⋀" => Expansion::Branching(vec![negate(&inner[0])?, negate(&inner[1])?]),
+                "⋁" => Expansion::Linear(vec![negate(&inner[0])?, negate(&inner[1])?]),
+                "⊃" => Expansion::Linear(vec![inner[0].clone(), negate(&inner[1])?]),
+                "≡" => Expansion::Branches(agreement(&inner[0], &inner[1], false)?),
+                "⊕" => Expansion::Branches(agreement(&inner[0], &inner[1], true)?),
+                "⥽" => Expansion::Linear(vec![negate(&strict_implication(&inner[0], &inner[1])?)?]),
+                "◻" if system == System::Deontic => Expansion::WouldNot(verum, negate(&inner[0])?),
+                "◇" if system == System::Deontic => would(&verum, &negate(&inner[0])?)?,
+                "◻" => Expansion::Possibility(negate(&inner[0])?),
+                "◇" => Expansion::Necessity(negate(&inner[0])?),
+                op @ COUNTERFACTUAL => {
+                    conditional(op)?;
+                    Expansion::WouldNot(inner[0].clone(), negate(&inner[1])?)
+                },
+                op @ CONDITIONAL_OBLIGATION => {
+                    conditional(op)?;
+                    Expansion::WouldNot(inner[1].clone(), negate(&inner[0])?)
+                },
+                op => return Err(format!("¬{} is not a formula of {}", op, name))
+            }
+        },
+        op => return Err(format!("{} is not an operator of {}", op, name))
+    };
+    Ok(expansion)
+}
+
+/// Keeps ⊤ out of the formulas written at a world, unless nothing else is
+/// to be written.
+fn facts(formulas: &[&String]) -> Vec<String> {
+    let facts: Vec<String> = formulas.iter()
+        .filter(|formula| formula.as_str() != VERUM)
+        .map(|formula| formula.to_string())
+        .collect();
+    if facts.is_empty() { vec![VERUM.to_string()] } else { facts }
+}
+
+/// Decides whether the premises entail the conclusion in VC or dyadic
+/// deontic logic. Returns `None` if they do, otherwise a verified sphere or
+/// preference countermodel. Stops with an error at `deadline`.
+pub fn eval_argument(premises: &[String], conclusion: &str, system: System, deadline: Option<Instant>) -> Result<Option<Countermodel>, String> {
+    let (premises, conclusion) = normalize_argument(premises, conclusion)?;
+
+    let mut prover = Prover::new(&premises, &conclusion, system)?;
+    prover.run(deadline)?;
+    open_countermodel(&prover.tableau, &premises, &conclusion, |branch| Ok(prover.countermodel(branch)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::eval_with;
+
+    fn eval(formulas: &[&str]) -> Option<Countermodel> {
+        eval_with(formulas, |premises, conclusion| eval_argument(premises, conclusion, System::VC, None))
+    }
+
+    #[test]
+    fn valid() {
+        let valid: [&[&str]; 12] = [
+            &["p □→ p"],
+            &["(p □→ q) ⋀ (p □→ r) ⊃ (p □→ q ⋀ r)"],
+            &["p ⋀ q ⊃ (p □→ q)"],
+            &["(p □→ q) ⊃ (p ⊃ q)"],
+            &["(p □→ q) ⋀ (q □→ p) ⊃ ((p □→ r) ≡ (q □→ r))"],
+            &["(p □→ r) ⋀ (q □→ r) ⊃ (p ⋁ q □→ r)"],
+            &["(p □→ r) ⋀ ¬(p □→ ¬q) ⊃ (p ⋀ q □→ r)"],
+            &["◻(p ⊃ q) ⊃ (p □→ q)"],
+            &["p ⥽ q", "p □→ q"],
+            &["◻p ⊃ p"],
+            &["(¬p □→ ⊥) ≡ ◻p"],
+            &["p □→ q", "p □→ (q ⋁ r)"]
+        ];
+        for formulas in valid {
+            assert!(eval(formulas).is_none(), "{:?} should be valid", formulas);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let invalid: [&[&str]; 7] = [
+            &["(p □→ q) ⊃ (p ⋀ r □→ q)"],
+            &["(p □→ q) ⋀ (q □→ r) ⊃ (p □→ r)"],
+            &["(p □→ q) ⊃ (¬q □→ ¬p)"],
+            &["(p □→ q) ⋁ (p □→ ¬q)"],
+            &["(p □→ q) ⊃ ◻(p ⊃ q)"],
+            &["(p □→ q) ⊃ (p ⥽ q)"],
+            &["◻p ⊃ ◻◻p"]
+        ];
+        for formulas in invalid {
+            let countermodel = eval(formulas).unwrap_or_else(|| panic!("{:?} should be invalid", formulas));
+            // centered: each world is alone in its innermost sphere
+            for w in countermodel.worlds().node_ids() {
+                let spheres = countermodel.spheres_around(w).unwrap();
+                assert_eq!(spheres[0].iter().copied().collect::<Vec<usize>>(), vec![w]);
+                assert!(spheres.windows(2).all(|pair| pair[0].is_subset(&pair[1])));
+            }
+        }
+
+        let countermodel = eval(&["(p □→ q) ⊃ (p ⋀ r □→ q)"]).unwrap();
+        assert!(countermodel.satisfies("p □→ q", 0).unwrap());
+        assert!(!countermodel.satisfies("p ⋀ r □→ q", 0).unwrap());
+    }
+
+    #[test]
+    fn deontic() {
+        let prove = |formulas: &[&str]| {
+            eval_with(formulas, |premises, conclusion| eval_argument(premises, conclusion, System::Deontic, None))
+        };
+        let valid: [&[&str]; 6] = [
+            &["◻p ⊃ ◇p"],
+            &["O(q/p) ⋀ O(r/p) ⊃ O(q ⋀ r/p)"],
+            &["O(p/p)"],
+            &["O(r/p) ⋀ O(r/q) ⊃ O(r/p ⋁ q)"],
+            &["O(q/p) ⋀ ¬O(¬r/p) ⊃ O(q/p ⋀ r)"],
+            &["◻p ≡ O(p/⊤)"]
+        ];
+        for formulas in valid {
+            assert!(prove(formulas).is_none(), "{:?} should be valid", formulas);
+        }
+        let invalid: [&[&str]; 5] = [
+            &["◻p ⊃ p"],
+            &["O(q/p) ⊃ O(q/p ⋀ r)"],
+            &["O(q/p) ⋀ p ⊃ ◻q"],
+            &["◻p ⊃ ◻◻p"],
+            &["O(q/p) ⊃ (p ⊃ q)"]
+        ];
+        for formulas in invalid {
+            let countermodel = prove(formulas).unwrap_or_else(|| panic!("{:?} should be invalid", formulas));
+            // serial: every world ranks some world
+            for w in countermodel.worlds().node_ids() {
+                assert!(!countermodel.preferences(w).unwrap().is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(eval_argument(&[], "[a]p ⊃ (p □→ p)", System::VC, None).is_err());
+        assert!(eval_argument(&[], "p □→", System::VC, None).is_err());
+        assert!(eval_argument(&[], "O(p/q) ⊃ (p □→ q)", System::Deontic, None).is_err());
+    }
+}
@@ -0,0 +1,834 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use crate::ast::{CONDITIONAL_OBLIGATION, COUNTERFACTUAL, Expr, FALSUM, IDENTITY, VERUM, knowledge};
+use crate::graphs::{FrameProperties, FrameProperty, Graph, classes, node::World};
+use crate::modal_config::ModalOptions;
+use crate::parser::{existent, instantiate, parse_expr, parse_formula};
+
+/// A Kripke model read off an open tableau branch. World 0 is the root world,
+/// and quantifiers at a world range over its domain.
+/// A temporal countermodel is a lasso: the worlds are times, each followed
+/// by the next, and the last by the time the loop starts at. An
+/// intuitionistic countermodel is a partial order of worlds along which
+/// truth persists. A first degree countermodel has one world where each
+/// atom is true, false, both (b) or neither (n). A sphere countermodel
+/// nests spheres of worlds around each world, itself alone the innermost,
+/// and the worlds a world accesses are those of its spheres. A preference
+/// countermodel ranks worlds from the best for each world, which accesses
+/// the best. A world may be named by a nominal, true there and nowhere
+/// else, which is what `↓` binds there.
+pub struct Countermodel {
+    worlds: Graph<World>,
+    valuation: Vec<HashSet<String>>,
+    semantics: Semantics,
+    // what the frame of a Kripke model should be
+    frame: Vec<FrameProperty>
+}
+
+#[derive(PartialEq, Clone)]
+enum Semantics {
+    Kripke,
+    // a lasso going back to this time after the last
+    Trace(usize),
+    // ⊃ and ¬ look at every world above, the relation being ≤
+    Intuitionistic,
+    // the atoms of the argument and those of them that are false
+    FirstDegree { atoms: BTreeSet<String>, false_atoms: HashSet<String> },
+    // for each world, the index of the first of its spheres each world is in
+    Spheres(Vec<BTreeMap<usize, usize>>),
+    // for each world, the rank of each world it ranks, 0 the best
+    Preference(Vec<BTreeMap<usize, usize>>)
+}
+
+impl Countermodel {
+    pub fn new(worlds: Graph<World>, valuation: Vec<HashSet<String>>) -> Countermodel {
+        Countermodel { worlds, valuation, semantics: Semantics::Kripke, frame: Vec::new() }
+    }
+
+    /// The model, whose frame should have `properties`.
+    pub fn with_frame(mut self, properties: Vec<FrameProperty>) -> Countermodel {
+        self.frame = properties;
+        self
+    }
+
+    /// The model with `domain` at every world.
+    pub fn with_domain(self, domain: BTreeSet<String>) -> Countermodel {
+        let domains = vec![domain; self.worlds.size()];
+        self.with_domains(domains)
+    }
+
+    /// The model with `domains[w]` at each world w.
+    pub fn with_domains(mut self, domains: Vec<BTreeSet<String>>) -> Countermodel {
+        for (world, domain) in domains.into_iter().enumerate() {
+            if let Some(world) = self.worlds.get_node_mut(world) {
+                world.set_domain(domain);
+            }
+        }
+        self
+    }
+
+    pub fn domain(&self, world: usize) -> Option<&BTreeSet<String>> {
+        self.worlds.get_node(world).map(|world| world.domain())
+    }
+
+    /// The trace through times 0, 1, ... with the given true atoms, going
+    /// back to time `loop_start` after the last.
+    pub fn trace(valuation: Vec<HashSet<String>>, loop_start: usize) -> Countermodel {
+        let mut worlds = Graph::<World>::new(valuation.len());
+        for time in 1..valuation.len() {
+            worlds.add_edge(time - 1, time);
+        }
+        worlds.add_edge(valuation.len() - 1, loop_start);
+        Countermodel { worlds, valuation, semantics: Semantics::Trace(loop_start), frame: Vec::new() }
+    }
+
+    /// The worlds ordered by the reflexive transitive closure of the edges
+    /// of `worlds`. Atoms true at a world should be true above it.
+    pub fn intuitionistic(mut worlds: Graph<World>, valuation: Vec<HashSet<String>>) -> Countermodel {
+        worlds.implement_modals(&ModalOptions::new(true, false, true, false));
+        Countermodel { worlds, valuation, semantics: Semantics::Intuitionistic, frame: Vec::new() }
+    }
+
+    /// The worlds with spheres around each world w made of the worlds at
+    /// most some distance from it, `distances[w]` mapping the worlds of its
+    /// spheres to their distance. For system VC, w alone is at distance 0.
+    pub fn spheres(valuation: Vec<HashSet<String>>, distances: Vec<BTreeMap<usize, usize>>) -> Countermodel {
+        let mut worlds = Graph::<World>::new(valuation.len());
+        for (w, around) in distances.iter().enumerate() {
+            for v in around.keys() {
+                worlds.add_edge(w, *v);
+            }
+        }
+        Countermodel { worlds, valuation, semantics: Semantics::Spheres(distances), frame: Vec::new() }
+    }
+
+    /// The worlds with each world w ranking the worlds of `ranks[w]` from 0,
+    /// the best, as in dyadic deontic logic. Each world accesses the worlds
+    /// it ranks best, and should rank some.
+    pub fn preference(valuation: Vec<HashSet<String>>, ranks: Vec<BTreeMap<usize, usize>>) -> Countermodel {
+        let mut worlds = Graph::<World>::new(valuation.len());
+        for (w, ranked) in ranks.iter().enumerate() {
+            for (v, _) in ranked.iter().filter(|(_, rank)| **rank == 0) {
+                worlds.add_edge(w, *v);
+            }
+        }
+        Countermodel { worlds, valuation, semantics: Semantics::Preference(ranks), frame: Vec::new() }
+    }
+
+    /// The worlds `world` ranks, in classes of equally good worlds from the
+    /// best, `None` unless the model has preference semantics.
+    pub fn preferences(&self, world: usize) -> Option<Vec<BTreeSet<usize>>> {
+        let Semantics::Preference(ranks) = &self.semantics else {
+            return None;
+        };
+        let ranked = ranks.get(world)?;
+        let levels: BTreeSet<usize> = ranked.values().copied().collect();
+        Some(levels.into_iter()
+            .map(|level| ranked.iter().filter(|(_, rank)| **rank == level).map(|(v, _)| *v).collect())
+            .collect())
+    }
+
+    /// The spheres around `world` from the innermost out, `None` unless the
+    /// model has sphere semantics.
+    pub fn spheres_around(&self, world: usize) -> Option<Vec<BTreeSet<usize>>> {
+        let Semantics::Spheres(distances) = &self.semantics else {
+            return None;
+        };
+        let around = distances.get(world)?;
+        let radii: BTreeSet<usize> = around.values().copied().collect();
+        Some(radii.into_iter()
+            .map(|radius| around.iter().filter(|(_, d)| **d <= radius).map(|(v, _)| *v).collect())
+            .collect())
+    }
+
+    /// A single world valuing `atoms` in FDE: true if among `true_atoms`,
+    /// false if among `false_atoms`, possibly both or neither.
+    pub fn first_degree(atoms: BTreeSet<String>, true_atoms: HashSet<String>, false_atoms: HashSet<String>) -> Countermodel {
+        Countermodel {
+            worlds: Graph::<World>::new(1),
+            valuation: vec![true_atoms],
+            semantics: Semantics::FirstDegree { atoms, false_atoms },
+            frame: Vec::new()
+        }
+    }
+
+    /// The time a trace loops back to, `None` for any other model.
+    pub fn loop_start(&self) -> Option<usize> {
+        match self.semantics {
+            Semantics::Trace(loop_start) => Some(loop_start),
+            _ => None
+        }
+    }
+
+    pub fn is_intuitionistic(&self) -> bool {
+        self.semantics == Semantics::Intuitionistic
+    }
+
+    pub fn worlds(&self) -> &Graph<World> {
+        &self.worlds
+    }
+
+    pub fn true_atoms(&self, world: usize) -> Option<&HashSet<String>> {
+        self.valuation.get(world)
+    }
+
+    fn name(&self, world: usize) -> Option<&String> {
+        self.worlds.get_node(world).and_then(|w| w.name())
+    }
+
+    /// Whether an atom is true at `world`. Names identical there by its true
+    /// identities `a = b` are interchangeable, and `E!(a)` is true of the
+    /// names in its domain.
+    fn atom_holds(&self, atom: &str, world: usize) -> Result<bool, String> {
+        let atoms = self.true_atoms(world).ok_or(format!("No world {} in countermodel", world))?;
+        let domain = self.domain(world).ok_or(format!("No world {} in countermodel", world))?;
+        let identities = atoms.iter()
+            .filter(|atom| atom.contains(IDENTITY))
+            .map(|atom| parse_expr(atom))
+            .collect::<Result<Vec<Expr>, String>>()?;
+        let names = classes(identities.iter()
+            .filter_map(|expr| expr.identity())
+            .map(|(a, b)| (a.clone(), b.clone())));
+        let canonical = |name: &String| names.get(name).unwrap_or(name).clone();
+
+        let expr = parse_expr(atom)?;
+        if let Some(value) = constant(atom) {
+            return Ok(value);
+        }
+        if self.name(world).is_some_and(|name| name == atom) {
+            return Ok(true);
+        }
+        if let Some((a, b)) = expr.identity() {
+            return Ok(canonical(a) == canonical(b));
+        }
+        if let Some(name) = existent(atom)? {
+            return Ok(domain.iter().any(|member| canonical(member) == canonical(&name)));
+        }
+        if names.is_empty() {
+            return Ok(atoms.contains(atom));
+        }
+        let renamed = expr.rename(&names);
+        for true_atom in atoms {
+            if parse_expr(true_atom)?.rename(&names) == renamed {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Evaluates `formula` at `world`, on a trace at the time `world` is
+    /// first reached.
+    pub fn satisfies(&self, formula: &str, world: usize) -> Result<bool, String> {
+        if let Some(loop_start) = self.loop_start() {
+            // times past the horizon repeat the loop exactly: by then every
+            // past operator has seen the loop often enough to settle
+            let period = self.valuation.len() - loop_start;
+            let horizon = self.valuation.len() + period * (parse_expr(formula)?.subformulas().len() + 1);
+            return self.satisfies_at(formula, world, horizon);
+        }
+        if self.is_intuitionistic() {
+            return self.forces(formula, world);
+        }
+        if let Semantics::FirstDegree { false_atoms, .. } = &self.semantics {
+            return Ok(self.first_degree_value(formula, false_atoms)?.0);
+        }
+        let instructions = parse_formula(formula)?;
+        let variables = instructions.variables();
+        let successors = || self.worlds.adj_to(world)
+            .ok_or(format!("No world {} in countermodel", world));
+        let domain = || self.domain(world)
+            .ok_or(format!("No world {} in countermodel", world));
+
+        match instructions.operators().as_str() {
+            "" => self.atom_holds(&variables[0], world),
+            "¬" => Ok(!self.satisfies(&variables[0], world)?),
+            "⋀" => Ok(self.satisfies(&variables[0], world)? && self.satisfies(&variables[1], world)?),
+            "⋁" => Ok(self.satisfies(&variables[0], world)? || self.satisfies(&variables[1], world)?),
+            "⊃" => Ok(!self.satisfies(&variables[0], world)? || self.satisfies(&variables[1], world)?),
+            "≡" => Ok(self.satisfies(&variables[0], world)? == self.satisfies(&variables[1], world)?),
+            "⊕" => Ok(self.satisfies(&variables[0], world)? != self.satisfies(&variables[1], world)?),
+            "⥽" => {
+                for w in successors()? {
+                    if self.satisfies(&variables[0], w)? && !self.satisfies(&variables[1], w)? { return Ok(false); }
+                }
+                Ok(true)
+            },
+            COUNTERFACTUAL => match &self.semantics {
+                Semantics::Spheres(distances) => self.at_closest(distances, &variables[0], &variables[1], world),
+                _ => Err(format!("No sphere semantics for {}", COUNTERFACTUAL))
+            },
+            CONDITIONAL_OBLIGATION => match &self.semantics {
+                Semantics::Preference(ranks) => self.at_closest(ranks, &variables[1], &variables[0], world),
+                _ => Err(format!("No preference semantics for {}", formula))
+            },
+            "◻" => {
+                for w in successors()? {
+                    if !self.satisfies(&variables[0], w)? { return Ok(false); }
+                }
+                Ok(true)
+            },
+            "◇" => {
+                for w in successors()? {
+                    if self.satisfies(&variables[0], w)? { return Ok(true); }
+                }
+                Ok(false)
+            },
+            "∀" => {
+                for name in domain()? {
+                    if !self.satisfies(&instantiate(&variables[0], instructions.index(), name)?, world)? { return Ok(false); }
+                }
+                Ok(true)
+            },
+            "∃" => {
+                for name in domain()? {
+                    if self.satisfies(&instantiate(&variables[0], instructions.index(), name)?, world)? { return Ok(true); }
+                }
+                Ok(false)
+            },
+            "@" => {
+                for w in self.worlds.node_ids() {
+                    if self.atom_holds(instructions.index(), w)? {
+                        return self.satisfies(&variables[0], w);
+                    }
+                }
+                Err(format!("Nominal {} names no world", instructions.index()))
+            },
+            "↓" => {
+                let name = self.name(world).ok_or(format!("World w{} has no name for {}", world, formula))?;
+                self.satisfies(&instantiate(&variables[0], instructions.index(), name)?, world)
+            },
+            "[]" => {
+                for w in self.labelled_successors(world, instructions.index())? {
+                    if !self.satisfies(&variables[0], w)? { return Ok(false); }
+                }
+                Ok(true)
+            },
+            "<>" => {
+                for w in self.labelled_successors(world, instructions.index())? {
+                    if self.satisfies(&variables[0], w)? { return Ok(true); }
+                }
+                Ok(false)
+            },
+            "E_" | "C_" | "D_" => {
+                let relations: Vec<String> = instructions.group().iter().map(|agent| knowledge(agent)).collect();
+                let accessible = match instructions.operators().as_str() {
+                    "E_" => {
+                        let mut accessible = Vec::new();
+                        for relation in relations.iter() {
+                            accessible.extend(self.labelled_successors(world, relation)?);
+                        }
+                        Some(accessible)
+                    },
+                    "C_" => self.worlds.reachable_by_any(world, &relations),
+                    _ => self.worlds.common_successors(world, &relations)
+                }.ok_or(format!("No world {} in countermodel", world))?;
+                for w in accessible {
+                    if !self.satisfies(&variables[0], w)? { return Ok(false); }
+                }
+                Ok(true)
+            },
+            op => Err(format!("No semantics for {} in {}", op, formula))
+        }
+    }
+
+    /// Whether `antecedent □→ consequent`, or O(consequent/antecedent),
+    /// holds at `world`: vacuously when no world `distances` puts around it
+    /// makes the antecedent true, and otherwise when the consequent is true
+    /// at the closest, or best, worlds that do.
+    fn at_closest(&self, distances: &[BTreeMap<usize, usize>], antecedent: &str, consequent: &str, world: usize) -> Result<bool, String> {
+        let around = distances.get(world).ok_or(format!("No world {} in countermodel", world))?;
+        let mut closest: Option<(usize, bool)> = None;
+        for (v, distance) in around {
+            if !self.satisfies(antecedent, *v)? { continue; }
+            match closest {
+                Some((nearest, _)) if nearest < *distance => {},
+                Some((nearest, false)) if nearest == *distance => {},
+                _ => closest = Some((*distance, self.satisfies(consequent, *v)?))
+            }
+        }
+        Ok(closest.is_none_or(|(_, holds)| holds))
+    }
+
+    /// Intuitionistic truth of `formula` at `world`: an implication or
+    /// negation holds when it holds classically at every world above.
+    fn forces(&self, formula: &str, world: usize) -> Result<bool, String> {
+        let instructions = parse_formula(formula)?;
+        let variables = instructions.variables();
+        let above = self.worlds.adj_to(world)
+            .ok_or(format!("No world {} in countermodel", world))?;
+
+        match instructions.operators().as_str() {
+            "" => Ok(constant(&variables[0]).unwrap_or_else(|| self.valuation[world].contains(&variables[0]))),
+            "⋀" => Ok(self.forces(&variables[0], world)? && self.forces(&variables[1], world)?),
+            "⋁" => Ok(self.forces(&variables[0], world)? || self.forces(&variables[1], world)?),
+            "⊃" => {
+                for w in above {
+                    if self.forces(&variables[0], w)? && !self.forces(&variables[1], w)? { return Ok(false); }
+                }
+                Ok(true)
+            },
+            "¬" => {
+                for w in above {
+                    if self.forces(&variables[0], w)? { return Ok(false); }
+                }
+                Ok(true)
+            },
+            // both implications, and the negation of that
+            "≡" => {
+                for w in above {
+                    if self.forces(&variables[0], w)? != self.forces(&variables[1], w)? { return Ok(false); }
+                }
+                Ok(true)
+            },
+            "⊕" => {
+                let iff = format!("({}) ≡ ({})", variables[0], variables[1]);
+                for w in above {
+                    if self.forces(&iff, w)? { return Ok(false); }
+                }
+                Ok(true)
+            },
+            op => Err(format!("No intuitionistic semantics for {} in {}", op, formula))
+        }
+    }
+
+    /// Whether `formula` is true and whether it is false in a first degree
+    /// model, ⊃ being material and A ≡ B being (A ⊃ B) ⋀ (B ⊃ A).
+    fn first_degree_value(&self, formula: &str, false_atoms: &HashSet<String>) -> Result<(bool, bool), String> {
+        let instructions = parse_formula(formula)?;
+        let variables = instructions.variables();
+        let value = |formula: &String| self.first_degree_value(formula, false_atoms);
+
+        match instructions.operators().as_str() {
+            "" => Ok(match constant(&variables[0]) {
+                Some(value) => (value, !value),
+                None => (self.valuation[0].contains(&variables[0]), false_atoms.contains(&variables[0]))
+            }),
+            "¬" => value(&variables[0]).map(|(t, f)| (f, t)),
+            "⋀" => {
+                let ((t1, f1), (t2, f2)) = (value(&variables[0])?, value(&variables[1])?);
+                Ok((t1 && t2, f1 || f2))
+            },
+            "⋁" => {
+                let ((t1, f1), (t2, f2)) = (value(&variables[0])?, value(&variables[1])?);
+                Ok((t1 || t2, f1 && f2))
+            },
+            "⊃" => {
+                let ((t1, f1), (t2, f2)) = (value(&variables[0])?, value(&variables[1])?);
+                Ok((f1 || t2, t1 && f2))
+            },
+            "≡" | "⊕" => {
+                let ((t1, f1), (t2, f2)) = (value(&variables[0])?, value(&variables[1])?);
+                let (t, f) = ((f1 || t2) && (f2 || t1), (t1 && f2) || (t2 && f1));
+                Ok(if instructions.operators() == "≡" { (t, f) } else { (f, t) })
+            },
+            op => Err(format!("No first degree semantics for {} in {}", op, formula))
+        }
+    }
+
+    /// Evaluates `formula` at `time` on a trace. A future operator at a
+    /// time looks as far as one loop past the later of the time and the
+    /// horizon, which covers every state the future holds.
+    fn satisfies_at(&self, formula: &str, time: usize, horizon: usize) -> Result<bool, String> {
+        let instructions = parse_formula(formula)?;
+        let variables = instructions.variables();
+        let holds = |formula: &String, time: usize| self.satisfies_at(formula, time, horizon);
+        let period = self.valuation.len() - self.loop_start().unwrap_or(0);
+        let future = time..time.max(horizon) + period;
+
+        match instructions.operators().as_str() {
+            "" => Ok(constant(&variables[0]).unwrap_or_else(|| self.valuation[self.state(time)].contains(&variables[0]))),
+            "¬" => Ok(!holds(&variables[0], time)?),
+            "⋀" => Ok(holds(&variables[0], time)? && holds(&variables[1], time)?),
+            "⋁" => Ok(holds(&variables[0], time)? || holds(&variables[1], time)?),
+            "⊃" => Ok(!holds(&variables[0], time)? || holds(&variables[1], time)?),
+            "≡" => Ok(holds(&variables[0], time)? == holds(&variables[1], time)?),
+            "⊕" => Ok(holds(&variables[0], time)? != holds(&variables[1], time)?),
+            "X" => holds(&variables[0], time + 1),
+            "Y" => Ok(time > 0 && holds(&variables[0], time - 1)?),
+            "Z" => Ok(time == 0 || holds(&variables[0], time - 1)?),
+            "G" => {
+                for t in future {
+                    if !holds(&variables[0], t)? { return Ok(false); }
+                }
+                Ok(true)
+            },
+            "F" => {
+                for t in future {
+                    if holds(&variables[0], t)? { return Ok(true); }
+                }
+                Ok(false)
+            },
+            "H" => {
+                for t in 0..=time {
+                    if !holds(&variables[0], t)? { return Ok(false); }
+                }
+                Ok(true)
+            },
+            "P" => {
+                for t in 0..=time {
+                    if holds(&variables[0], t)? { return Ok(true); }
+                }
+                Ok(false)
+            },
+            "U" => {
+                for t in future {
+                    if holds(&variables[1], t)? { return Ok(true); }
+                    if !holds(&variables[0], t)? { return Ok(false); }
+                }
+                Ok(false)
+            },
+            "S" => {
+                for t in (0..=time).rev() {
+                    if holds(&variables[1], t)? { return Ok(true); }
+                    if !holds(&variables[0], t)? { return Ok(false); }
+                }
+                Ok(false)
+            },
+            op => Err(format!("No semantics on a trace for {} in {}", op, formula))
+        }
+    }
+
+    /// The world of a trace at `time`.
+    fn state(&self, time: usize) -> usize {
+        let loop_start = self.loop_start().unwrap_or(0);
+        if time < self.valuation.len() {
+            time
+        } else {
+            loop_start + (time - loop_start) % (self.valuation.len() - loop_start)
+        }
+    }
+
+    fn labelled_successors(&self, world: usize, index: &str) -> Result<Vec<usize>, String> {
+        self.worlds.labelled_successors(world, index)
+            .ok_or(format!("No world {} in countermodel", world))
+    }
+
+    /// Checks that the model really is a countermodel: every premise true and
+    /// the conclusion false at the root world, on a frame of its logic.
+    pub fn verify(&self, premises: &[String], conclusion: Option<&String>) -> Result<(), String> {
+        for premise in premises {
+            if !self.satisfies(premise, 0)? {
+                return Err(format!("Countermodel check failed: premise {} is false at w0\n{}", premise, self));
+            }
+        }
+        if let Some(conclusion) = conclusion {
+            if self.satisfies(conclusion, 0)? {
+                return Err(format!("Countermodel check failed: conclusion {} is true at w0\n{}", conclusion, self));
+            }
+        }
+        let properties = FrameProperties::check(&self.worlds);
+        for property in self.frame.iter() {
+            if let Some(witness) = properties.witness(*property) {
+                return Err(format!("Countermodel check failed: frame is not {} at {}\n{}", property, witness, self));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The value of ⊤ or ⊥, the same at every world.
+fn constant(atom: &str) -> Option<bool> {
+    match atom {
+        VERUM => Some(true),
+        FALSUM => Some(false),
+        _ => None
+    }
+}
+
+/// Sets of worlds as `{w0, w1}`, joined by `separator`.
+fn world_sets(sets: &[BTreeSet<usize>], separator: &str) -> String {
+    sets.iter()
+        .map(|set| format!("{{{}}}", set.iter().map(|w| format!("w{}", w)).collect::<Vec<String>>().join(", ")))
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+impl fmt::Display for Countermodel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Semantics::FirstDegree { atoms, false_atoms } = &self.semantics {
+            let values: Vec<String> = atoms.iter()
+                .map(|atom| {
+                    let value = match (self.valuation[0].contains(atom), false_atoms.contains(atom)) {
+                        (true, false) => "1",
+                        (false, true) => "0",
+                        (true, true) => "b",
+                        (false, false) => "n"
+                    };
+                    format!("{} = {}", atom, value)
+                })
+                .collect();
+            return writeln!(f, "w0: {}", values.join(", "));
+        }
+        let domains: Vec<&BTreeSet<String>> = self.worlds.node_ids().into_iter()
+            .map(|w| self.worlds.get_node(w).unwrap().domain())
+            .collect();
+        let names = |domain: &BTreeSet<String>| domain.iter().cloned().collect::<Vec<String>>().join(", ");
+        if domains.iter().all(|domain| *domain == domains[0]) {
+            if !domains[0].is_empty() {
+                writeln!(f, "D = {{{}}}", names(domains[0]))?;
+            }
+        } else {
+            for (w, domain) in domains.iter().enumerate() {
+                writeln!(f, "D(w{}) = {{{}}}", w, names(domain))?;
+            }
+        }
+        for w in self.worlds.node_ids() {
+            let mut atoms: Vec<&String> = self.valuation[w].iter().collect();
+            atoms.sort();
+            if self.loop_start().is_some() {
+                let next = self.worlds.adj_to(w).unwrap().into_iter().next().unwrap();
+                let atoms: Vec<&str> = atoms.iter().map(|a| a.as_str()).collect();
+                writeln!(f, "w{}: {{{}}} → w{}", w, atoms.join(", "), next)?;
+                continue;
+            }
+            let ordering = match (self.spheres_around(w), self.preferences(w)) {
+                (Some(spheres), _) => Some(("spheres", world_sets(&spheres, " ⊂ "))),
+                (_, Some(classes)) => Some(("prefers", world_sets(&classes, " ≻ "))),
+                _ => None
+            };
+            if let Some((name, sets)) = ordering {
+                let atoms: Vec<&str> = atoms.iter().map(|a| a.as_str()).collect();
+                writeln!(f, "w{}: {{{}}} {} {}", w, atoms.join(", "), name, sets)?;
+                continue;
+            }
+            let successors = |label: &str| self.worlds.labelled_successors(w, label).unwrap().iter()
+                .map(|s| format!("w{}", s))
+                .collect::<Vec<String>>()
+                .join(", ");
+            let label = self.name(w).map_or(String::new(), |name| format!(" ({})", name));
+            write!(
+                f,
+                "w{}{}: {{{}}} {} {{{}}}",
+                w,
+                label,
+                atoms.iter().filter(|a| Some(**a) != self.name(w)).map(|a| a.as_str()).collect::<Vec<&str>>().join(", "),
+                if self.is_intuitionistic() { "≤" } else { "R" },
+                successors("")
+            )?;
+            for label in self.worlds.labels() {
+                write!(f, " R_{} {{{}}}", label, successors(label))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_worlds() -> Countermodel {
+        let mut worlds = Graph::<World>::new(2);
+        worlds.add_edge(0, 1);
+        let valuation = vec![
+            HashSet::from(["p".to_string()]),
+            HashSet::from(["q".to_string()])
+        ];
+        Countermodel::new(worlds, valuation)
+    }
+
+    #[test]
+    fn satisfaction() {
+        let model = two_worlds();
+        assert!(model.satisfies("p ⋀ ¬q", 0).unwrap());
+        assert!(model.satisfies("◻q", 0).unwrap());
+        assert!(!model.satisfies("◇p", 0).unwrap());
+        assert!(model.satisfies("◻p", 1).unwrap());
+        assert!(model.satisfies("p ⊃ ◇q", 0).unwrap());
+        assert!(model.satisfies("p ⊃ (", 0).is_err());
+    }
+
+    #[test]
+    fn indexed() {
+        let mut model = two_worlds();
+        model.worlds.add_labelled_edge(1, 0, "a");
+        assert!(model.satisfies("[a]p", 1).unwrap());
+        assert!(model.satisfies("<a>p ⋀ ¬<b>p", 1).unwrap());
+        assert!(model.satisfies("[a]q ⋀ ◻q", 0).unwrap());
+        assert_eq!("w0: {p} R {w1} R_a {}\nw1: {q} R {} R_a {w0}\n", model.to_string());
+    }
+
+    #[test]
+    fn groups() {
+        // w0 -a-> w1 -b-> w2, and w0 -b-> w1
+        let mut worlds = Graph::<World>::new(3);
+        worlds.add_labelled_edge(0, 1, "K_a");
+        worlds.add_labelled_edge(0, 1, "K_b");
+        worlds.add_labelled_edge(1, 2, "K_b");
+        let valuation = vec![
+            HashSet::from(["p".to_string()]),
+            HashSet::from(["p".to_string(), "q".to_string()]),
+            HashSet::from(["q".to_string()])
+        ];
+        let model = Countermodel::new(worlds, valuation);
+        assert!(model.satisfies("E_{a,b} (p ⋀ q)", 0).unwrap());
+        assert!(!model.satisfies("E_{a,b} E_{a,b} p", 0).unwrap());
+        assert!(model.satisfies("D_{a,b} q ⋀ E_a E_b q", 0).unwrap());
+        assert!(model.satisfies("C_{a,b} q", 1).unwrap());
+        assert!(!model.satisfies("C_{a,b} q", 0).unwrap());
+        assert!(model.satisfies("C_a p", 0).unwrap());
+        assert!(model.satisfies("D_{a,b} ¬q", 2).unwrap());
+    }
+
+    #[test]
+    fn traces() {
+        // p, then q and r alternating forever
+        let valuation = vec![
+            HashSet::from(["p".to_string()]),
+            HashSet::from(["q".to_string()]),
+            HashSet::from(["r".to_string()])
+        ];
+        let model = Countermodel::trace(valuation, 1);
+        assert_eq!(Some(1), model.loop_start());
+        assert!(model.satisfies("p ⋀ X q ⋀ X X r ⋀ X X X q", 0).unwrap());
+        assert!(model.satisfies("G F q ⋀ G F r ⋀ F G ¬p", 0).unwrap());
+        assert!(!model.satisfies("F G q", 0).unwrap());
+        assert!(model.satisfies("p U q ⋀ ¬(p U r)", 0).unwrap());
+        assert!(model.satisfies("X (¬Y q ⋀ Y p ⋀ P p ⋀ ¬H q)", 0).unwrap());
+        assert!(model.satisfies("¬Y p ⋀ Z ¬p ⋀ H p", 0).unwrap());
+        // the second visit to w1 comes after r, not p
+        assert!(model.satisfies("X X X (Y r ⋀ (¬p S r) ⋀ ¬(q S p))", 0).unwrap());
+        assert!(model.satisfies("G (r ⊃ X q)", 0).unwrap());
+        assert!(model.satisfies("◻p", 0).is_err());
+        assert_eq!("w0: {p} → w1\nw1: {q} → w2\nw2: {r} → w1\n", model.to_string());
+    }
+
+    #[test]
+    fn intuitionistic() {
+        // w0 ≤ w1 ≤ w2 with p from w1 and q at w2
+        let mut worlds = Graph::<World>::new(3);
+        worlds.add_edge(0, 1);
+        worlds.add_edge(1, 2);
+        let valuation = vec![
+            HashSet::new(),
+            HashSet::from(["p".to_string()]),
+            HashSet::from(["p".to_string(), "q".to_string()])
+        ];
+        let model = Countermodel::intuitionistic(worlds, valuation);
+        assert!(model.is_intuitionistic());
+        assert!(!model.satisfies("p ⋁ ¬p", 0).unwrap());
+        assert!(model.satisfies("¬¬p", 0).unwrap());
+        assert!(!model.satisfies("¬¬p ⊃ p", 0).unwrap());
+        assert!(!model.satisfies("p ⊃ q", 0).unwrap());
+        assert!(model.satisfies("p ⊃ q", 2).unwrap());
+        assert!(model.satisfies("◻p", 0).is_err());
+        assert_eq!("w0: {} ≤ {w0, w1, w2}\nw1: {p} ≤ {w1, w2}\nw2: {p, q} ≤ {w2}\n", model.to_string());
+    }
+
+    #[test]
+    fn first_degree() {
+        // p both, q neither, r true, s false
+        let atoms = BTreeSet::from(["p", "q", "r", "s"].map(String::from));
+        let true_atoms = HashSet::from(["p", "r"].map(String::from));
+        let false_atoms = HashSet::from(["p", "s"].map(String::from));
+        let model = Countermodel::first_degree(atoms, true_atoms, false_atoms);
+        assert!(model.satisfies("p ⋀ ¬p", 0).unwrap());
+        assert!(!model.satisfies("q ⋁ ¬q", 0).unwrap());
+        assert!(model.satisfies("¬s ⋀ (s ⊃ q)", 0).unwrap());
+        assert!(!model.satisfies("r ⊃ s", 0).unwrap());
+        assert!(model.satisfies("¬(r ⊃ s) ⋀ ¬¬(p ⋁ q)", 0).unwrap());
+        assert!(model.satisfies("◇p", 0).is_err());
+        assert_eq!("w0: p = b, q = n, r = 1, s = 0\n", model.to_string());
+    }
+
+    #[test]
+    fn spheres() {
+        let valuation = vec![HashSet::new(), HashSet::from(["p", "q"].map(String::from)), HashSet::from(["p".to_string()])];
+        let distances = vec![
+            BTreeMap::from([(0, 0), (1, 1), (2, 2)]),
+            BTreeMap::from([(1, 0)]),
+            BTreeMap::from([(2, 0)])
+        ];
+        let model = Countermodel::spheres(valuation.clone(), distances);
+        assert!(model.satisfies("p □→ q", 0).unwrap());
+        assert!(model.satisfies("q □→ p", 0).unwrap());
+        assert!(!model.satisfies("¬q □→ p", 0).unwrap());
+        assert!(model.satisfies("r □→ ⊥", 0).unwrap());
+        assert!(model.satisfies("◇(p ⋀ ¬q) ⋀ ¬(p ⥽ q) ⋀ (p ⥽ p)", 0).unwrap());
+        assert!(model.satisfies("◻(p □→ q) ⋁ ◻(p □→ ¬q)", 0).is_ok());
+        assert_eq!(
+            "w0: {} spheres {w0} ⊂ {w0, w1} ⊂ {w0, w1, w2}\nw1: {p, q} spheres {w1}\nw2: {p} spheres {w2}\n",
+            model.to_string()
+        );
+
+        // the closest p-worlds are w1 and w2, and only w1 is a q-world
+        let tied = Countermodel::spheres(valuation, vec![
+            BTreeMap::from([(0, 0), (1, 1), (2, 1)]),
+            BTreeMap::from([(1, 0)]),
+            BTreeMap::from([(2, 0)])
+        ]);
+        assert!(!tied.satisfies("p □→ q", 0).unwrap());
+        assert!(tied.satisfies("p □→ p ⋁ q", 0).unwrap());
+        assert!(two_worlds().satisfies("p □→ q", 0).is_err());
+    }
+
+    #[test]
+    fn preference() {
+        let valuation = vec![HashSet::new(), HashSet::from(["h", "t"].map(String::from)), HashSet::new()];
+        let ranks = vec![
+            BTreeMap::from([(1, 0), (2, 1)]),
+            BTreeMap::from([(1, 0)]),
+            BTreeMap::from([(2, 0)])
+        ];
+        let model = Countermodel::preference(valuation, ranks);
+        assert!(model.satisfies("◻h ⋀ O(t/h) ⋀ O(¬t/¬h)", 0).unwrap());
+        assert!(!model.satisfies("O(t/¬h)", 0).unwrap());
+        assert!(model.satisfies("¬h ⋀ ◇t ⋀ O(⊥/p)", 0).unwrap());
+        assert_eq!(model.preferences(0).unwrap(), vec![BTreeSet::from([1]), BTreeSet::from([2])]);
+        assert!(model.spheres_around(0).is_none());
+        assert_eq!("w0: {} prefers {w1} ≻ {w2}\nw1: {h, t} prefers {w1}\nw2: {} prefers {w2}\n", model.to_string());
+    }
+
+    #[test]
+    fn quantifiers() {
+        let domain = BTreeSet::from(["a".to_string(), "b".to_string()]);
+        let valuation = vec![HashSet::from(["P(a)".to_string(), "R(a, b)".to_string(), "R(b, b)".to_string()])];
+        let model = Countermodel::new(Graph::<World>::new(1), valuation).with_domain(domain);
+        assert!(model.satisfies("∃x P(x) ⋀ ¬∀x P(x)", 0).unwrap());
+        assert!(model.satisfies("∀x ∃y R(x, y) ⋀ ∃y ∀x R(x, y)", 0).unwrap());
+        assert!(!model.satisfies("∀x (P(x) ⋁ R(x, a))", 0).unwrap());
+        assert_eq!("D = {a, b}\nw0: {P(a), R(a, b), R(b, b)} R {}\n", model.to_string());
+
+        // a exists only at w1, where it is not P
+        let mut worlds = Graph::<World>::new(2);
+        worlds.add_edge(0, 1);
+        worlds.get_node_mut(1).unwrap().set_domain(BTreeSet::from(["a".to_string()]));
+        let model = Countermodel::new(worlds, vec![HashSet::new(), HashSet::new()]);
+        assert!(model.satisfies("∀x ◻P(x) ⋀ ¬◻∀x P(x)", 0).unwrap());
+        assert!(model.satisfies("¬E!(a) ⋀ ◻E!(a)", 0).unwrap());
+        assert_eq!("D(w0) = {}\nD(w1) = {a}\nw0: {} R {w1}\nw1: {} R {}\n", model.to_string());
+    }
+
+    #[test]
+    fn nominals() {
+        // i names w1, which sees itself, and w0 is named by n0 alone
+        let mut worlds = Graph::<World>::new(2);
+        worlds.add_edge(0, 1);
+        worlds.add_edge(1, 1);
+        worlds.get_node_mut(0).unwrap().set_name(Some("n0".to_string()));
+        worlds.get_node_mut(1).unwrap().set_name(Some("i".to_string()));
+        let valuation = vec![HashSet::new(), HashSet::from(["i".to_string(), "p".to_string()])];
+        let model = Countermodel::new(worlds, valuation);
+        assert!(model.satisfies("@_i p ⋀ ◇i ⋀ ¬i", 0).unwrap());
+        assert!(model.satisfies("@_n0 ◻i", 1).unwrap());
+        assert!(model.satisfies("↓x.◻¬x ⋀ ◇↓x.◇x", 0).unwrap());
+        assert!(model.satisfies("@_j p", 0).is_err());
+        assert_eq!("w0 (n0): {} R {w1}\nw1 (i): {p} R {w1}\n", model.to_string());
+        assert!(two_worlds().satisfies("↓x.x", 0).is_err());
+    }
+
+    #[test]
+    fn verification() {
+        let model = two_worlds();
+        let premises = vec!["p".to_string(), "◇q".to_string()];
+        assert!(model.verify(&premises, Some(&"q".to_string())).is_ok());
+        assert!(model.verify(&premises, Some(&"◻q".to_string())).is_err());
+        assert!(model.verify(&["q".to_string()], None).is_err());
+
+        // the same model, but not on a reflexive frame
+        let model = two_worlds().with_frame(vec![FrameProperty::Serial, FrameProperty::Reflexive]);
+        let err = model.verify(&premises, Some(&"q".to_string())).unwrap_err();
+        assert!(err.starts_with("Countermodel check failed: frame is not serial at w1"), "{}", err);
+    }
+}
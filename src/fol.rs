@@ -0,0 +1,170 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Term {
+    Var(String),
+    Const(String)
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Term::Var(name) | Term::Const(name) => write!(f, "{}", name)
+        }
+    }
+}
+
+/// A first-order formula.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Fol {
+    Verum,
+    Falsum,
+    Pred(String, Vec<Term>),
+    Not(Box<Fol>),
+    And(Box<Fol>, Box<Fol>),
+    Or(Box<Fol>, Box<Fol>),
+    Implies(Box<Fol>, Box<Fol>),
+    Forall(String, Box<Fol>),
+    Exists(String, Box<Fol>)
+}
+
+impl Fol {
+    pub fn pred(name: &str, args: &[Term]) -> Fol {
+        Fol::Pred(name.to_string(), args.to_vec())
+    }
+
+    /// Identity of two terms, written infix.
+    pub fn equals(a: &Term, b: &Term) -> Fol {
+        Fol::pred("=", &[a.clone(), b.clone()])
+    }
+
+    pub fn negated(self) -> Fol {
+        Fol::Not(Box::new(self))
+    }
+
+    pub fn and(self, other: Fol) -> Fol {
+        Fol::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Fol) -> Fol {
+        Fol::Or(Box::new(self), Box::new(other))
+    }
+
+    pub fn implies(self, other: Fol) -> Fol {
+        Fol::Implies(Box::new(self), Box::new(other))
+    }
+
+    pub fn forall(var: &str, body: Fol) -> Fol {
+        Fol::Forall(var.to_string(), Box::new(body))
+    }
+
+    pub fn exists(var: &str, body: Fol) -> Fol {
+        Fol::Exists(var.to_string(), Box::new(body))
+    }
+
+    fn is_binary(&self) -> bool {
+        matches!(self, Fol::And(..) | Fol::Or(..) | Fol::Implies(..))
+    }
+
+    /// Renders the formula in TPTP FOF syntax. Variables are upper-cased and
+    /// predicate and constant names turned into TPTP lower words.
+    pub fn to_tptp(&self) -> String {
+        fn term(t: &Term) -> String {
+            match t {
+                Term::Var(name) => name.to_uppercase(),
+                Term::Const(name) => tptp_name(name)
+            }
+        }
+        fn wrap(formula: &Fol) -> String {
+            let infix = matches!(formula, Fol::Pred(name, _) if name == "=");
+            if formula.is_binary() || infix {
+                format!("({})", formula.to_tptp())
+            } else {
+                formula.to_tptp()
+            }
+        }
+
+        match self {
+            Fol::Verum => "$true".to_string(),
+            Fol::Falsum => "$false".to_string(),
+            Fol::Pred(name, args) if name == "=" => format!("{} = {}", term(&args[0]), term(&args[1])),
+            Fol::Pred(name, args) if args.is_empty() => tptp_name(name),
+            Fol::Pred(name, args) => format!(
+                "{}({})",
+                tptp_name(name),
+                args.iter().map(term).collect::<Vec<String>>().join(",")
+            ),
+            Fol::Not(a) => format!("~ {}", wrap(a)),
+            Fol::And(a, b) => format!("{} & {}", wrap(a), wrap(b)),
+            Fol::Or(a, b) => format!("{} | {}", wrap(a), wrap(b)),
+            Fol::Implies(a, b) => format!("{} => {}", wrap(a), wrap(b)),
+            Fol::Forall(var, body) => format!("! [{}] : {}", var.to_uppercase(), wrap(body)),
+            Fol::Exists(var, body) => format!("? [{}] : {}", var.to_uppercase(), wrap(body))
+        }
+    }
+}
+
+/// Makes a TPTP lower word: names that already are one are kept, anything
+/// else gets an `a_` prefix with other characters replaced by `_`.
+pub fn tptp_name(name: &str) -> String {
+    let is_word = name.chars().next().is_some_and(|ch| ch.is_ascii_lowercase())
+        && name.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_');
+    if is_word {
+        name.to_string()
+    } else {
+        let sanitized: String = name.chars()
+            .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+            .collect();
+        format!("a_{}", sanitized)
+    }
+}
+
+impl fmt::Display for Fol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn wrap(formula: &Fol) -> String {
+            if formula.is_binary() {
+                format!("({})", formula)
+            } else {
+                formula.to_string()
+            }
+        }
+
+        match self {
+            Fol::Verum => write!(f, "⊤"),
+            Fol::Falsum => write!(f, "⊥"),
+            Fol::Pred(name, args) if name == "=" => write!(f, "{} = {}", args[0], args[1]),
+            Fol::Pred(name, args) if args.is_empty() => write!(f, "{}", name),
+            Fol::Pred(name, args) => write!(
+                f,
+                "{}({})",
+                name,
+                args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>().join(", ")
+            ),
+            Fol::Not(a) => write!(f, "¬{}", wrap(a)),
+            Fol::And(a, b) => write!(f, "{} ⋀ {}", wrap(a), wrap(b)),
+            Fol::Or(a, b) => write!(f, "{} ⋁ {}", wrap(a), wrap(b)),
+            Fol::Implies(a, b) => write!(f, "{} ⊃ {}", wrap(a), wrap(b)),
+            Fol::Forall(var, body) => write!(f, "∀{}{}", var, wrap(body)),
+            Fol::Exists(var, body) => write!(f, "∃{}{}", var, wrap(body))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendering() {
+        let x = Term::Var("x".to_string());
+        let formula = Fol::forall("x", Fol::pred("R", &[x.clone(), x.clone()]).implies(Fol::pred("p", &[x])));
+        assert_eq!("∀x(R(x, x) ⊃ p(x))", formula.to_string());
+        assert_eq!("! [X] : (a_R(X,X) => p(X))", formula.to_tptp());
+        assert_eq!("a__1", tptp_name("◻1"));
+
+        let y = Term::Const("w0".to_string());
+        let identity = Fol::equals(&Term::Var("x".to_string()), &y).negated();
+        assert_eq!("¬x = w0", identity.to_string());
+        assert_eq!("~ (X = w0)", identity.to_tptp());
+    }
+}
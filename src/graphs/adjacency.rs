@@ -0,0 +1,271 @@
+use std::collections::HashSet;
+
+/// Storage for the edges of a `Graph`, indexed directly by vertex id.
+pub trait Adjacency {
+    type Neighbours<'a>: Iterator<Item = usize> where Self: 'a;
+
+    fn with_vertices(n: usize) -> Self;
+    fn vertices(&self) -> usize;
+    /// Adds an isolated vertex and returns its id.
+    fn add_vertex(&mut self) -> usize;
+    fn add_edge(&mut self, v: usize, w: usize);
+    fn has_edge(&self, v: usize, w: usize) -> bool;
+    fn neighbours(&self, v: usize) -> Self::Neighbours<'_>;
+    fn out_degree(&self, v: usize) -> usize;
+}
+
+/// Sparse adjacency: one hash set of successors per vertex.
+#[derive(Debug, Clone, Default)]
+pub struct AdjacencyList {
+    successors: Vec<HashSet<usize>>
+}
+
+impl AdjacencyList {
+    pub fn successors(&self, v: usize) -> &HashSet<usize> {
+        &self.successors[v]
+    }
+}
+
+impl Adjacency for AdjacencyList {
+    type Neighbours<'a> = std::iter::Copied<std::collections::hash_set::Iter<'a, usize>>;
+
+    fn with_vertices(n: usize) -> Self {
+        AdjacencyList { successors: vec![HashSet::new(); n] }
+    }
+
+    fn vertices(&self) -> usize {
+        self.successors.len()
+    }
+
+    fn add_vertex(&mut self) -> usize {
+        self.successors.push(HashSet::new());
+        self.successors.len() - 1
+    }
+
+    fn add_edge(&mut self, v: usize, w: usize) {
+        if let Some(successors) = self.successors.get_mut(v) {
+            successors.insert(w);
+        }
+    }
+
+    fn has_edge(&self, v: usize, w: usize) -> bool {
+        self.successors.get(v).is_some_and(|successors| successors.contains(&w))
+    }
+
+    fn neighbours(&self, v: usize) -> Self::Neighbours<'_> {
+        self.successors[v].iter().copied()
+    }
+
+    fn out_degree(&self, v: usize) -> usize {
+        self.successors[v].len()
+    }
+}
+
+const WORD: usize = u64::BITS as usize;
+
+/// Dense adjacency: an n×n bit matrix, one row of `u64` words per vertex.
+/// Closures work a whole row at a time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BitMatrix {
+    n: usize,
+    words: usize,
+    rows: Vec<Vec<u64>>
+}
+
+impl BitMatrix {
+    pub fn row(&self, v: usize) -> &[u64] {
+        &self.rows[v]
+    }
+
+    /// Row `v` |= row `w`.
+    fn union_row(&mut self, v: usize, w: usize) {
+        if v == w {
+            return;
+        }
+        let (target, source) = if v < w {
+            let (low, high) = self.rows.split_at_mut(w);
+            (&mut low[v], &high[0])
+        } else {
+            let (low, high) = self.rows.split_at_mut(v);
+            (&mut high[0], &low[w])
+        };
+        for (t, s) in target.iter_mut().zip(source) {
+            *t |= *s;
+        }
+    }
+
+    pub fn reflexive_closure(&mut self) {
+        for v in 0..self.n {
+            self.add_edge(v, v);
+        }
+    }
+
+    pub fn symmetric_closure(&mut self) {
+        for v in 0..self.n {
+            for w in self.neighbours(v).collect::<Vec<usize>>() {
+                self.add_edge(w, v);
+            }
+        }
+    }
+
+    /// Warshall's algorithm: for each k, every row reaching k takes on row k.
+    pub fn transitive_closure(&mut self) {
+        for k in 0..self.n {
+            for v in 0..self.n {
+                if self.has_edge(v, k) {
+                    self.union_row(v, k);
+                }
+            }
+        }
+    }
+
+    /// Rxy ⋀ Rxz ⊃ Ryz: every successor of x takes on row x.
+    pub fn euclidean_closure(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for x in 0..self.n {
+                for y in self.neighbours(x).collect::<Vec<usize>>() {
+                    let before = self.out_degree(y);
+                    self.union_row(y, x);
+                    if y == x {
+                        continue;
+                    }
+                    changed |= self.out_degree(y) != before;
+                }
+            }
+        }
+    }
+
+    /// A world with nowhere to go can see itself.
+    pub fn serial_completion(&mut self) {
+        for x in 0..self.n {
+            if self.out_degree(x) == 0 {
+                self.add_edge(x, x);
+            }
+        }
+    }
+}
+
+impl Adjacency for BitMatrix {
+    type Neighbours<'a> = Bits<'a>;
+
+    fn with_vertices(n: usize) -> Self {
+        let words = n.div_ceil(WORD).max(1);
+        BitMatrix { n, words, rows: vec![vec![0; words]; n] }
+    }
+
+    fn vertices(&self) -> usize {
+        self.n
+    }
+
+    fn add_vertex(&mut self) -> usize {
+        if self.n + 1 > self.words * WORD {
+            self.words += 1;
+            for row in self.rows.iter_mut() {
+                row.push(0);
+            }
+        }
+        self.rows.push(vec![0; self.words]);
+        self.n += 1;
+        self.n - 1
+    }
+
+    fn add_edge(&mut self, v: usize, w: usize) {
+        if v < self.n && w < self.n {
+            self.rows[v][w / WORD] |= 1 << (w % WORD);
+        }
+    }
+
+    fn has_edge(&self, v: usize, w: usize) -> bool {
+        v < self.n && w < self.n && self.rows[v][w / WORD] & (1 << (w % WORD)) != 0
+    }
+
+    fn neighbours(&self, v: usize) -> Self::Neighbours<'_> {
+        Bits { words: &self.rows[v], idx: 0, current: self.rows[v][0] }
+    }
+
+    fn out_degree(&self, v: usize) -> usize {
+        self.rows[v].iter().map(|word| word.count_ones() as usize).sum()
+    }
+}
+
+/// Set bits of a matrix row, in increasing order.
+pub struct Bits<'a> {
+    words: &'a [u64],
+    idx: usize,
+    current: u64
+}
+
+impl Iterator for Bits<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.idx += 1;
+            self.current = *self.words.get(self.idx)?;
+        }
+        let bit = self.current.trailing_zeros() as usize;
+        self.current &= self.current - 1;
+        Some(self.idx * WORD + bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_matrix() {
+        let mut matrix = BitMatrix::with_vertices(3);
+        matrix.add_edge(0, 1);
+        matrix.add_edge(1, 2);
+        assert!(matrix.has_edge(0, 1));
+        assert!(!matrix.has_edge(1, 0));
+
+        // growing past one word keeps existing edges
+        for _ in 0..70 {
+            matrix.add_vertex();
+        }
+        matrix.add_edge(2, 72);
+        assert_eq!(73, matrix.vertices());
+        assert_eq!(vec![72], matrix.neighbours(2).collect::<Vec<usize>>());
+
+        matrix.transitive_closure();
+        assert_eq!(vec![1, 2, 72], matrix.neighbours(0).collect::<Vec<usize>>());
+        assert_eq!(0, matrix.out_degree(72));
+
+        matrix.symmetric_closure();
+        assert!(matrix.has_edge(72, 0));
+        matrix.reflexive_closure();
+        assert_eq!(1, matrix.out_degree(40));
+    }
+
+    #[test]
+    fn euclidean() {
+        // 0 → 1, 0 → 2 gives 1 ⟷ 2 and loops on 1 and 2, but not on 0
+        let mut matrix = BitMatrix::with_vertices(3);
+        matrix.add_edge(0, 1);
+        matrix.add_edge(0, 2);
+        matrix.euclidean_closure();
+        assert_eq!(vec![1, 2], matrix.neighbours(1).collect::<Vec<usize>>());
+        assert_eq!(vec![1, 2], matrix.neighbours(2).collect::<Vec<usize>>());
+        assert!(!matrix.has_edge(0, 0));
+    }
+
+    #[test]
+    fn backends_agree() {
+        let mut list = AdjacencyList::with_vertices(5);
+        let mut matrix = BitMatrix::with_vertices(5);
+        for (v, w) in [(0, 1), (1, 2), (2, 0), (3, 4), (3, 3)] {
+            list.add_edge(v, w);
+            matrix.add_edge(v, w);
+        }
+        for v in 0..5 {
+            let mut from_list: Vec<usize> = list.neighbours(v).collect();
+            from_list.sort();
+            assert_eq!(from_list, matrix.neighbours(v).collect::<Vec<usize>>());
+            assert_eq!(list.out_degree(v), matrix.out_degree(v));
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::VecDeque;
+use crate::graphs::{Adjacency, AdjacencyList, BitMatrix, Graph};
+
+/// Strongly connected components, numbered in topological order of the
+/// quotient graph: edges between components only go to higher numbers.
+#[derive(Debug, PartialEq)]
+pub struct Components {
+    component: Vec<usize>,
+    members: Vec<Vec<usize>>
+}
+
+impl Components {
+    pub fn count(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn component_of(&self, v: usize) -> Option<usize> {
+        self.component.get(v).copied()
+    }
+
+    pub fn members(&self, component: usize) -> Option<&Vec<usize>> {
+        self.members.get(component)
+    }
+
+    pub fn components(&self) -> &Vec<Vec<usize>> {
+        &self.members
+    }
+
+    pub fn strongly_connected(&self, v: usize, w: usize) -> bool {
+        self.component_of(v).is_some_and(|c| Some(c) == self.component_of(w))
+    }
+}
+
+struct Tarjan {
+    index: Vec<Option<usize>>,
+    lowlink: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    counter: usize
+}
+
+impl Tarjan {
+    fn visit(&mut self, v: usize) {
+        self.index[v] = Some(self.counter);
+        self.lowlink[v] = self.counter;
+        self.counter += 1;
+        self.stack.push(v);
+        self.on_stack[v] = true;
+    }
+
+    /// Pops the component whose root is `v`.
+    fn pop_component(&mut self, v: usize) -> Vec<usize> {
+        let mut members = Vec::new();
+        while let Some(w) = self.stack.pop() {
+            self.on_stack[w] = false;
+            members.push(w);
+            if w == v { break; }
+        }
+        members.sort();
+        members
+    }
+}
+
+impl<T, A: Adjacency> Graph<T, A> {
+    /// Tarjan's algorithm, iterative so deep graphs cannot overflow the stack.
+    pub fn strongly_connected_components(&self) -> Components {
+        let n = self.size();
+        let mut tarjan = Tarjan {
+            index: vec![None; n],
+            lowlink: vec![0; n],
+            on_stack: vec![false; n],
+            stack: Vec::new(),
+            counter: 0
+        };
+        // Tarjan finds components sinks first
+        let mut found: Vec<Vec<usize>> = Vec::new();
+
+        for root in 0..n {
+            if tarjan.index[root].is_some() {
+                continue;
+            }
+            tarjan.visit(root);
+            let mut calls = vec![(root, self.neighbours(root).unwrap())];
+
+            while let Some((v, neighbours)) = calls.last_mut() {
+                let v = *v;
+                match neighbours.next() {
+                    Some(w) => match tarjan.index[w] {
+                        None => {
+                            tarjan.visit(w);
+                            calls.push((w, self.neighbours(w).unwrap()));
+                        },
+                        Some(w_index) if tarjan.on_stack[w] => {
+                            tarjan.lowlink[v] = tarjan.lowlink[v].min(w_index);
+                        },
+                        Some(_) => {}
+                    },
+                    None => {
+                        calls.pop();
+                        if let Some((u, _)) = calls.last() {
+                            tarjan.lowlink[*u] = tarjan.lowlink[*u].min(tarjan.lowlink[v]);
+                        }
+                        if Some(tarjan.lowlink[v]) == tarjan.index[v] {
+                            found.push(tarjan.pop_component(v));
+                        }
+                    }
+                }
+            }
+        }
+
+        found.reverse();
+        let mut component = vec![0; n];
+        for (c, members) in found.iter().enumerate() {
+            for v in members {
+                component[*v] = c;
+            }
+        }
+        Components { component, members: found }
+    }
+
+    /// Some cycle `v0 → v1 → … → v0`, listed without repeating `v0`.
+    pub fn find_cycle(&self) -> Option<Vec<usize>> {
+        if let Some(v) = (0..self.size()).find(|v| self.adjacencies().has_edge(*v, *v)) {
+            return Some(vec![v]);
+        }
+        let components = self.strongly_connected_components();
+        let members = components.components().iter().find(|members| members.len() > 1)?;
+
+        // walk inside the component until a vertex repeats
+        let c = components.component_of(members[0]).unwrap();
+        let mut position: Vec<Option<usize>> = vec![None; self.size()];
+        let mut walk = Vec::new();
+        let mut v = members[0];
+        while position[v].is_none() {
+            position[v] = Some(walk.len());
+            walk.push(v);
+            v = self.neighbours(v).unwrap()
+                .find(|w| components.component_of(*w) == Some(c))
+                .expect("Every vertex of a non-trivial component has a successor in it");
+        }
+        Some(walk.split_off(position[v].unwrap()))
+    }
+
+    pub fn has_cycle(&self) -> bool {
+        self.find_cycle().is_some()
+    }
+
+    /// Kahn's algorithm. None if the graph has a cycle.
+    pub fn topological_order(&self) -> Option<Vec<usize>> {
+        let n = self.size();
+        let mut in_degree = vec![0; n];
+        for v in 0..n {
+            for w in self.neighbours(v).unwrap() {
+                in_degree[w] += 1;
+            }
+        }
+
+        let mut queue: VecDeque<usize> = (0..n).filter(|v| in_degree[*v] == 0).collect();
+        let mut order = Vec::with_capacity(n);
+        while let Some(v) = queue.pop_front() {
+            order.push(v);
+            for w in self.neighbours(v).unwrap() {
+                in_degree[w] -= 1;
+                if in_degree[w] == 0 {
+                    queue.push_back(w);
+                }
+            }
+        }
+
+        if order.len() == n {
+            Some(order)
+        } else {
+            None
+        }
+    }
+
+    /// Row v has bit w set when w can be reached from v in one or more steps.
+    pub fn reachability(&self) -> BitMatrix {
+        let mut matrix = self.to_matrix();
+        matrix.transitive_closure();
+        matrix
+    }
+
+    /// Graph with one node per strongly connected component, holding its
+    /// members, and an edge wherever an edge joins two components.
+    pub fn quotient(&self) -> Graph<Vec<usize>> {
+        let components = self.strongly_connected_components();
+        let mut quotient = Graph::<Vec<usize>, AdjacencyList>::with_nodes(components.components().clone());
+        for v in 0..self.size() {
+            let c = components.component_of(v).unwrap();
+            for w in self.neighbours(v).unwrap() {
+                let d = components.component_of(w).unwrap();
+                if c != d {
+                    quotient.add_edge(c, d);
+                }
+            }
+        }
+        quotient
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphs::test_graph as graph;
+
+    #[test]
+    fn components() {
+        // {0, 1, 2} → {3, 4} → {5}
+        let g = graph(6, &[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 3), (4, 5)]);
+        let components = g.strongly_connected_components();
+        assert_eq!(&vec![vec![0, 1, 2], vec![3, 4], vec![5]], components.components());
+        assert!(components.strongly_connected(1, 2));
+        assert!(!components.strongly_connected(2, 3));
+
+        let quotient = g.quotient();
+        assert_eq!(3, quotient.size());
+        assert_eq!(Some(&vec![3, 4]), quotient.get_node(1));
+        assert!(quotient.adj_test(0, 1).unwrap());
+        assert!(!quotient.adj_test(0, 2).unwrap());
+        assert_eq!(Some(vec![0, 1, 2]), quotient.topological_order());
+
+        let reachable = g.reachability();
+        assert!(reachable.has_edge(0, 0));
+        assert!(reachable.has_edge(1, 5));
+        assert!(!reachable.has_edge(5, 5));
+        assert!(!reachable.has_edge(3, 2));
+    }
+
+    #[test]
+    fn cycles() {
+        let dag = graph(5, &[(0, 1), (0, 2), (1, 3), (2, 3), (3, 4)]);
+        assert!(!dag.has_cycle());
+        let order = dag.topological_order().unwrap();
+        let position = |v: usize| order.iter().position(|w| *w == v).unwrap();
+        assert!(position(0) < position(1) && position(1) < position(3) && position(2) < position(3));
+
+        let cyclic = graph(4, &[(0, 1), (1, 2), (2, 3), (3, 1)]);
+        assert_eq!(None, cyclic.topological_order());
+        let cycle = cyclic.find_cycle().unwrap();
+        assert_eq!(3, cycle.len());
+        for (idx, v) in cycle.iter().enumerate() {
+            assert!(cyclic.adj_test(*v, cycle[(idx + 1) % cycle.len()]).unwrap());
+        }
+
+        assert_eq!(Some(vec![2]), graph(3, &[(0, 1), (2, 2)]).find_cycle());
+    }
+
+    #[test]
+    fn deep_chain() {
+        let edges: Vec<(usize, usize)> = (0..100_000).map(|v| (v, (v + 1) % 100_000)).collect();
+        let ring = graph(100_000, &edges);
+        assert_eq!(1, ring.strongly_connected_components().count());
+    }
+}
@@ -0,0 +1,218 @@
+use std::fmt;
+use crate::graphs::{Adjacency, BitMatrix, Graph};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum FrameProperty {
+    Reflexive,
+    Irreflexive,
+    Symmetric,
+    Antisymmetric,
+    Transitive,
+    Euclidean,
+    Serial,
+    Dense,
+    // confluent: Rxy ⋀ Rxz implies some w with Ryw ⋀ Rzw
+    Convergent,
+    // Rxy ⋀ Rxz implies Ryz, y = z or Rzy
+    Connected,
+    // Rxy ⋀ Rxz implies y = z
+    PartiallyFunctional
+}
+
+impl FrameProperty {
+    pub const ALL: [FrameProperty; 11] = [
+        FrameProperty::Reflexive,
+        FrameProperty::Irreflexive,
+        FrameProperty::Symmetric,
+        FrameProperty::Antisymmetric,
+        FrameProperty::Transitive,
+        FrameProperty::Euclidean,
+        FrameProperty::Serial,
+        FrameProperty::Dense,
+        FrameProperty::Convergent,
+        FrameProperty::Connected,
+        FrameProperty::PartiallyFunctional
+    ];
+
+    /// The first counterexample to the property in `matrix`, if any.
+    fn counterexample(&self, matrix: &BitMatrix) -> Option<Witness> {
+        let n = matrix.vertices();
+        let r = |x: usize, y: usize| matrix.has_edge(x, y);
+        let pairs = || (0..n).flat_map(move |x| matrix.neighbours(x).map(move |y| (x, y)));
+        // x, y, z with Rxy and Rxz
+        let forks = || (0..n).flat_map(move |x| {
+            matrix.neighbours(x).flat_map(move |y| matrix.neighbours(x).map(move |z| (x, y, z)))
+        });
+        // x, y, z with Rxy and Ryz
+        let chains = || pairs().flat_map(move |(x, y)| matrix.neighbours(y).map(move |z| (x, y, z)));
+
+        match self {
+            FrameProperty::Reflexive => (0..n).find(|x| !r(*x, *x)).map(Witness::World),
+            FrameProperty::Irreflexive => (0..n).find(|x| r(*x, *x)).map(Witness::World),
+            FrameProperty::Serial => (0..n).find(|x| matrix.out_degree(*x) == 0).map(Witness::World),
+            FrameProperty::Symmetric => pairs()
+                .find(|(x, y)| !r(*y, *x))
+                .map(|(x, y)| Witness::Pair(x, y)),
+            FrameProperty::Antisymmetric => pairs()
+                .find(|(x, y)| x != y && r(*y, *x))
+                .map(|(x, y)| Witness::Pair(x, y)),
+            FrameProperty::Dense => pairs()
+                .find(|(x, z)| !matrix.neighbours(*x).any(|y| r(y, *z)))
+                .map(|(x, z)| Witness::Pair(x, z)),
+            FrameProperty::Transitive => chains()
+                .find(|(x, _, z)| !r(*x, *z))
+                .map(|(x, y, z)| Witness::Triple(x, y, z)),
+            FrameProperty::Euclidean => forks()
+                .find(|(_, y, z)| !r(*y, *z))
+                .map(|(x, y, z)| Witness::Triple(x, y, z)),
+            FrameProperty::Convergent => forks()
+                .find(|(_, y, z)| {
+                    !matrix.row(*y).iter().zip(matrix.row(*z)).any(|(a, b)| a & b != 0)
+                })
+                .map(|(x, y, z)| Witness::Triple(x, y, z)),
+            FrameProperty::Connected => forks()
+                .find(|(_, y, z)| y != z && !r(*y, *z) && !r(*z, *y))
+                .map(|(x, y, z)| Witness::Triple(x, y, z)),
+            FrameProperty::PartiallyFunctional => forks()
+                .find(|(_, y, z)| y != z)
+                .map(|(x, y, z)| Witness::Triple(x, y, z))
+        }
+    }
+}
+
+impl fmt::Display for FrameProperty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FrameProperty::Reflexive => "reflexive",
+            FrameProperty::Irreflexive => "irreflexive",
+            FrameProperty::Symmetric => "symmetric",
+            FrameProperty::Antisymmetric => "antisymmetric",
+            FrameProperty::Transitive => "transitive",
+            FrameProperty::Euclidean => "euclidean",
+            FrameProperty::Serial => "serial",
+            FrameProperty::Dense => "dense",
+            FrameProperty::Convergent => "convergent",
+            FrameProperty::Connected => "connected",
+            FrameProperty::PartiallyFunctional => "partially functional"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Worlds at which a frame property fails, in the order the property's
+/// definition quantifies over them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Witness {
+    World(usize),
+    Pair(usize, usize),
+    Triple(usize, usize, usize)
+}
+
+impl fmt::Display for Witness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Witness::World(x) => write!(f, "w{}", x),
+            Witness::Pair(x, y) => write!(f, "(w{}, w{})", x, y),
+            Witness::Triple(x, y, z) => write!(f, "(w{}, w{}, w{})", x, y, z)
+        }
+    }
+}
+
+/// Which frame properties a graph has, with a witness for each failure.
+#[derive(Debug, PartialEq)]
+pub struct FrameProperties {
+    results: Vec<(FrameProperty, Option<Witness>)>
+}
+
+impl FrameProperties {
+    pub fn check<T, A: Adjacency>(graph: &Graph<T, A>) -> FrameProperties {
+        let matrix = graph.to_matrix();
+        FrameProperties {
+            results: FrameProperty::ALL.iter()
+                .map(|property| (*property, property.counterexample(&matrix)))
+                .collect()
+        }
+    }
+
+    pub fn holds(&self, property: FrameProperty) -> bool {
+        self.witness(property).is_none()
+    }
+
+    pub fn witness(&self, property: FrameProperty) -> Option<Witness> {
+        self.results.iter()
+            .find(|(checked, _)| *checked == property)
+            .and_then(|(_, witness)| *witness)
+    }
+
+    pub fn satisfied(&self) -> Vec<FrameProperty> {
+        self.results.iter()
+            .filter(|(_, witness)| witness.is_none())
+            .map(|(property, _)| *property)
+            .collect()
+    }
+}
+
+impl fmt::Display for FrameProperties {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (property, witness) in self.results.iter() {
+            match witness {
+                None => writeln!(f, "{}: yes", property)?,
+                Some(witness) => writeln!(f, "{}: no, fails at {}", property, witness)?
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphs::test_graph as graph;
+    use crate::modal_config::ModalOptions;
+
+    #[test]
+    fn equivalence() {
+        let mut worlds = graph(3, &[(0, 1), (1, 2)]);
+        worlds.implement_modals(&ModalOptions::new_default());
+        let properties = FrameProperties::check(&worlds);
+        for property in [
+            FrameProperty::Reflexive, FrameProperty::Symmetric, FrameProperty::Transitive,
+            FrameProperty::Euclidean, FrameProperty::Serial, FrameProperty::Dense,
+            FrameProperty::Convergent, FrameProperty::Connected
+        ] {
+            assert!(properties.holds(property), "{}", property);
+        }
+        assert_eq!(Some(Witness::World(0)), properties.witness(FrameProperty::Irreflexive));
+        assert_eq!(Some(Witness::Pair(0, 1)), properties.witness(FrameProperty::Antisymmetric));
+        assert_eq!(Some(Witness::Triple(0, 0, 1)), properties.witness(FrameProperty::PartiallyFunctional));
+    }
+
+    #[test]
+    fn witnesses() {
+        // 0 → 1 → 2, 0 → 3
+        let properties = FrameProperties::check(&graph(4, &[(0, 1), (1, 2), (0, 3)]));
+        assert_eq!(Some(Witness::World(0)), properties.witness(FrameProperty::Reflexive));
+        assert_eq!(Some(Witness::World(2)), properties.witness(FrameProperty::Serial));
+        assert_eq!(Some(Witness::Pair(0, 1)), properties.witness(FrameProperty::Symmetric));
+        assert_eq!(Some(Witness::Pair(0, 1)), properties.witness(FrameProperty::Dense));
+        assert_eq!(Some(Witness::Triple(0, 1, 2)), properties.witness(FrameProperty::Transitive));
+        assert_eq!(Some(Witness::Triple(0, 1, 1)), properties.witness(FrameProperty::Euclidean));
+        assert_eq!(Some(Witness::Triple(0, 1, 3)), properties.witness(FrameProperty::Convergent));
+        assert_eq!(Some(Witness::Triple(0, 1, 3)), properties.witness(FrameProperty::Connected));
+        assert!(properties.holds(FrameProperty::Irreflexive));
+        assert!(properties.holds(FrameProperty::Antisymmetric));
+        assert!(!properties.holds(FrameProperty::PartiallyFunctional));
+
+        // a successor function is partially functional, and a single
+        // loop is dense, convergent and euclidean
+        let properties = FrameProperties::check(&graph(3, &[(0, 1), (1, 2), (2, 2)]));
+        assert!(properties.holds(FrameProperty::PartiallyFunctional));
+        assert!(properties.holds(FrameProperty::Serial));
+        assert!(properties.holds(FrameProperty::Convergent));
+        assert!(!properties.holds(FrameProperty::Dense));
+        assert_eq!(
+            "reflexive: no, fails at w0",
+            properties.to_string().lines().next().unwrap()
+        );
+    }
+}
@@ -1,16 +1,53 @@
-use std::collections::HashSet;
-use crate::graphs::{Formula, FormulaState, World};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+use crate::graphs::{Adjacency, AdjacencyList, BitMatrix, Formula, FormulaState, World};
+use crate::ast::{FALSUM, IDENTITY, VERUM};
+use crate::graphs::node::{Closure, Relation, Sign};
 use crate::graphs::search::GraphSearch;
 use crate::modal_config::ModalOptions;
+use crate::parser::{negate, parse_expr};
 
-pub struct Graph<T> {
-    adjacencies: Vec<HashSet<usize>>,
+pub struct Graph<T, A: Adjacency = AdjacencyList> {
+    adjacencies: A,
+    // further relations over the same nodes, by label; the unlabelled
+    // relation is `adjacencies`
+    labelled: BTreeMap<String, A>,
     nodes: Vec<T>
 }
 
-impl<T> Graph<T> {
+impl<T, A: Adjacency> Graph<T, A> {
+    /// Graph over `nodes` with no edges.
+    pub fn with_nodes(nodes: Vec<T>) -> Graph<T, A> {
+        Graph {
+            adjacencies: A::with_vertices(nodes.len()),
+            labelled: BTreeMap::new(),
+            nodes
+        }
+    }
+
     pub fn adj_to(&self, n: usize) -> Option<HashSet<usize>> {
-        self.adjacencies.iter().nth(n).cloned()
+        Some(self.neighbours(n)?.collect())
+    }
+
+    /// Successors of `n` without copying them out.
+    pub fn neighbours(&self, n: usize) -> Option<A::Neighbours<'_>> {
+        if n < self.size() {
+            Some(self.adjacencies.neighbours(n))
+        } else {
+            None
+        }
+    }
+
+    pub fn out_degree(&self, n: usize) -> Option<usize> {
+        if n < self.size() {
+            Some(self.adjacencies.out_degree(n))
+        } else {
+            None
+        }
+    }
+
+    pub fn adjacencies(&self) -> &A {
+        &self.adjacencies
     }
 
     pub fn size(&self) -> usize {
@@ -18,16 +55,89 @@ impl<T> Graph<T> {
     }
 
     pub fn add_edge(&mut self, v: usize, w: usize) {
-        if let Some(neighbours) = self.adjacencies.iter_mut().nth(v) {
-            if !neighbours.contains(&w) {
-                neighbours.insert(w);
+        self.adjacencies.add_edge(v, w);
+    }
+
+    /// Adds an edge to the relation named `label`, the empty label being
+    /// the unlabelled relation.
+    pub fn add_labelled_edge(&mut self, v: usize, w: usize, label: &str) {
+        if label.is_empty() {
+            self.add_edge(v, w);
+        } else {
+            self.labelled_mut(label).add_edge(v, w);
+        }
+    }
+
+    /// Successors of `n` under the relation named `label`, in increasing order.
+    pub fn labelled_successors(&self, n: usize, label: &str) -> Option<Vec<usize>> {
+        if n >= self.size() {
+            return None;
+        }
+        let mut successors: Vec<usize> = if label.is_empty() {
+            self.adjacencies.neighbours(n).collect()
+        } else {
+            self.labelled.get(label).map_or(Vec::new(), |adjacencies| adjacencies.neighbours(n).collect())
+        };
+        successors.sort();
+        Some(successors)
+    }
+
+    /// Successors of `n` under every one of the relations named by `labels`.
+    pub fn common_successors(&self, n: usize, labels: &[String]) -> Option<Vec<usize>> {
+        let (first, rest) = labels.split_first()?;
+        let mut successors = self.labelled_successors(n, first)?;
+        for label in rest {
+            let others = self.labelled_successors(n, label)?;
+            successors.retain(|w| others.contains(w));
+        }
+        Some(successors)
+    }
+
+    /// Nodes reachable from `n` in zero or more steps of any of the relations
+    /// named by `labels`, in increasing order.
+    pub fn reachable_by_any(&self, n: usize, labels: &[String]) -> Option<Vec<usize>> {
+        if n >= self.size() {
+            return None;
+        }
+        let mut marked = vec![false; self.size()];
+        marked[n] = true;
+        let mut stack = vec![n];
+        while let Some(v) = stack.pop() {
+            for label in labels {
+                for w in self.labelled_successors(v, label)? {
+                    if !marked[w] {
+                        marked[w] = true;
+                        stack.push(w);
+                    }
+                }
             }
         }
+        Some((0..self.size()).filter(|v| marked[*v]).collect())
+    }
+
+    /// Labels of the relations besides the unlabelled one.
+    pub fn labels(&self) -> impl Iterator<Item = &String> {
+        self.labelled.keys()
+    }
+
+    fn labelled_mut(&mut self, label: &str) -> &mut A {
+        let size = self.size();
+        self.labelled.entry(label.to_string()).or_insert_with(|| A::with_vertices(size))
+    }
+
+    fn add_vertex(&mut self) -> usize {
+        for adjacencies in self.labelled.values_mut() {
+            adjacencies.add_vertex();
+        }
+        self.adjacencies.add_vertex()
     }
 
     pub fn adj_test(&self, u: usize, w: usize) -> Option<bool> {
-        Some(self.adjacencies.iter().nth(u)?
-            .contains(&w))
+        if u < self.size() {
+            Some(self.adjacencies.has_edge(u, w))
+        } else {
+            None
+        }
     }
 
     pub fn node_ids(&self) -> Vec<usize> {
@@ -35,89 +145,111 @@ impl<T> Graph<T> {
     }
 
     pub fn get_node_mut(&mut self, id: usize) -> Option<&mut T> {
-        self.nodes.iter_mut().nth(id)
+        self.nodes.get_mut(id)
     }
 
     pub fn get_node(&self, id: usize) -> Option<&T> {
-        self.nodes.iter().nth(id)
+        self.nodes.get(id)
+    }
+
+    /// Copy of the edges as a dense bit matrix.
+    pub fn to_matrix(&self) -> BitMatrix {
+        matrix_of(&self.adjacencies)
     }
 }
 
-impl Graph<World> {
-    pub fn new(n: usize) -> Graph<World> {
-        let mut adjacencies: Vec<HashSet<usize>> = Vec::new(); 
-        let mut nodes: Vec<World> = Vec::with_capacity(n);
-        for i in 0..n {
-            adjacencies.push(HashSet::new());
-            nodes.push(World::new(i));
-        };
+fn matrix_of<A: Adjacency>(adjacencies: &A) -> BitMatrix {
+    let mut matrix = BitMatrix::with_vertices(adjacencies.vertices());
+    for v in 0..adjacencies.vertices() {
+        for w in adjacencies.neighbours(v) {
+            matrix.add_edge(v, w);
+        }
+    }
+    matrix
+}
 
-        Graph {
-            adjacencies,
-            nodes
+/// Closes `adjacencies` under the frame conditions, see `implement_modals`.
+fn close_under<A: Adjacency>(adjacencies: &mut A, config: &ModalOptions) {
+    let mut matrix = matrix_of(adjacencies);
+    loop {
+        let before = matrix.clone();
+        if config.rho() {
+            matrix.reflexive_closure();
+        }
+        if config.sigma() {
+            matrix.symmetric_closure();
+        }
+        if config.tau() {
+            matrix.transitive_closure();
+        }
+        if config.euclid() {
+            matrix.euclidean_closure();
+        }
+        if config.eta() {
+            // extendable: a world with nowhere to go can see itself
+            matrix.serial_completion();
+        }
+        if matrix == before { break; }
+    }
+
+    for v in 0..adjacencies.vertices() {
+        for w in matrix.neighbours(v) {
+            adjacencies.add_edge(v, w);
         }
     }
+}
+
+impl<A: Adjacency> Graph<World, A> {
+    pub fn new(n: usize) -> Graph<World, A> {
+        Graph::with_nodes((0..n).map(World::new).collect())
+    }
 
     pub fn add_world(&mut self) -> usize {
-        let new_idx = self.size() - 1;
+        let new_idx = self.size();
         self.nodes.push(World::new(new_idx));
-        self.adjacencies.push(HashSet::new());
+        self.add_vertex();
         new_idx
     }
 
+    /// Closes the accessibility relation under the frame conditions, working
+    /// on a bit matrix so symmetry and transitivity go a row at a time.
+    /// Conditions that ask for worlds to exist or coincide, convergence,
+    /// connectedness, density and partial functionality, are left to the
+    /// tableau, which adds the worlds and identities they need.
     pub fn implement_modals(&mut self, config: &ModalOptions) {
-        if config.rho() {
-            // reflexive
-            for id in self.node_ids() {
-                self.add_edge(id, id);
-            }
-        }
-        if config.tau() {
-            // transitive
-            let worlds: Vec<usize> = self.node_ids();
-            for w in worlds {
-                if let Some(bfs) = GraphSearch::bfs(self, w).all_marked() {
-                    for w_prime in bfs { self.add_edge(w, w_prime); }
-                }
-            }
-        }
-        if config.sigma() {
-            // symmetrical 
-            let nodes: Vec<usize> = self.node_ids();
-            for w in nodes {
-                let adjs = self.adj_to(w).unwrap();
-                for w_prime in adjs {
-                    self.add_edge(w_prime, w);
-                }
-            }
-        }
-        if config.eta() {
-            // extendable
-            todo!()
+        close_under(&mut self.adjacencies, config);
+    }
+
+    /// `implement_modals` for the relation named `label`.
+    pub fn implement_labelled_modals(&mut self, label: &str, config: &ModalOptions) {
+        if label.is_empty() {
+            self.implement_modals(config);
+        } else {
+            close_under(self.labelled_mut(label), config);
         }
-    }  
+    }
 }
 
 impl Graph<Formula> {
     pub fn new(formulas: Vec<String>) -> Graph<Formula> {
-        fn next_child(node: usize, len: usize) -> HashSet<usize> {
-            let mut output = HashSet::new();
-            let child = node + 1;
-            if child < len {
-                output.insert(child);
-            }
+        Graph::new_signed(formulas.into_iter().map(|formula| (formula, Sign::True)).collect())
+    }
 
-            output
-        }
+    /// A single branch of signed formulas at the root world.
+    pub fn new_signed(formulas: Vec<(String, Sign)>) -> Graph<Formula> {
         let node_count = formulas.len();
 
+        let mut adjacencies = AdjacencyList::with_vertices(node_count);
+        for idx in 1..node_count {
+            adjacencies.add_edge(idx - 1, idx);
+        }
+
         Graph {
             nodes: formulas.into_iter()
-                .map(|formula| Formula::new(formula, 0))
+                .map(|(formula, sign)| Formula::signed(formula, 0, sign))
                 .collect::<Vec<Formula>>(),
-            adjacencies: (0..node_count)
-                .map(|idx| next_child(idx, node_count))
-                .collect::<Vec<HashSet<usize>>>()
+            labelled: BTreeMap::new(),
+            adjacencies
         }
     }
 
@@ -142,43 +274,237 @@ impl Graph<Formula> {
 
     pub fn terminal_unclosed(&self, root: usize) -> Option<Vec<usize>> {
         let search = GraphSearch::bfs(self, root);
-        let terminal_unclosed = search.all_marked()?.iter()
-            .filter(|idx| self.adj_to(**idx).unwrap().is_empty())
-            .filter(|idx| self.get_node(**idx).unwrap().state() != &FormulaState::Closed)
-            .copied()
+        let terminal_unclosed: Vec<usize> = std::iter::once(root)
+            .chain(search.marked())
+            .filter(|idx| self.is_open_terminal(*idx))
             .collect();
-        Some(terminal_unclosed)
+
+        if terminal_unclosed.is_empty() {
+            None
+        } else {
+            Some(terminal_unclosed)
+        }
+    }
+
+    fn is_open_terminal(&self, idx: usize) -> bool {
+        self.adjacencies.out_degree(idx) == 0
+            && self.get_node(idx).unwrap().state() != &FormulaState::Closed
+    }
+
+    /// Leaves of the tableau, closed or not.
+    pub fn terminals(&self) -> Vec<usize> {
+        self.node_ids().into_iter()
+            .filter(|idx| self.adjacencies.out_degree(*idx) == 0)
+            .collect()
     }
 
+    /// Number of nodes on the longest branch. The search from the root
+    /// reaches each node after its parent, whose depth it adds one to.
+    pub fn max_depth(&self) -> usize {
+        if self.size() == 0 {
+            return 0;
+        }
+        let mut depths = vec![0; self.size()];
+        depths[0] = 1;
+        let search = GraphSearch::bfs(self, 0);
+        for idx in search.marked() {
+            let parent = search.path_back(idx).unwrap().nth(1).unwrap();
+            depths[idx] = depths[parent] + 1;
+        }
+        depths.into_iter().max().unwrap()
+    }
+
+    /// Root-to-leaf paths of the open branches, from a single search: the
+    /// tableau is a tree, so the search tree path to a leaf is its branch.
     pub fn unclosed_branches(&self) -> Option<Vec<Vec<usize>>> {
-        let paths = self.terminal_unclosed(0)?.iter()
-            .map(|t_node| GraphSearch::shortest_path(self, 0, *t_node).unwrap())
+        let search = GraphSearch::bfs(self, 0);
+        let paths: Vec<Vec<usize>> = std::iter::once(0)
+            .chain(search.marked())
+            .filter(|idx| self.is_open_terminal(*idx))
+            .map(|t_node| search.path_to(t_node).unwrap())
             .collect();
-        Some(paths)
+
+        if paths.is_empty() {
+            None
+        } else {
+            Some(paths)
+        }
+    }
+
+    pub fn find_contradictions(&mut self, closure: Closure) {
+        self.find_contradictions_below(0, closure);
     }
 
-    pub fn find_contradictions(&mut self) {
+    /// Closes the open branches through `node` that are contradictory, as
+    /// `find_contradictions` does for all of them.
+    pub fn find_contradictions_below(&mut self, node: usize, closure: Closure) {
         // for each unclosed branch, look for pairs of nodes p, ¬p
-        // if found, close terminal node
+        // or p true and false as `closure` says; if found, close terminal node.
+        // Identicals are substituted in literals first, each name becoming the
+        // least name identical to it at the world, and a = a is true everywhere,
+        // as is ⊤ while ⊥ is true nowhere
+        let terminals = self.terminal_unclosed(node).unwrap_or_default();
         if let Some(unclosed_branches) = self.unclosed_branches() {
+            let unclosed_branches = unclosed_branches.into_iter()
+                .filter(|branch| terminals.contains(branch.last().unwrap()));
+            // branches share most of their formulas, so negate each only once
+            let mut negations: HashMap<String, Option<String>> = HashMap::new();
             for branch in unclosed_branches {
-                for node in branch.iter() {
-                    for node_prime in &branch[*node..] {
-                        todo!()
-                        // compare node and node_prime
+                let identities = self.branch_identities(&branch);
+                let canonical = |world: usize| *identities.get(&world).unwrap_or(&world);
+                let names = self.branch_names(&branch, canonical);
+                let on_branch: HashSet<(String, usize, Sign)> = branch.iter()
+                    .map(|idx| self.get_node(*idx).unwrap())
+                    .filter(|node| node.relation().is_none())
+                    .map(|node| {
+                        let world = canonical(node.world());
+                        let formula = match names.get(&world) {
+                            Some(names) => match parse_expr(node.formula()) {
+                                Ok(expr) if expr.is_literal() => expr.rename(names).to_string(),
+                                _ => node.formula().clone()
+                            },
+                            None => node.formula().clone()
+                        };
+                        (formula, world, node.sign())
+                    })
+                    .collect();
+                let reflexive = |formula: &str| formula.contains(IDENTITY) && parse_expr(formula)
+                    .is_ok_and(|expr| expr.identity().is_some_and(|(a, b)| a == b));
+                let absurd = |formula: &str, sign: Sign| {
+                    let negated = formula.strip_prefix('¬');
+                    match sign {
+                        Sign::True => formula == FALSUM || negated == Some(VERUM),
+                        Sign::False => formula == VERUM || negated == Some(FALSUM)
                     }
+                };
+                let contradiction = on_branch.iter()
+                    .any(|(formula, world, sign)| {
+                        let with_negation = matches!(
+                            (sign, closure),
+                            (Sign::True, Closure::NoGluts) | (Sign::False, Closure::NoGaps)
+                        );
+                        (*sign == Sign::False && on_branch.contains(&(formula.clone(), *world, Sign::True)))
+                            || (with_negation && negations.entry(formula.clone())
+                                .or_insert_with(|| negate(formula).ok())
+                                .as_ref()
+                                .is_some_and(|negation| on_branch.contains(&(negation.clone(), *world, *sign))))
+                            || (*sign == Sign::False && reflexive(formula))
+                            || (with_negation && formula.strip_prefix('¬').is_some_and(reflexive))
+                            || absurd(formula, *sign)
+                    });
+
+                if contradiction {
+                    let terminal = *branch.last().unwrap();
+                    self.get_node_mut(terminal).unwrap().close();
+                }
+            }
+        }
+    }
+
+    /// Relational lines written on a branch.
+    pub fn branch_relations(&self, branch: &[usize]) -> Vec<Relation> {
+        branch.iter()
+            .filter_map(|idx| self.get_node(*idx).unwrap().relation())
+            .collect()
+    }
+
+    /// Maps each world identified with another on the branch to the lowest
+    /// world of its class. Worlds not identified with any are left out.
+    pub fn branch_identities(&self, branch: &[usize]) -> HashMap<usize, usize> {
+        classes(self.branch_relations(branch).into_iter().filter_map(|relation| match relation {
+            Relation::Identity(v, w) => Some((v, w)),
+            _ => None
+        }))
+    }
+
+    /// For each world, as `canonical` maps worlds, with identities `a = b`
+    /// true on the branch, each name identical to another there mapped to
+    /// the least name of its class.
+    fn branch_names(&self, branch: &[usize], canonical: impl Fn(usize) -> usize) -> HashMap<usize, HashMap<String, String>> {
+        let mut identities: HashMap<usize, Vec<(String, String)>> = HashMap::new();
+        for idx in branch {
+            let node = self.get_node(*idx).unwrap();
+            if node.relation().is_some() || node.sign() == Sign::False || !node.formula().contains(IDENTITY) {
+                continue;
+            }
+            if let Ok(expr) = parse_expr(node.formula()) {
+                if let Some((a, b)) = expr.identity() {
+                    identities.entry(canonical(node.world())).or_default().push((a.clone(), b.clone()));
                 }
             }
         }
+        identities.into_iter().map(|(world, pairs)| (world, classes(pairs))).collect()
+    }
+
+    pub fn branch_contains(&self, branch: &[usize], formula: &str, world: usize) -> bool {
+        self.branch_contains_signed(branch, formula, world, Sign::True)
+    }
+
+    pub fn branch_contains_signed(&self, branch: &[usize], formula: &str, world: usize, sign: Sign) -> bool {
+        branch.iter()
+            .map(|idx| self.get_node(*idx).unwrap())
+            .any(|node| node.formula() == formula && node.world() == world && node.sign() == sign)
+    }
+
+    pub fn branch_contains_all(&self, branch: &[usize], formulas: &[String], world: usize) -> bool {
+        formulas.iter().all(|formula| self.branch_contains(branch, formula, world))
+    }
+
+    /// The branch ending at `terminal`.
+    pub fn branch_to(&self, terminal: usize) -> Vec<usize> {
+        self.branches_to(&[terminal]).remove(0)
+    }
+
+    /// The branches ending at each of `terminals`, from a single search.
+    pub fn branches_to(&self, terminals: &[usize]) -> Vec<Vec<usize>> {
+        let search = GraphSearch::bfs(self, 0);
+        terminals.iter()
+            .map(|terminal| search.path_to(*terminal).expect("Terminal should be on the tableau"))
+            .collect()
     }
-    pub fn new_node_from(&mut self, parent: usize, formula: String, world: usize) {
-        let new_node = Formula::new(formula, world);
+
+    /// Adds a relational line below `parent`.
+    pub fn new_relation_from(&mut self, parent: usize, relation: Relation) -> usize {
+        self.nodes.push(Formula::new_relation(relation));
+        let id = self.add_vertex();
+        self.add_edge(parent, id);
+        id
+    }
+
+    pub fn new_node_from(&mut self, parent: usize, formula: String, world: usize) -> usize {
+        self.new_signed_node_from(parent, formula, world, Sign::True)
+    }
+
+    pub fn new_signed_node_from(&mut self, parent: usize, formula: String, world: usize, sign: Sign) -> usize {
+        let new_node = Formula::signed(formula, world, sign);
         self.nodes.push(new_node);
-        self.add_edge(parent, self.size() - 1);
-        self.adjacencies.push(HashSet::new());
+        let id = self.add_vertex();
+        self.add_edge(parent, id);
+        id
     }
 }
 
+/// Maps each element related to another by `pairs`, in the equivalence
+/// relation they generate, to the least element of its class.
+pub fn classes<T: Ord + Hash + Clone>(pairs: impl IntoIterator<Item = (T, T)>) -> HashMap<T, T> {
+    fn find<T: Eq + Hash + Clone>(parent: &HashMap<T, T>, mut x: T) -> T {
+        while let Some(p) = parent.get(&x).filter(|p| **p != x) {
+            x = p.clone();
+        }
+        x
+    }
+
+    let mut parent: HashMap<T, T> = HashMap::new();
+    for (v, w) in pairs {
+        let (v, w) = (find(&parent, v), find(&parent, w));
+        let (low, high) = if v <= w { (v, w) } else { (w, v) };
+        parent.insert(low.clone(), low.clone());
+        parent.insert(high, low);
+    }
+    let elements: Vec<T> = parent.keys().cloned().collect();
+    elements.into_iter().map(|x| (x.clone(), find(&parent, x))).collect()
+}
+
 #[cfg(test)]
 mod test {
     use std::vec;
@@ -186,6 +512,7 @@ mod test {
     use  super::*;
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn worlds() {
         let mut worlds = Graph::<World>::new(4);
 
@@ -203,6 +530,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn modal_connections() {
         let config = ModalOptions::new_default();
         let mut worlds = Graph::<World>::new(10);
@@ -223,6 +551,117 @@ mod test {
         }
     }
 
+    #[test]
+    fn matrix_backend() {
+        let mut list = Graph::<World>::new(6);
+        let mut matrix = Graph::<World, BitMatrix>::new(6);
+        for (v, w) in [(0, 1), (1, 2), (3, 4)] {
+            list.add_edge(v, w);
+            matrix.add_edge(v, w);
+        }
+        let config = ModalOptions::new(false, false, true, true);
+        list.implement_modals(&config);
+        matrix.implement_modals(&config);
+        for v in list.node_ids() {
+            assert_eq!(list.adj_to(v), matrix.adj_to(v));
+        }
+        assert!(matrix.adj_test(0, 2).unwrap());
+        assert!(!matrix.adj_test(0, 3).unwrap());
+        // dead ends 2, 4 and 5 see themselves
+        assert_eq!(Some(1), matrix.out_degree(5));
+        assert_eq!(None, matrix.adj_to(6));
+
+        assert_eq!(6, matrix.add_world());
+        assert_eq!(Some(0), matrix.out_degree(6));
+    }
+
+    #[test]
+    fn frame_conditions() {
+        use crate::graphs::{FrameProperties, FrameProperty};
+
+        // a fork 0 → 1, 0 → 2 and a chain 2 → 3
+        let fork = || {
+            let mut worlds = Graph::<World>::new(4);
+            for (v, w) in [(0, 1), (0, 2), (2, 3)] {
+                worlds.add_edge(v, w);
+            }
+            worlds
+        };
+        let conditions = [
+            ("K5", FrameProperty::Euclidean),
+            ("KD", FrameProperty::Serial),
+            ("S4", FrameProperty::Transitive),
+            ("KD45", FrameProperty::Euclidean)
+        ];
+        for (logic, property) in conditions {
+            let mut worlds = fork();
+            worlds.implement_modals(&ModalOptions::for_logic(logic).unwrap());
+            assert!(FrameProperties::check(&worlds).holds(property), "{}", logic);
+        }
+
+        // conditions asking for worlds to exist or coincide are the
+        // tableau's to meet, edges are left alone
+        for logic in ["K.2", "K.3", "K+dense", "K+functional"] {
+            let mut worlds = fork();
+            worlds.implement_modals(&ModalOptions::for_logic(logic).unwrap());
+            for v in 0..4 {
+                assert_eq!(fork().adj_to(v), worlds.adj_to(v), "{}", logic);
+            }
+        }
+    }
+
+    #[test]
+    fn labelled_edges() {
+        let mut worlds = Graph::<World>::new(2);
+        worlds.add_edge(0, 1);
+        worlds.add_labelled_edge(1, 0, "a");
+        assert_eq!(Some(vec![0]), worlds.labelled_successors(1, "a"));
+        assert_eq!(Some(vec![]), worlds.labelled_successors(1, ""));
+        assert_eq!(Some(vec![]), worlds.labelled_successors(0, "b"));
+        assert_eq!(vec!["a"], worlds.labels().collect::<Vec<&String>>());
+
+        // new worlds join every relation, and each relation is closed on its own
+        let w = worlds.add_world();
+        worlds.add_labelled_edge(0, w, "a");
+        worlds.implement_labelled_modals("a", &ModalOptions::new(true, false, true, false));
+        assert_eq!(Some(vec![0, 1, 2]), worlds.labelled_successors(1, "a"));
+        assert_eq!(Some(vec![1]), worlds.labelled_successors(0, ""));
+        assert_eq!(None, worlds.labelled_successors(3, "a"));
+
+        // 0 -a-> 1 -b-> 2, and 0 -b-> 1
+        let mut worlds = Graph::<World>::new(4);
+        worlds.add_labelled_edge(0, 1, "a");
+        worlds.add_labelled_edge(1, 2, "b");
+        worlds.add_labelled_edge(0, 1, "b");
+        worlds.add_labelled_edge(0, 3, "b");
+        let (a, b) = ("a".to_string(), "b".to_string());
+        assert_eq!(Some(vec![1]), worlds.common_successors(0, &[a.clone(), b.clone()]));
+        assert_eq!(Some(vec![0, 1, 2, 3]), worlds.reachable_by_any(0, &[a.clone(), b.clone()]));
+        assert_eq!(Some(vec![0, 1]), worlds.reachable_by_any(0, std::slice::from_ref(&a)));
+        assert_eq!(Some(vec![2]), worlds.reachable_by_any(2, &[a, b]));
+    }
+
+    #[test]
+    fn deep_tableau() {
+        let formulas: Vec<String> = (0..200_000).map(|idx| format!("p{}", idx)).collect();
+        let mut graph = Graph::<Formula>::new(formulas);
+        graph.new_node_from(199_999, "q".to_owned(), 0);
+        graph.new_node_from(199_999, "r".to_owned(), 0);
+
+        let branches = graph.unclosed_branches().unwrap();
+        assert_eq!(2, branches.len());
+        assert_eq!(200_001, branches[0].len());
+        assert_eq!(Some(&0), branches[1].first());
+        assert_eq!(branches, graph.branches_to(&[200_000, 200_001]));
+        assert_eq!(branches[1], graph.branch_to(200_001));
+        assert_eq!(200_001, graph.max_depth());
+
+        let dfs = GraphSearch::dfs(&graph, 0);
+        assert_eq!(200_001, dfs.marked().count());
+        assert_eq!(vec![199_999, 200_001], dfs.path_to(200_001).unwrap()[199_999..]);
+        assert_eq!(Some(200_001), dfs.path_back(200_001).unwrap().next());
+    }
+
     #[test]
     fn formalas() {
         let world: usize = 0;
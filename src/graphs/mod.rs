@@ -1,6 +1,22 @@
+mod adjacency;
+mod components;
+mod frame;
 mod graph;
 pub mod node;
-mod search;
+pub mod search;
 
+pub use adjacency::*;
+pub use components::*;
+pub use frame::*;
 pub use graph::*;
 use node::*;
+
+/// Worlds 0 to n - 1 with the given edges, for tests.
+#[cfg(test)]
+pub(crate) fn test_graph(n: usize, edges: &[(usize, usize)]) -> Graph<World> {
+    let mut graph = Graph::<World>::new(n);
+    for (v, w) in edges {
+        graph.add_edge(*v, *w);
+    }
+    graph
+}
@@ -1,20 +1,80 @@
+use std::collections::BTreeSet;
+
 #[derive(PartialEq)]
 #[derive(Debug)]
 pub struct Formula {
     formula: String,
     world: usize,
+    sign: Sign,
     state: FormulaState,
+    relation: Option<Relation>
+}
+
+/// Whether a formula is written as true or as false at its world. Classical
+/// tableaux only write truths, with falsity as a negation; signed tableaux
+/// need both where ¬p being true is not p being false.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum Sign {
+    True,
+    False
+}
+
+/// Which lines close a branch besides a formula both true and false at a
+/// world.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Closure {
+    // p and ¬p both true, as classically and in K3
+    NoGluts,
+    // p and ¬p both false, as in LP
+    NoGaps,
+    // nothing else, as in FDE
+    Signs
+}
+
+/// A fact about worlds that holds only on the branch where it is written,
+/// added by the frame rules rather than by a formula.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Relation {
+    // the first world sees the second
+    Access(usize, usize),
+    // the two worlds are the same
+    Identity(usize, usize)
 }
 
 impl Formula {
     pub fn new(formula: String, world: usize) -> Self {
+        Formula::signed(formula, world, Sign::True)
+    }
+
+    pub fn signed(formula: String, world: usize, sign: Sign) -> Self {
         Formula {
             formula,
             world,
-            state: FormulaState::Active
+            sign,
+            state: FormulaState::Active,
+            relation: None
         }
     }
 
+    /// A relational line such as `w1 R w2`, which no rule expands.
+    pub fn new_relation(relation: Relation) -> Self {
+        let (formula, world) = match relation {
+            Relation::Access(v, w) => (format!("w{} R w{}", v, w), v),
+            Relation::Identity(v, w) => (format!("w{} = w{}", v, w), v)
+        };
+        Formula {
+            formula,
+            world,
+            sign: Sign::True,
+            state: FormulaState::Inactive,
+            relation: Some(relation)
+        }
+    }
+
+    pub fn relation(&self) -> Option<Relation> {
+        self.relation
+    }
+
     pub fn state(&self) -> &FormulaState {
         &self.state
     }
@@ -23,6 +83,10 @@ impl Formula {
         self.world
     }
 
+    pub fn sign(&self) -> Sign {
+        self.sign
+    }
+
     pub fn formula(&self) -> &String {
         &self.formula
     }
@@ -35,9 +99,17 @@ impl Formula {
         self.state = FormulaState::WaitingNewWorlds;
     }
 
+    pub fn wait_for_constants(&mut self) {
+        self.state = FormulaState::WaitingNewConstants;
+    }
+
     pub fn close(&mut self) {
         self.state = FormulaState::Closed;
     }
+
+    pub fn postpone(&mut self) {
+        self.state = FormulaState::Postponed;
+    }
 }
 
 #[derive(PartialEq)]
@@ -46,6 +118,10 @@ pub enum FormulaState {
     Active,
     Inactive,
     WaitingNewWorlds,
+    // a universal, instantiated again as constants are added
+    WaitingNewConstants,
+    // an eventuality, expanded once the rest of its branch is saturated
+    Postponed,
     Closed,
 }
 
@@ -53,14 +129,50 @@ pub enum FormulaState {
 #[derive(Debug)]
 pub struct World {
     id: usize,
-    c: Option<String>
+    // least nominal true at the world, shown in countermodels
+    name: Option<String>,
+    origin: usize,
+    // individuals existing at the world, which quantifiers range over
+    domain: BTreeSet<String>
 }
 
 impl World {
     pub fn new(id: usize) -> World {
         World{
             id,
-            c: None
+            name: None,
+            origin: 0,
+            domain: BTreeSet::new()
         }
     }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Tableau node at which the world was introduced. The world only
+    /// exists on branches passing through this node.
+    pub fn origin(&self) -> usize {
+        self.origin
+    }
+
+    pub fn set_origin(&mut self, origin: usize) {
+        self.origin = origin;
+    }
+
+    pub fn name(&self) -> Option<&String> {
+        self.name.as_ref()
+    }
+
+    pub fn set_name(&mut self, name: Option<String>) {
+        self.name = name;
+    }
+
+    pub fn domain(&self) -> &BTreeSet<String> {
+        &self.domain
+    }
+
+    pub fn set_domain(&mut self, domain: BTreeSet<String>) {
+        self.domain = domain;
+    }
 }
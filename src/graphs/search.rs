@@ -1,138 +1,145 @@
-use::std::collections::{HashMap, VecDeque};
-use crate::graphs::Graph;
+use::std::collections::VecDeque;
+use crate::graphs::{Adjacency, Graph};
 
 pub struct GraphSearch {
     source: usize,
-    marked: Vec<usize>,
-    edge_to: Option<HashMap<usize, usize>>,
+    // indexed by vertex, so marking and lookups are O(1)
+    marked: Vec<bool>,
+    // vertices in the order they were first reached
+    order: Vec<usize>,
+    edge_to: Vec<Option<usize>>,
 }
 
 impl GraphSearch {
-    pub fn dfs<T>(g: &Graph<T>, source: usize) -> GraphSearch {
-        let mut dfs = GraphSearch {
+    fn empty(size: usize, source: usize) -> GraphSearch {
+        GraphSearch {
             source,
-            marked: Vec::new(),
-            edge_to: Some(HashMap::with_capacity(g.size()))
-        };
-
-        if let Some(worlds) = g.adj_to(source) {
-            dfs.inner_dfs(g, source);
-        } else {
-            dfs.edge_to = None;
+            marked: vec![false; size],
+            order: Vec::new(),
+            edge_to: vec![None; size]
         }
-        dfs
     }
 
-    fn inner_dfs<T>(&mut self, g: &Graph<T>, v:usize) {
-        if let Some(adj) = g.adj_to(v) {
-            for w in adj.iter() {
-                if !(self.marked.contains(w)) {
-                    self.edge_to.as_mut()
-                        .expect("New search should always start with Some(Hashmap)")
-                        .insert(*w, v);
-                    self.marked.push(*w);
-                    self.inner_dfs(g, *w);
-                }
-            }
-        }       
+    fn mark(&mut self, w: usize, from: usize) {
+        self.marked[w] = true;
+        self.edge_to[w] = Some(from);
+        self.order.push(w);
     }
 
-    pub fn bfs<T>(g: &Graph<T>, source: usize) -> GraphSearch {
-        let mut bfs = GraphSearch{
-            source,
-            marked: Vec::new(),
-            edge_to: Some(HashMap::with_capacity(g.size()))
+    /// Depth-first search from `source`, with an explicit stack so deep
+    /// graphs cannot overflow the call stack.
+    pub fn dfs<T, A: Adjacency>(g: &Graph<T, A>, source: usize) -> GraphSearch {
+        let mut dfs = GraphSearch::empty(g.size(), source);
+        let Some(neighbours) = g.neighbours(source) else {
+            return dfs;
         };
-        
-        if let Some(worlds) = g.adj_to(source) {
-            bfs.inner_bfs(g, source);
-        } else {
-            bfs.edge_to = None;
-        }
 
-        bfs
+        let mut stack = vec![(source, neighbours)];
+        while let Some((v, neighbours)) = stack.last_mut() {
+            let v = *v;
+            match neighbours.find(|w| !dfs.marked[*w]) {
+                Some(w) => {
+                    dfs.mark(w, v);
+                    stack.push((w, g.neighbours(w).unwrap()));
+                },
+                None => { stack.pop(); }
+            }
+        }
+        dfs
     }
 
-    fn inner_bfs<T>(&mut self, g: &Graph<T>, source: usize) {
+    pub fn bfs<T, A: Adjacency>(g: &Graph<T, A>, source: usize) -> GraphSearch {
+        let mut bfs = GraphSearch::empty(g.size(), source);
+        if g.neighbours(source).is_none() {
+            return bfs;
+        }
+
         let mut queue: VecDeque<usize> = VecDeque::new();
-        // self.marked.push(source);
         queue.push_back(source);
-
-        while !queue.is_empty() {
-            let v = queue.pop_front().unwrap();
-            for w in g.adj_to(v).unwrap().iter() {
-                if !(self.marked.contains(w)) {
-                    self.edge_to.as_mut()
-                        .expect("New seach should always have Some(HashMap)")
-                        .insert(*w, v);
-                    self.marked.push(*w);
-                    queue.push_back(*w);
+        while let Some(v) = queue.pop_front() {
+            for w in g.neighbours(v).unwrap() {
+                if !bfs.marked[w] {
+                    bfs.mark(w, v);
+                    queue.push_back(w);
                 }
             }
         }
+        bfs
+    }
+
+    /// Vertices reached from the source, in visiting order. The source is
+    /// only included if it lies on a cycle.
+    pub fn marked(&self) -> impl Iterator<Item = usize> + '_ {
+        self.order.iter().copied()
     }
 
     pub fn all_marked(&self) -> Option<Vec<usize>> {
-        if self.marked.is_empty() {
+        if self.order.is_empty() {
             None
         } else {
-            Some(self.marked.clone())
+            Some(self.order.clone())
         }
     }
 
     pub fn has_path_to(&self, v: usize) -> bool {
-        self.marked.contains(&v)
+        self.marked.get(v).copied().unwrap_or(false)
     }
 
-    pub fn path_to(&self, v: usize) -> Option<Vec<usize>> {
-        if !self.has_path_to(v) {
-            None
+    /// Walks the search tree from `v` back to the source, both included.
+    pub fn path_back(&self, v: usize) -> Option<PathBack<'_>> {
+        if v == self.source || self.has_path_to(v) {
+            Some(PathBack { search: self, next: Some(v) })
         } else {
-            let mut path: Vec<usize> = Vec::new();
-            path.push(v);
-
-            let mut x = v;
-            loop {
-                x = *(self.edge_to.as_ref()
-                    .expect("If edge_to is None, flow should follow first if")
-                    .get(&x).unwrap());
-                if x == self.source { break };
-                path.push(x);    
-            }
-            path.push(self.source);
-            path.sort();
-            Some(path)
+            None
         }
     }
 
-    pub fn shortest_path<T>(g: &Graph<T>, source: usize, target: usize) -> Option<Vec<usize>> {
-        let mut bfs = GraphSearch{
-            source,
-            marked: Vec::new(),
-            edge_to: Some(HashMap::with_capacity(g.size()))
-        };
+    /// Path from the source to `v`.
+    pub fn path_to(&self, v: usize) -> Option<Vec<usize>> {
+        let mut path: Vec<usize> = self.path_back(v)?.collect();
+        path.reverse();
+        Some(path)
+    }
+
+    pub fn shortest_path<T, A: Adjacency>(g: &Graph<T, A>, source: usize, target: usize) -> Option<Vec<usize>> {
+        let mut bfs = GraphSearch::empty(g.size(), source);
+        if source >= g.size() {
+            return None;
+        }
         let mut queue: VecDeque<usize> = VecDeque::new();
-        bfs.marked.push(source);
         queue.push_back(source);
 
-        'outer: while !queue.is_empty() {
-            let v = queue.pop_front().unwrap();
-            for w in g.adj_to(v).unwrap().iter() {
-                if *w == target {
-                    bfs.edge_to.as_mut()
-                        .expect("New search always has Some(Vec)")
-                        .insert(*w, v);
-                    bfs.marked.push(*w);
-                    break 'outer;                    
-                } else if !(bfs.marked.contains(w)) {
-                    bfs.edge_to.as_mut()
-                        .expect("New search always has Some(Vec)")
-                        .insert(*w, v);
-                    bfs.marked.push(*w);
-                    queue.push_back(*w);
+        'outer: while let Some(v) = queue.pop_front() {
+            for w in g.neighbours(v).unwrap() {
+                if w == target {
+                    bfs.mark(w, v);
+                    break 'outer;
+                } else if !bfs.marked[w] && w != source {
+                    bfs.mark(w, v);
+                    queue.push_back(w);
                 }
             }
         }
         bfs.path_to(target)
     }
 }
+
+/// Iterator over a search-tree path, from a vertex up to the source.
+pub struct PathBack<'a> {
+    search: &'a GraphSearch,
+    next: Option<usize>
+}
+
+impl Iterator for PathBack<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let v = self.next?;
+        self.next = if v == self.search.source {
+            None
+        } else {
+            self.search.edge_to[v]
+        };
+        Some(v)
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::time::Instant;
+use crate::countermodel::Countermodel;
+use crate::graphs::{Graph, node::{Closure, Formula, Sign, World}};
+use crate::model::MAX_WORLDS;
+use crate::parser::{parse_expr, parse_formula};
+use crate::prover::{branch_worlds, check_deadline, normalize_argument, open_countermodel, valuation, world_limit};
+
+/// Signed tableau for intuitionistic propositional logic. A branch writes
+/// formulas as true (T) or false (F) at worlds ordered by ≤, the reflexive
+/// transitive closure of the edges from each world to the worlds made from
+/// it. Truth persists up the order, so every T formula is copied to the
+/// worlds above its own. F p ⊃ q and F ¬p need a world above with T p, and
+/// a new one is made only when no world above already witnesses them. A new
+/// world has a T formula its parent lacks, so chains of worlds are no longer
+/// than the number of subformulas.
+struct Prover {
+    tableau: Graph<Formula>,
+    worlds: Graph<World>
+}
+
+enum Expansion {
+    Literal,
+    Linear(Vec<(String, Sign)>),
+    Branching(Vec<(String, Sign)>),
+    // a world above where the first formula is true and the second false
+    Above(String, Option<String>)
+}
+
+impl Prover {
+    fn new(premises: &[String], conclusion: &str) -> Prover {
+        let mut formulas: Vec<(String, Sign)> = premises.iter()
+            .map(|premise| (premise.clone(), Sign::True))
+            .collect();
+        formulas.push((conclusion.to_string(), Sign::False));
+        Prover {
+            tableau: Graph::<Formula>::new_signed(formulas),
+            worlds: Graph::<World>::new(1)
+        }
+    }
+
+    fn run(&mut self, deadline: Option<Instant>) -> Result<(), String> {
+        loop {
+            while let Some(node_id) = self.tableau.first_active_node() {
+                check_deadline(deadline)?;
+                self.expand(node_id)?;
+                self.tableau.find_contradictions_below(node_id, Closure::NoGluts);
+            }
+            if !self.persist() { break; }
+            self.tableau.find_contradictions(Closure::NoGluts);
+        }
+        Ok(())
+    }
+
+    fn expand(&mut self, node_id: usize) -> Result<(), String> {
+        let node = self.tableau.get_node(node_id)
+            .expect("Calling function should make sure node_id is valid");
+        let (world, sign) = (node.world(), node.sign());
+        let expansion = rules(node.formula(), sign)?;
+
+        let terminals = self.tableau.terminal_unclosed(node_id).unwrap_or_default();
+        let branches = self.tableau.branches_to(&terminals);
+        for (terminal, branch) in terminals.into_iter().zip(branches) {
+            let on_branch = |formula: &String, sign: Sign, world: usize| {
+                self.tableau.branch_contains_signed(&branch, formula, world, sign)
+            };
+            match &expansion {
+                Expansion::Literal => {},
+                Expansion::Linear(formulas) => {
+                    let missing: Vec<&(String, Sign)> = formulas.iter()
+                        .filter(|(f, s)| !on_branch(f, *s, world))
+                        .collect();
+                    let mut parent = terminal;
+                    for (formula, sign) in missing {
+                        parent = self.tableau.new_signed_node_from(parent, formula.clone(), world, *sign);
+                    }
+                },
+                Expansion::Branching(formulas) => {
+                    if formulas.iter().any(|(f, s)| on_branch(f, *s, world)) { continue; }
+                    for (formula, sign) in formulas {
+                        self.tableau.new_signed_node_from(terminal, formula.clone(), world, *sign);
+                    }
+                },
+                Expansion::Above(antecedent, consequent) => {
+                    let witnessed = self.above(&branch, world).into_iter().any(|w| {
+                        on_branch(antecedent, Sign::True, w)
+                            && consequent.as_ref().is_none_or(|c| on_branch(c, Sign::False, w))
+                    });
+                    if witnessed { continue; }
+
+                    // with the antecedent already true here, this world does
+                    let (mut parent, mut target) = (terminal, world);
+                    if !on_branch(antecedent, Sign::True, world) {
+                        target = self.new_world(world, terminal, &branch)?;
+                        parent = self.tableau.new_signed_node_from(terminal, antecedent.clone(), target, Sign::True);
+                    }
+                    if let Some(consequent) = consequent {
+                        self.tableau.new_signed_node_from(parent, consequent.clone(), target, Sign::False);
+                    }
+                }
+            }
+        }
+
+        self.tableau.get_node_mut(node_id).unwrap().deactivate();
+        Ok(())
+    }
+
+    /// Copies each T formula to the worlds above its own on every open
+    /// branch. Returns whether anything was added.
+    fn persist(&mut self) -> bool {
+        let mut changed = false;
+        for branch in self.tableau.unclosed_branches().unwrap_or_default() {
+            let truths: Vec<(String, usize)> = branch.iter()
+                .map(|idx| self.tableau.get_node(*idx).unwrap())
+                .filter(|node| node.sign() == Sign::True)
+                .map(|node| (node.formula().clone(), node.world()))
+                .collect();
+            let mut parent = *branch.last().unwrap();
+            let mut added = HashSet::new();
+            for (formula, world) in truths {
+                for w in self.above(&branch, world) {
+                    if self.tableau.branch_contains_signed(&branch, &formula, w, Sign::True)
+                        || !added.insert((formula.clone(), w)) {
+                        continue;
+                    }
+                    parent = self.tableau.new_signed_node_from(parent, formula.clone(), w, Sign::True);
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Worlds of the branch at or above `world`.
+    fn above(&self, branch: &[usize], world: usize) -> Vec<usize> {
+        self.worlds.reachable_by_any(world, &[String::new()]).unwrap_or_default()
+            .into_iter()
+            .filter(|w| branch.contains(&self.worlds.get_node(*w).unwrap().origin()))
+            .collect()
+    }
+
+    /// Adds a world above `from` on the branch ending at `terminal`.
+    fn new_world(&mut self, from: usize, terminal: usize, branch: &[usize]) -> Result<usize, String> {
+        if branch_worlds(&self.worlds, branch).len() >= MAX_WORLDS {
+            return Err(world_limit());
+        }
+        let new_world = self.worlds.add_world();
+        self.worlds.get_node_mut(new_world).unwrap().set_origin(terminal);
+        self.worlds.add_edge(from, new_world);
+        Ok(new_world)
+    }
+
+    /// The worlds of an open branch ordered as on it, true atoms being the
+    /// T atoms.
+    fn countermodel(&self, branch: &[usize]) -> Countermodel {
+        let members: Vec<usize> = self.above(branch, 0);
+        let mut worlds = Graph::<World>::new(members.len());
+        for (local, world) in members.iter().enumerate() {
+            for successor in self.worlds.adj_to(*world).unwrap_or_default() {
+                if let Some(target) = members.iter().position(|w| *w == successor) {
+                    worlds.add_edge(local, target);
+                }
+            }
+        }
+        let valuation = valuation(&self.tableau, branch, &members, |node| node.sign() == Sign::True);
+        Countermodel::intuitionistic(worlds, valuation)
+    }
+}
+
+/// The signed rules. T ¬p is F p, as ¬p holds where p fails at every world
+/// above, and T p ⊃ q is F p or T q at each world, the copies above coming
+/// from persistence.
+fn rules(formula: &str, sign: Sign) -> Result<Expansion, String> {
+    let instructions = parse_formula(formula)?;
+    let variables = instructions.variables();
+    let signed = |idx: usize, sign: Sign| (variables[idx].clone(), sign);
+    let expansion = match (instructions.operators().as_str(), sign) {
+        ("", _) => Expansion::Literal,
+        ("⋀", Sign::True) => Expansion::Linear(vec![signed(0, Sign::True), signed(1, Sign::True)]),
+        ("⋀", Sign::False) => Expansion::Branching(vec![signed(0, Sign::False), signed(1, Sign::False)]),
+        ("⋁", Sign::True) => Expansion::Branching(vec![signed(0, Sign::True), signed(1, Sign::True)]),
+        ("⋁", Sign::False) => Expansion::Linear(vec![signed(0, Sign::False), signed(1, Sign::False)]),
+        ("⊃", Sign::True) => Expansion::Branching(vec![signed(0, Sign::False), signed(1, Sign::True)]),
+        ("⊃", Sign::False) => Expansion::Above(variables[0].clone(), Some(variables[1].clone())),
+        ("¬", Sign::True) => Expansion::Linear(vec![signed(0, Sign::False)]),
+        ("¬", Sign::False) => Expansion::Above(variables[0].clone(), None),
+        // A ≡ B is (A ⊃ B) ⋀ (B ⊃ A), and A ⊕ B is ¬(A ≡ B)
+        ("≡", sign) => {
+            let (a, b) = (parse_expr(&variables[0])?, parse_expr(&variables[1])?);
+            let implications = vec![
+                (a.clone().implies(b.clone()).to_string(), sign),
+                (b.implies(a).to_string(), sign)
+            ];
+            if sign == Sign::True { Expansion::Linear(implications) } else { Expansion::Branching(implications) }
+        },
+        ("⊕", sign) => {
+            let iff = parse_expr(&variables[0])?.iff(parse_expr(&variables[1])?).to_string();
+            if sign == Sign::True { Expansion::Linear(vec![(iff, Sign::False)]) } else { Expansion::Above(iff, None) }
+        },
+        (op, _) => return Err(format!("{} is not a connective of intuitionistic logic", op))
+    };
+    Ok(expansion)
+}
+
+/// Decides whether the premises intuitionistically entail the conclusion.
+/// Returns `None` if they do, otherwise a verified countermodel, and fails
+/// if `deadline` passes first.
+pub fn eval_argument(premises: &[String], conclusion: &str, deadline: Option<Instant>) -> Result<Option<Countermodel>, String> {
+    let (premises, conclusion) = normalize_argument(premises, conclusion)?;
+
+    let mut prover = Prover::new(&premises, &conclusion);
+    prover.run(deadline)?;
+    open_countermodel(&prover.tableau, &premises, &conclusion, |branch| Ok(prover.countermodel(branch)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::eval_with;
+    use crate::graphs::search::GraphSearch;
+
+    fn eval(formulas: &[&str]) -> Option<Countermodel> {
+        eval_with(formulas, |premises, conclusion| eval_argument(premises, conclusion, None))
+    }
+
+    #[test]
+    fn world_limit() {
+        // worlds on other branches do not count towards a branch's limit
+        assert!(eval(&["¬(¬q ⋀ ¬p) ⊃ (¬(p ⋀ p) ⊃ ((p ⊃ q) ⊃ (p ⋁ p)))"]).is_some());
+    }
+
+    #[test]
+    fn valid() {
+        let valid: [&[&str]; 14] = [
+            &["(p ≡ q) ⊃ (q ⊃ p)"],
+            &["p ⊕ p ⊃ ⊥"],
+            &["⊥ ⊃ p"],
+            &["¬p ≡ (p ⊃ ⊥)"],
+            &["p ⊃ p"],
+            &["p ⊃ ¬¬p"],
+            &["¬¬¬p ⊃ ¬p"],
+            &["¬¬(p ⋁ ¬p)"],
+            &["p ⊃ q", "q ⊃ r", "p ⊃ r"],
+            &["p ⋁ q", "¬p", "q"],
+            &["p", "¬p", "q"],
+            &["(p ⋁ q) ⊃ r", "(p ⊃ r) ⋀ (q ⊃ r)"],
+            &["¬(p ⋁ q) ⊃ (¬p ⋀ ¬q)"],
+            &["(p ⊃ q) ⊃ (¬q ⊃ ¬p)"]
+        ];
+        for formulas in valid {
+            assert!(eval(formulas).is_none(), "{:?} should be valid", formulas);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let invalid: [&[&str]; 8] = [
+            &["(p ⊕ q) ⊃ (p ⋁ q)"],
+            &["((p ⊃ ⊥) ⊃ ⊥) ⊃ p"],
+            &["p ⋁ ¬p"],
+            &["¬¬p ⊃ p"],
+            &["((p ⊃ q) ⊃ p) ⊃ p"],
+            &["(¬q ⊃ ¬p) ⊃ (p ⊃ q)"],
+            &["¬(p ⋀ q) ⊃ (¬p ⋁ ¬q)"],
+            &["(p ⊃ q) ⋁ (q ⊃ p)"]
+        ];
+        for formulas in invalid {
+            let countermodel = eval(formulas).unwrap_or_else(|| panic!("{:?} should be invalid", formulas));
+            assert!(countermodel.is_intuitionistic());
+            // ≤ is a partial order: reflexive and, past the closure, acyclic
+            let worlds = countermodel.worlds();
+            for w in worlds.node_ids() {
+                assert!(worlds.adj_test(w, w).unwrap());
+                assert!(worlds.node_ids().into_iter()
+                    .filter(|v| *v != w && worlds.adj_test(w, *v).unwrap())
+                    .all(|v| !GraphSearch::dfs(worlds, v).has_path_to(w)));
+            }
+        }
+
+        let countermodel = eval(&["p ⋁ ¬p"]).unwrap();
+        assert_eq!("w0: {} ≤ {w0, w1}\nw1: {p} ≤ {w1}\n", countermodel.to_string());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(eval_argument(&[], "◻p ⊃ p", None).is_err());
+        assert!(eval_argument(&[], "p ⊃", None).is_err());
+    }
+}
@@ -2,7 +2,22 @@
 #![allow(unused_variables)]
 
 mod parser;
-mod modal_config;
+mod prover;
+pub mod modal_config;
 pub mod configs;
 pub mod model;
-mod graphs;
+pub mod countermodel;
+pub mod ast;
+pub mod truth_table;
+pub mod normal_forms;
+pub mod intuitionistic;
+pub mod many_valued;
+pub mod sat;
+pub mod temporal;
+pub mod counterfactual;
+pub mod provability;
+pub mod fol;
+pub mod translation;
+pub mod tptp;
+pub mod bench;
+pub mod graphs;
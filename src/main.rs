@@ -1,8 +1,10 @@
 use std::error::Error;
 use std::env;
+use std::fs::read_to_string;
 use std::process::exit;
-use logic_model::model::Model;
-use logic_model::configs::Config;
+use logic_model::model::{Model, Verdict};
+use logic_model::configs::{Command, Config, Method};
+use logic_model::{bench, normal_forms, translation, truth_table};
 
 
 fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>>  {
@@ -12,12 +14,50 @@ fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>>  {
         tracing::error!("{}", err);
         exit(1);
     });
-    let _ = Model::from_file(config.infile()).unwrap_or_else(|err| {
+    if config.command() == Command::Normalize {
+        for formula in read_to_string(config.infile())?.lines().filter(|line| !line.trim().is_empty()) {
+            println!("{}", normal_forms::convert(formula, config.form(), config.definitional())?);
+        }
+        return Ok(());
+    }
+
+    if config.command() == Command::Bench {
+        let results = bench::run_dir(&config)?;
+        print!("{}", bench::format_results(&results, config.format()));
+        return Ok(());
+    }
+
+    let mut model = Model::from_config(config.infile(), &config).unwrap_or_else(|err| {
         tracing::error!("{:?} ({})", err, config.infile());
         exit(1);
     });
 
-    // model.eval_tableau();
+    let conclusion = model.conclusion().expect("Models read from file have a conclusion").clone();
+    if config.command() == Command::Tptp {
+        println!("{}", translation::tptp_problem(model.premises(), &conclusion, &model.relation_options())?);
+        return Ok(());
+    }
+
+    let method = model.resolve_method(config.method())?;
+    tracing::info!("Evaluating with {:?}", method);
+
+    let verdict = match method {
+        Method::TruthTable => {
+            let (table, countermodel) = truth_table::eval_argument(model.premises(), &conclusion)?;
+            println!("{}", table);
+            for formula in model.premises().iter().chain([&conclusion]) {
+                println!("{}: {}", formula, table.classify_formula(formula)?);
+            }
+            countermodel.map_or(Verdict::Valid, Verdict::Invalid)
+        },
+        method => model.decide(method)?
+    };
+
+    match verdict {
+        Verdict::Valid => println!("Valid"),
+        Verdict::Invalid(countermodel) => println!("Invalid, countermodel:\n{}", countermodel),
+        Verdict::Unknown(reason) => println!("Unknown, {}", reason)
+    }
 
     Ok(())
 }
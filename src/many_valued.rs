@@ -0,0 +1,213 @@
+use std::collections::{BTreeSet, HashSet};
+use std::time::Instant;
+use crate::configs::Logic;
+use crate::countermodel::Countermodel;
+use crate::graphs::{Graph, node::{Formula, Sign}};
+use crate::parser::{negate, parse_expr, parse_formula};
+use crate::prover::{check_deadline, normalize_argument, open_countermodel};
+
+/// Signed tableau for first degree entailment and its extensions, after
+/// Priest. A formula is written as true (+) or as untrue (-). Every rule
+/// keeps the sign of its line, negations being pushed inwards by De Morgan,
+/// so the logics differ only in what closes a branch: in FDE a formula both
+/// true and untrue, in K3 also p and ¬p both true, in LP also p and ¬p both
+/// untrue. ⊃ is material, A ⊃ B being ¬A ⋁ B, A ≡ B is (A ⊃ B) ⋀ (B ⊃ A)
+/// and A ⊕ B is ¬(A ≡ B).
+enum Expansion {
+    Literal,
+    Linear(Vec<String>),
+    Branching(Vec<String>)
+}
+
+fn rules(formula: &str, sign: Sign) -> Result<Expansion, String> {
+    let instructions = parse_formula(formula)?;
+    let variables = instructions.variables();
+    let implications = |parts: &[String]| -> Result<Vec<String>, String> {
+        let (a, b) = (parse_expr(&parts[0])?, parse_expr(&parts[1])?);
+        Ok(vec![a.clone().implies(b.clone()).to_string(), b.implies(a).to_string()])
+    };
+    let negated = |formulas: Vec<String>| formulas.iter().map(|formula| negate(formula)).collect::<Result<Vec<String>, String>>();
+    // whether the formula being true makes all its parts true, or only one
+    let (conjuncts, alternatives) = match instructions.operators().as_str() {
+        "" => return Ok(Expansion::Literal),
+        "⋀" => (true, variables.clone()),
+        "⋁" => (false, variables.clone()),
+        "⊃" => (false, vec![negate(&variables[0])?, variables[1].clone()]),
+        "≡" => (true, implications(variables)?),
+        "⊕" => (false, negated(implications(variables)?)?),
+        "¬" => {
+            let inner = parse_formula(&variables[0])?;
+            let parts = inner.variables();
+            match inner.operators().as_str() {
+                "" => return Ok(Expansion::Literal),
+                "¬" => (true, vec![parts[0].clone()]),
+                "⋀" => (false, vec![negate(&parts[0])?, negate(&parts[1])?]),
+                "⋁" => (true, vec![negate(&parts[0])?, negate(&parts[1])?]),
+                "⊃" => (true, vec![parts[0].clone(), negate(&parts[1])?]),
+                "≡" => (false, negated(implications(parts)?)?),
+                "⊕" => (true, implications(parts)?),
+                op => return Err(format!("{} is not a connective of first degree entailment", op))
+            }
+        },
+        op => return Err(format!("{} is not a connective of first degree entailment", op))
+    };
+    // an untrue conjunction has an untrue conjunct
+    Ok(if conjuncts == (sign == Sign::True) {
+        Expansion::Linear(alternatives)
+    } else {
+        Expansion::Branching(alternatives)
+    })
+}
+
+fn expand(tableau: &mut Graph<Formula>, node_id: usize) -> Result<(), String> {
+    let node = tableau.get_node(node_id)
+        .expect("Calling function should make sure node_id is valid");
+    let sign = node.sign();
+    let expansion = rules(node.formula(), sign)?;
+
+    for terminal in tableau.terminal_unclosed(node_id).unwrap_or_default() {
+        match &expansion {
+            Expansion::Literal => {},
+            Expansion::Linear(formulas) => {
+                let mut parent = terminal;
+                for formula in formulas {
+                    parent = tableau.new_signed_node_from(parent, formula.clone(), 0, sign);
+                }
+            },
+            Expansion::Branching(formulas) => {
+                for formula in formulas {
+                    tableau.new_signed_node_from(terminal, formula.clone(), 0, sign);
+                }
+            }
+        }
+    }
+
+    tableau.get_node_mut(node_id).unwrap().deactivate();
+    Ok(())
+}
+
+/// Reads the valuation off an open branch: an atom is true if written true
+/// and false if its negation is. In LP an atom written as neither is given
+/// a value its untrue lines allow, as LP has no gaps.
+fn countermodel(tableau: &Graph<Formula>, branch: &[usize], atoms: BTreeSet<String>, logic: Logic) -> Result<Countermodel, String> {
+    let contains = |formula: &str, sign: Sign| tableau.branch_contains_signed(branch, formula, 0, sign);
+    let mut true_atoms = HashSet::new();
+    let mut false_atoms = HashSet::new();
+    for atom in atoms.iter() {
+        let negation = negate(atom)?;
+        let mut value = (contains(atom, Sign::True), contains(&negation, Sign::True));
+        if logic == Logic::LP && value == (false, false) {
+            value = if contains(atom, Sign::False) { (false, true) } else { (true, false) };
+        }
+        if value.0 { true_atoms.insert(atom.clone()); }
+        if value.1 { false_atoms.insert(atom.clone()); }
+    }
+    Ok(Countermodel::first_degree(atoms, true_atoms, false_atoms))
+}
+
+/// Decides whether the premises entail the conclusion in K3, LP or FDE.
+/// Returns `None` if they do, otherwise a verified countermodel, giving up
+/// at `deadline`.
+pub fn eval_argument(premises: &[String], conclusion: &str, logic: Logic, deadline: Option<Instant>) -> Result<Option<Countermodel>, String> {
+    if !matches!(logic, Logic::K3 | Logic::LP | Logic::FDE) {
+        return Err(format!("{:?} is not a many-valued logic", logic));
+    }
+    let (premises, conclusion) = normalize_argument(premises, conclusion)?;
+    let atoms = premises.iter().chain([&conclusion])
+        .map(|formula| parse_expr(formula).map(|expr| expr.atoms()))
+        .collect::<Result<Vec<BTreeSet<String>>, String>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let mut formulas: Vec<(String, Sign)> = premises.iter()
+        .map(|premise| (premise.clone(), Sign::True))
+        .collect();
+    formulas.push((conclusion.clone(), Sign::False));
+    let mut tableau = Graph::<Formula>::new_signed(formulas);
+    tableau.find_contradictions(logic.closure());
+    while let Some(node_id) = tableau.first_active_node() {
+        check_deadline(deadline)?;
+        expand(&mut tableau, node_id)?;
+        tableau.find_contradictions(logic.closure());
+    }
+
+    open_countermodel(&tableau, &premises, &conclusion, |branch| countermodel(&tableau, branch, atoms, logic))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::eval_with;
+
+    fn eval(logic: Logic, formulas: &[&str]) -> Option<Countermodel> {
+        eval_with(formulas, |premises, conclusion| eval_argument(premises, conclusion, logic, None))
+    }
+
+    #[test]
+    fn fde() {
+        let valid: [&[&str]; 5] = [
+            &["p ⋀ q", "q"],
+            &["p", "p ⋁ q"],
+            &["¬(p ⋀ q)", "¬p ⋁ ¬q"],
+            &["¬¬p", "p"],
+            &["p ⋀ (q ⋁ r)", "(p ⋀ q) ⋁ (p ⋀ r)"]
+        ];
+        for formulas in valid {
+            assert!(eval(Logic::FDE, formulas).is_none(), "{:?} should be valid in FDE", formulas);
+        }
+        let invalid: [&[&str]; 4] = [
+            &["p", "¬p", "q"],
+            &["p ⋁ ¬p"],
+            &["p", "p ⊃ q", "q"],
+            &["p ⊃ p"]
+        ];
+        for formulas in invalid {
+            assert!(eval(Logic::FDE, formulas).is_some(), "{:?} should be invalid in FDE", formulas);
+        }
+        let countermodel = eval(Logic::FDE, &["p", "¬p", "q"]).unwrap();
+        assert_eq!("w0: p = b, q = n\n", countermodel.to_string());
+    }
+
+    #[test]
+    fn k3() {
+        assert!(eval(Logic::K3, &["p", "¬p", "q"]).is_none());
+        assert!(eval(Logic::K3, &["p", "p ⊃ q", "q"]).is_none());
+        let countermodel = eval(Logic::K3, &["p ⋁ ¬p"]).unwrap();
+        assert_eq!("w0: p = n\n", countermodel.to_string());
+        assert!(eval(Logic::K3, &["p ⊃ p"]).is_some());
+    }
+
+    #[test]
+    fn lp() {
+        // no explosion, and modus ponens fails on a glut
+        let countermodel = eval(Logic::LP, &["p", "¬p", "q"]).unwrap();
+        assert_eq!("w0: p = b, q = 0\n", countermodel.to_string());
+        let countermodel = eval(Logic::LP, &["p", "p ⊃ q", "q"]).unwrap();
+        assert_eq!("w0: p = b, q = 0\n", countermodel.to_string());
+        assert!(eval(Logic::LP, &["p ⋁ ¬p"]).is_none());
+        assert!(eval(Logic::LP, &["p ⊃ p"]).is_none());
+        assert!(eval(Logic::LP, &["¬(p ⋀ ¬p)"]).is_none());
+    }
+
+    #[test]
+    fn connectives() {
+        assert!(eval(Logic::FDE, &["p ≡ q", "p ⊃ q"]).is_none());
+        assert!(eval(Logic::FDE, &["p ≡ q", "p", "q"]).is_some());
+        assert!(eval(Logic::K3, &["p ≡ p"]).is_some());
+        assert!(eval(Logic::LP, &["p ≡ p"]).is_none());
+        assert!(eval(Logic::LP, &["p ⊕ q", "¬(p ≡ q)"]).is_none());
+        // ⊥ explodes and ⊤ follows from anything, in each logic
+        for logic in [Logic::K3, Logic::LP, Logic::FDE] {
+            assert!(eval(logic, &["⊥", "q"]).is_none());
+            assert!(eval(logic, &["p", "⊤"]).is_none());
+            assert!(eval(logic, &["¬⊤", "q"]).is_none());
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(eval_argument(&[], "◻p ⊃ p", Logic::LP, None).is_err());
+        assert!(eval_argument(&[], "p", Logic::Classical, None).is_err());
+    }
+}
@@ -1,33 +1,135 @@
+use crate::graphs::FrameProperty;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ModalOptions {
     rho: bool,
     sigma: bool,
     tau: bool,
-    eta: bool
+    eta: bool,
+    // Rxy ⋀ Rxz ⊃ Ryz, axiom 5
+    euclid: bool,
+    // Rxy ⋀ Rxz ⊃ ∃w(Ryw ⋀ Rzw), axiom .2
+    convergent: bool,
+    // Rxy ⋀ Rxz ⊃ Ryz ⋁ y = z ⋁ Rzy, axiom .3
+    connected: bool,
+    // Rxy ⋀ Rxz ⊃ y = z, axiom ◇p ⊃ ◻p
+    functional: bool,
+    // Rxz ⊃ ∃y(Rxy ⋀ Ryz), axiom ◻◻p ⊃ ◻p
+    dense: bool
 }
 
 impl ModalOptions {
-    pub fn all_true() -> ModalOptions {
+    pub fn new(rho: bool, sigma: bool, tau: bool, eta: bool) -> ModalOptions {
         ModalOptions {
-            rho: true,
-            sigma: true,
-            tau: true,
-            eta: true
+            rho,
+            sigma,
+            tau,
+            eta,
+            euclid: false,
+            convergent: false,
+            connected: false,
+            functional: false,
+            dense: false
         }
     }
 
+    pub fn all_true() -> ModalOptions {
+        ModalOptions::new(true, true, true, true)
+    }
+
     pub fn new_default() -> ModalOptions {
-        ModalOptions {
-            rho: true,
-            sigma: true,
-            tau: true,
-            eta: false
-        }       
+        ModalOptions::new(true, true, true, false)
+    }
+
+    /// Options for a normal modal logic by its usual name, e.g. K, KD45,
+    /// S4.3, optionally followed by frame conditions as in `KD+functional`.
+    pub fn for_logic(name: &str) -> Result<ModalOptions, String> {
+        let mut conditions = name.split('+');
+        let name = conditions.next().unwrap_or_default();
+        let mut options = ModalOptions::for_system(name)?;
+        for condition in conditions {
+            match condition {
+                "reflexive" => options.rho = true,
+                "symmetric" => options.sigma = true,
+                "transitive" => options.tau = true,
+                "serial" => options.eta = true,
+                "euclidean" => options.euclid = true,
+                "convergent" => options.convergent = true,
+                "connected" => options.connected = true,
+                "functional" => options.functional = true,
+                "dense" => options.dense = true,
+                _ => return Err(format!("Unknown frame condition {}", condition))
+            }
+        }
+        Ok(options)
+    }
+
+    fn for_system(name: &str) -> Result<ModalOptions, String> {
+        let (base, extensions) = match name.split_once('.') {
+            Some((base, extensions)) => (base, extensions),
+            None => (name, "")
+        };
+        let mut options = match base {
+            "S4" => ModalOptions::new(true, false, true, false),
+            "S5" => ModalOptions::new(true, true, true, false),
+            "T" => ModalOptions::new(true, false, false, false),
+            "B" => ModalOptions::new(true, true, false, false),
+            "D" => ModalOptions::new(false, false, false, true),
+            _ if base.starts_with('K') => {
+                let mut options = ModalOptions::new(false, false, false, false);
+                for axiom in base[1..].chars() {
+                    match axiom {
+                        'T' => options.rho = true,
+                        'B' => options.sigma = true,
+                        '4' => options.tau = true,
+                        'D' => options.eta = true,
+                        '5' => options.euclid = true,
+                        _ => return Err(format!("Unknown axiom {} in logic {}", axiom, name))
+                    }
+                }
+                options
+            },
+            _ => return Err(format!("Unknown logic {}", name))
+        };
+        for axiom in extensions.chars() {
+            match axiom {
+                '2' => options.convergent = true,
+                '3' => options.connected = true,
+                _ => return Err(format!("Unknown extension .{} in logic {}", axiom, name))
+            }
+        }
+        Ok(options)
     }
 
     pub fn parse_config() -> ModalOptions {
         todo!()
     }
 
+    pub fn with_euclid(mut self) -> ModalOptions {
+        self.euclid = true;
+        self
+    }
+
+    pub fn with_convergent(mut self) -> ModalOptions {
+        self.convergent = true;
+        self
+    }
+
+    pub fn with_connected(mut self) -> ModalOptions {
+        self.connected = true;
+        self
+    }
+
+    pub fn with_functional(mut self) -> ModalOptions {
+        self.functional = true;
+        self
+    }
+
+    pub fn with_dense(mut self) -> ModalOptions {
+        self.dense = true;
+        self
+    }
+
     pub fn rho(&self) -> bool {
         self.rho
     }
@@ -43,4 +145,82 @@ impl ModalOptions {
     pub fn eta(&self) -> bool {
         self.eta
     }
-}
\ No newline at end of file
+
+    pub fn euclid(&self) -> bool {
+        self.euclid
+    }
+
+    pub fn convergent(&self) -> bool {
+        self.convergent
+    }
+
+    pub fn connected(&self) -> bool {
+        self.connected
+    }
+
+    pub fn functional(&self) -> bool {
+        self.functional
+    }
+
+    pub fn dense(&self) -> bool {
+        self.dense
+    }
+
+    /// The properties a frame of these conditions has.
+    pub fn frame_properties(&self) -> Vec<FrameProperty> {
+        [
+            (self.rho, FrameProperty::Reflexive),
+            (self.sigma, FrameProperty::Symmetric),
+            (self.tau, FrameProperty::Transitive),
+            (self.eta, FrameProperty::Serial),
+            (self.euclid, FrameProperty::Euclidean),
+            (self.convergent, FrameProperty::Convergent),
+            (self.connected, FrameProperty::Connected),
+            (self.functional, FrameProperty::PartiallyFunctional),
+            (self.dense, FrameProperty::Dense)
+        ].into_iter()
+            .filter(|(condition, _)| *condition)
+            .map(|(_, property)| property)
+            .collect()
+    }
+
+    /// Only the conditions that edges alone can close a frame under. The
+    /// rest need new worlds or identities, which the tableau adds itself.
+    pub fn edge_closures(&self) -> ModalOptions {
+        ModalOptions {
+            eta: false,
+            convergent: false,
+            connected: false,
+            functional: false,
+            dense: false,
+            ..*self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logics() {
+        let kd45 = ModalOptions::for_logic("KD45").unwrap();
+        assert!(kd45.eta() && kd45.tau() && kd45.euclid());
+        assert!(!kd45.rho() && !kd45.sigma());
+
+        let s43 = ModalOptions::for_logic("S4.3").unwrap();
+        assert!(s43.rho() && s43.tau() && s43.connected() && !s43.convergent());
+        assert!(ModalOptions::for_logic("S4.2").unwrap().convergent());
+        assert!(!s43.edge_closures().connected());
+        assert!(!kd45.edge_closures().eta() && kd45.edge_closures().euclid());
+
+        assert!(ModalOptions::for_logic("K").is_ok());
+        assert!(ModalOptions::for_logic("K7").is_err());
+        assert!(ModalOptions::for_logic("S4.9").is_err());
+        assert!(ModalOptions::for_logic("GL").is_err());
+
+        let kd_alt = ModalOptions::for_logic("KD+functional+dense").unwrap();
+        assert!(kd_alt.eta() && kd_alt.functional() && kd_alt.dense());
+        assert!(ModalOptions::for_logic("K+linear").is_err());
+    }
+}
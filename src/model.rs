@@ -1,116 +1,1373 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::error::Error;
 use std::fs::read_to_string;
-use std::process::exit;
+use std::time::{Duration, Instant};
+use crate::configs::{Config, Domains, Logic, Method};
+use crate::countermodel::Countermodel;
 use crate::graphs::{Graph, node};
+use crate::graphs::node::{Closure, FormulaState, Relation};
 use crate::modal_config::ModalOptions;
-use crate::parser::{Instructions, parse_formula};
+use crate::ast::{EXISTENCE, GroupModality, IDENTITY, VERUM, Quantifier, knowledge};
+use crate::parser::{Instructions, at, bind, deontic, existence, existent, group, instantiate, negate, necessity, normalize, parse_expr, parse_formula, possibility, quantified, strict_implication};
+use crate::prover::{TIME_LIMIT, agreement, is_limit};
+use crate::{counterfactual, intuitionistic, many_valued, provability, sat, temporal, tptp, truth_table};
+
+/// Upper bound on worlds created while building a tableau, since tableaux
+/// over transitive frames need not terminate.
+pub const MAX_WORLDS: usize = 128;
+/// Default bound on the instances of a universal formula on a branch, as
+/// first-order tableaux need not terminate either.
+pub const MAX_INSTANCES: usize = 32;
 
 pub struct Model {
     worlds: Graph<node::World>,
     modal_options: ModalOptions,
+    // frame conditions of the relations of indexed modalities
+    index_options: HashMap<String, ModalOptions>,
+    indices: BTreeSet<String>,
+    // world each world was created from to fulfil an eventuality
+    eventuality_parent: HashMap<usize, usize>,
+    // whether the formulas are temporal, decided over linear time
+    temporal: bool,
+    // the logic of the formulas' counterfactuals or conditional obligations,
+    // if they have any
+    conditionals: Option<counterfactual::System>,
+    // whether the formulas are modal or temporal, which only classical logic allows
+    modal: bool,
+    logic: Logic,
+    // names used so far, so new constants are fresh
+    names: BTreeSet<String>,
+    // atoms naming a single world, as used after @_ or made up for ↓
+    nominals: BTreeSet<String>,
+    // whether the formulas have ↓, which needs every world named
+    binding: bool,
+    instantiation_bound: usize,
+    domains: Domains,
+    // whether names identical at one world are identical at all of them
+    rigid_identity: bool,
+    premises: Vec<String>,
+    conclusion: Option<String>,
+    deadline: Option<Instant>,
+    limit_reached: bool,
     pub tableau: Graph<node::Formula>
 }
 
+/// Size of a tableau after evaluation.
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub struct TableauStats {
+    pub nodes: usize,
+    pub worlds: usize,
+    pub branches_closed: usize,
+    pub max_depth: usize
+}
+
+/// Verdict on an argument, unknown when a limit cut the evaluation short.
+pub enum Verdict {
+    Valid,
+    Invalid(Countermodel),
+    Unknown(String)
+}
+
+/// What applying a tableau rule to a node adds below it.
+enum Expansion {
+    // nothing, the node is a literal
+    Literal,
+    // formulas added one after the other at the node's world
+    Linear(Vec<String>),
+    // one new branch per formula at the node's world
+    Branching(Vec<String>),
+    // one new branch per list of formulas, added one after the other
+    Branches(Vec<Vec<String>>),
+    // formula true at some world accessible by all the given relations
+    Possibility(Vec<String>, String),
+    // formula true at every accessible world, now and as worlds are added
+    Necessity,
+    // the operand with a fresh constant for the variable
+    Existential(String, String),
+    // the operand for every constant on the branch, now and as constants are added
+    Universal,
+    // formula true at the world the nominal names, made if there is none
+    At(String, String),
+    // the operand with a nominal of the node's world, or a fresh one, for the variable
+    Bind(String, String)
+}
+
+/// The worlds on a branch and the accessibility relations between them,
+/// closed under the frame conditions. Worlds identified on the branch share
+/// one world of the frame.
+struct BranchFrame {
+    // tableau worlds standing for each frame world, lowest first
+    members: Vec<Vec<usize>>,
+    local: HashMap<usize, usize>,
+    frame: Graph<node::World>
+}
+
+impl BranchFrame {
+    fn local(&self, world: usize) -> usize {
+        self.local[&world]
+    }
+
+    /// The tableau world new formulas for a frame world are written at.
+    fn world(&self, local: usize) -> usize {
+        self.members[local][0]
+    }
+
+    fn members(&self, local: usize) -> &[usize] {
+        &self.members[local]
+    }
+
+    /// Successors under the relation of `index`, the empty index being ◻'s.
+    fn successors(&self, local: usize, index: &str) -> Vec<usize> {
+        self.frame.labelled_successors(local, index).unwrap()
+    }
+
+    /// Successors under all of the relations of `indices`.
+    fn common_successors(&self, local: usize, indices: &[String]) -> Vec<usize> {
+        self.frame.common_successors(local, indices).unwrap()
+    }
+}
+
 impl Model {
-    pub  fn new(options: ModalOptions, formulas: Vec<String>) -> Model {
-        Model {
+    /// Builds a model testing whether the last formula follows from the others.
+    pub fn new(options: ModalOptions, formulas: Vec<String>) -> Result<Model, String> {
+        let mut formulas = formulas.iter()
+            .map(|formula| normalize(formula))
+            .collect::<Result<Vec<String>, String>>()?;
+        let conclusion = formulas.pop()
+            .ok_or("Should have at least a conclusion".to_string())?;
+
+        let mut lines = formulas.clone();
+        lines.push(negate(&conclusion)?);
+        let mut indices = BTreeSet::new();
+        let mut names = BTreeSet::new();
+        let mut nominals = BTreeSet::new();
+        let mut binding = false;
+        let (mut modal, mut temporal, mut counterfactual, mut dyadic) = (false, false, false, false);
+        for line in lines.iter() {
+            let expr = parse_expr(line)?;
+            indices.extend(expr.indices());
+            names.extend(expr.names());
+            names.extend(expr.atoms());
+            nominals.extend(expr.nominals());
+            binding |= expr.is_binding();
+            modal |= expr.is_modal();
+            temporal |= expr.is_temporal();
+            counterfactual |= expr.is_counterfactual();
+            dyadic |= expr.is_dyadic();
+        }
+        if modal && temporal {
+            return Err("Temporal and modal operators cannot be mixed".to_string());
+        }
+        let conditionals = match (counterfactual, dyadic) {
+            (true, true) => return Err("Counterfactuals and conditional obligations cannot be mixed".to_string()),
+            (true, false) => Some(counterfactual::System::VC),
+            (false, true) => Some(counterfactual::System::Deontic),
+            (false, false) => None
+        };
+
+        Ok(Model {
             worlds: Graph::<node::World>::new(1),
             modal_options: options,
-            tableau: Graph::<node::Formula>::new(formulas)
-        }
+            index_options: HashMap::new(),
+            indices,
+            eventuality_parent: HashMap::new(),
+            temporal,
+            conditionals,
+            modal: modal || temporal,
+            logic: Logic::Classical,
+            names,
+            nominals,
+            binding,
+            instantiation_bound: MAX_INSTANCES,
+            domains: Domains::Constant,
+            rigid_identity: true,
+            premises: formulas,
+            conclusion: Some(conclusion),
+            deadline: None,
+            limit_reached: false,
+            tableau: Graph::<node::Formula>::new(lines)
+        })
     }
 
-    pub fn from_file(filename: &str) -> Result<Model, Box< std::io::Error>> {
-        let formulas: Vec<String> = read_to_string(filename)?
-            .lines()
-            .map(String::from)
-            .collect();
+    /// Reads one formula per line, or a TPTP/QMLTP problem file, over S5.
+    pub fn from_file(filename: &str) -> Result<Model, Box<dyn Error + Send + Sync>> {
+        Model::from_file_with(filename, ModalOptions::new_default())
+    }
 
-        let model = Model {
-            worlds: Graph::<node::World>::new(1),
-            modal_options: ModalOptions::new_default(),
-            tableau: Graph::<node::Formula>::new(formulas.clone())
-        };
+    pub fn from_file_with(filename: &str, options: ModalOptions) -> Result<Model, Box<dyn Error + Send + Sync>> {
+        let formulas = Model::read_file(filename)?;
+        let model = Model::new(options, formulas.clone())?;
 
         tracing::info!("Model built {}", formulas.join(", "));
         Ok(model)
     }
 
-    pub fn eval_tableau(&mut self) -> Result<(), Box<String>> {
-        while let Some(node_id) = self.tableau.first_active_node() {
-            self.eval_node(node_id)?;
-            self.tableau.find_contradictions();
-            if self.tableau.unclosed_branches().is_some() { todo!() };
+    /// Reads a model from `filename` set up as `config` asks: its logic and
+    /// reading of `P`, the logics of indexed modalities and the options for
+    /// quantifiers.
+    pub fn from_config(filename: &str, config: &Config) -> Result<Model, Box<dyn Error + Send + Sync>> {
+        let mut model = if config.deontic() {
+            Model::deontic(*config.modal_options(), Model::read_file(filename)?)?
+        } else {
+            Model::from_file_with(filename, *config.modal_options())?
+        };
+        for (index, options) in config.index_options() {
+            model.set_index_options(index, *options)?;
+        }
+        model.set_logic(config.logic())?;
+        model.set_instantiation_bound(config.max_instances());
+        model.set_domains(config.domains());
+        model.set_rigid_identity(config.rigid_identity());
+        Ok(model)
+    }
+
+    /// Builds a model reading `P φ` as permission, ◇φ, rather than as the
+    /// past. Obligation `O φ` is ◻φ and `O(ψ/φ)` conditional obligation in
+    /// any case.
+    pub fn deontic(options: ModalOptions, formulas: Vec<String>) -> Result<Model, String> {
+        let formulas = formulas.iter()
+            .map(|formula| deontic(formula))
+            .collect::<Result<Vec<String>, String>>()?;
+        Model::new(options, formulas)
+    }
+
+    /// The formulas of a file of one formula per line, or of a TPTP/QMLTP
+    /// problem file, premises first.
+    pub fn read_file(filename: &str) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let contents = read_to_string(filename)?;
+        Ok(if tptp::is_tptp(&contents) {
+            tptp::read_problem(filename)?.formulas()?
+        } else {
+            contents.lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(String::from)
+                .collect()
+        })
+    }
+
+    pub fn premises(&self) -> &Vec<String> {
+        &self.premises
+    }
+
+    pub fn conclusion(&self) -> Option<&String> {
+        self.conclusion.as_ref()
+    }
+
+    pub fn modal_options(&self) -> &ModalOptions {
+        &self.modal_options
+    }
+
+    /// Frame conditions of the relation of `index`. Unless set, knowledge
+    /// `K_a` is S5, belief `B_a` is KD45 and other indices share those of ◻
+    /// that `set_index_options` accepts.
+    pub fn index_options(&self, index: &str) -> ModalOptions {
+        if index.is_empty() {
+            return self.modal_options;
+        }
+        match self.index_options.get(index) {
+            Some(options) => *options,
+            None if index.starts_with("K_") => ModalOptions::new_default(),
+            None if index.starts_with("B_") => ModalOptions::new(false, false, true, true).with_euclid(),
+            None => {
+                let options = &self.modal_options;
+                let shared = ModalOptions::new(options.rho(), options.sigma(), options.tau(), options.eta());
+                if options.euclid() { shared.with_euclid() } else { shared }
+            }
+        }
+    }
+
+    /// The frame conditions of each relation of the formulas, ◻'s first.
+    pub fn relation_options(&self) -> Vec<(String, ModalOptions)> {
+        std::iter::once(String::new())
+            .chain(self.indices.iter().cloned())
+            .map(|index| {
+                let options = self.index_options(&index);
+                (index, options)
+            })
+            .collect()
+    }
+
+    /// Sets the frame conditions of the relation of `index`. Only conditions
+    /// closed by edges and seriality are supported for indexed relations.
+    pub fn set_index_options(&mut self, index: &str, options: ModalOptions) -> Result<(), String> {
+        if options.convergent() || options.connected() || options.functional() || options.dense() {
+            return Err(format!("Only reflexive, symmetric, transitive, serial and euclidean relations are supported for [{}]", index));
+        }
+        self.index_options.insert(index.to_string(), options);
+        Ok(())
+    }
+
+    /// Decides the argument in `logic` rather than classically. Only the
+    /// provability logics allow modal operators.
+    pub fn set_logic(&mut self, logic: Logic) -> Result<(), String> {
+        if logic != Logic::Classical && !logic.is_provability() && self.modal {
+            return Err(format!("{:?} logic has no modal or temporal operators", logic));
+        }
+        self.logic = logic;
+        Ok(())
+    }
+
+    pub fn logic(&self) -> Logic {
+        self.logic
+    }
+
+    /// Bounds the instances of each universal formula on a branch.
+    pub fn set_instantiation_bound(&mut self, bound: usize) {
+        self.instantiation_bound = bound;
+    }
+
+    /// Sets how domains vary between worlds. With constant domains every
+    /// name exists, while otherwise quantifiers at a world range over the
+    /// names `E!` holds of there.
+    pub fn set_domains(&mut self, domains: Domains) {
+        self.domains = domains;
+    }
+
+    pub fn domains(&self) -> Domains {
+        self.domains
+    }
+
+    /// Sets whether identity is rigid, as by default, so that `a = b` at one
+    /// world holds at every world, or may hold at some worlds only.
+    pub fn set_rigid_identity(&mut self, rigid: bool) {
+        self.rigid_identity = rigid;
+    }
+
+    /// Stops the tableau with an error once `limit` has passed.
+    pub fn set_time_limit(&mut self, limit: Duration) {
+        self.deadline = Some(Instant::now() + limit);
+    }
+
+    /// Whether evaluation was cut short by the time or world limit or the
+    /// instantiation bound, so that the result is unknown rather than an
+    /// error in the input.
+    pub fn limit_reached(&self) -> bool {
+        self.limit_reached
+    }
+
+    pub fn stats(&self) -> TableauStats {
+        let terminals = self.tableau.terminals();
+        TableauStats {
+            nodes: self.tableau.size(),
+            worlds: self.worlds.size(),
+            branches_closed: terminals.iter()
+                .filter(|t| *self.tableau.get_node(**t).unwrap().state() == FormulaState::Closed)
+                .count(),
+            max_depth: self.tableau.max_depth()
+        }
+    }
+
+    /// The method `Method::Auto` stands for with this model's formulas.
+    /// Only the tableau decides non-classical logics.
+    pub fn resolve_method(&self, method: Method) -> Result<Method, String> {
+        if self.logic != Logic::Classical {
+            return match method {
+                Method::Auto | Method::Tableau => Ok(Method::Tableau),
+                method => Err(format!("{:?} cannot decide {:?} logic, use the tableau", method, self.logic))
+            };
+        }
+        if method != Method::Auto {
+            return Ok(method);
+        }
+        let mut formulas = self.premises.clone();
+        formulas.extend(self.conclusion.iter().cloned());
+        // SAT would take E! and = for ordinary predicates
+        let special = formulas.iter().any(|formula| formula.contains(EXISTENCE) || formula.contains(IDENTITY));
+        if sat::is_propositional(&formulas)? && !special { Ok(Method::Sat) } else { Ok(Method::Tableau) }
+    }
+
+    /// Whether the tableau is left to the prover of another logic, of time
+    /// or of conditionals.
+    fn uses_prover(&self) -> bool {
+        self.logic != Logic::Classical || self.temporal || self.conditionals.is_some()
+    }
+
+    /// Whether `method` runs the modal tableau `stats` describes, rather than
+    /// SAT, a truth table or another prover.
+    pub fn runs_tableau(&self, method: Method) -> bool {
+        self.resolve_method(method).is_ok_and(|method| method == Method::Tableau) && !self.uses_prover()
+    }
+
+    /// The verdict of the prover an argument in another logic, about time or
+    /// with conditionals gets, `None` if it is left to the modal tableau.
+    fn eval_prover(&self) -> Option<Result<Option<Countermodel>, String>> {
+        if !self.uses_prover() {
+            return None;
+        }
+        let Some(conclusion) = self.conclusion.clone() else {
+            return Some(Err("Model has no conclusion".to_string()));
+        };
+        Some(match self.logic {
+            Logic::Intuitionistic => intuitionistic::eval_argument(&self.premises, &conclusion, self.deadline),
+            Logic::GL | Logic::Grz => provability::eval_argument(&self.premises, &conclusion, self.logic, self.deadline),
+            Logic::Classical if self.temporal => temporal::eval_argument(&self.premises, &conclusion, self.deadline),
+            Logic::Classical => counterfactual::eval_argument(&self.premises, &conclusion, self.conditionals?, self.deadline),
+            logic => many_valued::eval_argument(&self.premises, &conclusion, logic, self.deadline)
+        })
+    }
+
+    /// `eval`, with running into a limit an unknown verdict rather than an
+    /// error.
+    pub fn decide(&mut self, method: Method) -> Result<Verdict, String> {
+        match self.eval(method) {
+            Ok(None) => Ok(Verdict::Valid),
+            Ok(Some(countermodel)) => Ok(Verdict::Invalid(countermodel)),
+            Err(err) if self.limit_reached => Ok(Verdict::Unknown(err)),
+            Err(err) => Err(err)
+        }
+    }
 
-            // update wrw
-            // apply waiting necessity formulae
-            self.tableau.find_contradictions();
-            if self.tableau.unclosed_branches().is_some() { todo!() };
+    /// Decides the argument with the given method.
+    pub fn eval(&mut self, method: Method) -> Result<Option<Countermodel>, String> {
+        let conclusion = self.conclusion.clone().ok_or("Model has no conclusion".to_string())?;
+        match self.resolve_method(method)? {
+            Method::Auto | Method::Tableau => self.eval_tableau(),
+            Method::Sat => sat::eval_argument(&self.premises, &conclusion),
+            Method::TruthTable => Ok(truth_table::eval_argument(&self.premises, &conclusion)?.1)
+        }
+    }
+
+    /// Runs the tableau to completion. Returns `None` if every branch closes,
+    /// otherwise a verified countermodel read off the first open branch.
+    /// Temporal formulas get the linear time tableau, counterfactuals the
+    /// tableau for VC and conditional obligations that for dyadic deontic
+    /// logic, whatever the options, and the non-classical logics their
+    /// signed tableaux.
+    pub fn eval_tableau(&mut self) -> Result<Option<Countermodel>, String> {
+        if let Some(result) = self.eval_prover() {
+            if result.as_ref().is_err_and(|err| is_limit(err)) {
+                self.limit_reached = true;
+            }
+            return result;
+        }
+        loop {
+            while let Some(node_id) = self.tableau.first_active_node() {
+                if self.deadline.is_some_and(|deadline| Instant::now() > deadline) {
+                    self.limit_reached = true;
+                    return Err(TIME_LIMIT.to_string());
+                }
+                self.eval_node(node_id)?;
+                self.tableau.find_contradictions(Closure::NoGluts);
+            }
+
+            // update wrw, apply waiting necessity formulae, then postponed
+            // possibilities and the rules for frame conditions, then one
+            // eventuality step per branch
+            if !(self.apply_nominals()? || self.apply_necessities()? || self.apply_universals()? || self.apply_existence()?
+                || self.apply_rigid_identity()? || self.apply_possibilities()? || self.apply_frame_rules()?
+                || self.apply_eventualities()?) { break; }
+            self.tableau.find_contradictions(Closure::NoGluts);
         }
 
         if let Some(open_branches) = self.tableau.unclosed_branches() {
             let branch = open_branches.first().unwrap().clone();
-            self.build_countermodel(branch);
+            let countermodel = self.build_countermodel(&branch)?;
+            countermodel.verify(&self.premises, self.conclusion.as_ref())?;
+            return Ok(Some(countermodel));
         }
 
-        Ok(())
+        Ok(None)
     }
 
-    fn eval_node(&mut self, node_id: usize) -> Result<(), Box<String>> {
+    fn eval_node(&mut self, node_id: usize) -> Result<(), String> {
         let node = self.tableau.get_node(node_id)
             .expect("Calling function should make sure node_id is valid");
+        let world = node.world();
+        // with constant domains everything exists
+        let nonexistence = self.domains == Domains::Constant
+            && node.formula().strip_prefix('¬').is_some_and(|atom| atom.starts_with(EXISTENCE));
         let instructions: Instructions = parse_formula(node.formula())?;
-        self.implement_instructions(instructions, node_id);
-        // implement instructions
-        //      create worlds, wrw
-        //      add formulae to open terminals
+        let expansion = self.implement_instructions(instructions)?;
+
+        let terminals = self.tableau.terminal_unclosed(node_id).unwrap_or_default();
+        match expansion {
+            Expansion::Literal if nonexistence => {
+                self.tableau.get_node_mut(node_id).unwrap().deactivate();
+                for terminal in terminals {
+                    self.tableau.get_node_mut(terminal).unwrap().close();
+                }
+                return Ok(());
+            },
+            Expansion::Literal => {},
+            Expansion::Linear(formulas) => {
+                for terminal in terminals {
+                    let mut parent = terminal;
+                    for formula in formulas.iter() {
+                        parent = self.tableau.new_node_from(parent, formula.clone(), world);
+                    }
+                }
+            },
+            Expansion::Branching(formulas) => {
+                for terminal in terminals {
+                    for formula in formulas.iter() {
+                        self.tableau.new_node_from(terminal, formula.clone(), world);
+                    }
+                }
+            },
+            Expansion::Branches(branches) => {
+                for terminal in terminals {
+                    for formulas in branches.iter() {
+                        let mut parent = terminal;
+                        for formula in formulas {
+                            parent = self.tableau.new_node_from(parent, formula.clone(), world);
+                        }
+                    }
+                }
+            },
+            Expansion::Possibility(_, formula) if is_eventuality(&formula)? => {
+                self.tableau.get_node_mut(node_id).unwrap().postpone();
+                return Ok(());
+            },
+            Expansion::Possibility(indices, _) if self.reuses_worlds(&indices) => {
+                self.tableau.get_node_mut(node_id).unwrap().postpone();
+                return Ok(());
+            },
+            Expansion::Possibility(indices, formula) => {
+                let branches = self.tableau.branches_to(&terminals);
+                for (terminal, branch) in terminals.into_iter().zip(branches) {
+                    let frame = self.branch_frame(&branch);
+                    let local = frame.local(world);
+                    let successors = frame.common_successors(local, &indices);
+                    let witnessed = successors.iter()
+                        .any(|w| self.holds_at(&branch, &frame, &formula, *w));
+                    if witnessed { continue; }
+
+                    // with at most one successor, an existing one must do
+                    let functional = indices.len() == 1 && self.index_options(&indices[0]).functional();
+                    let target = match successors.first() {
+                        Some(successor) if functional => frame.world(*successor),
+                        _ => self.new_world(world, terminal, &indices)?
+                    };
+                    self.tableau.new_node_from(terminal, formula.clone(), target);
+                }
+            },
+            Expansion::Necessity => {
+                self.tableau.get_node_mut(node_id).unwrap().wait();
+                return Ok(());
+            },
+            Expansion::Existential(var, operand) => {
+                let constant = self.fresh_constant();
+                let instance = instantiate(&operand, &var, &constant)?;
+                for terminal in terminals {
+                    let mut parent = terminal;
+                    if self.domains != Domains::Constant {
+                        parent = self.tableau.new_node_from(parent, existence(&constant), world);
+                    }
+                    self.tableau.new_node_from(parent, instance.clone(), world);
+                }
+            },
+            Expansion::Universal => {
+                self.tableau.get_node_mut(node_id).unwrap().wait_for_constants();
+                return Ok(());
+            },
+            Expansion::At(nominal, formula) => {
+                let branches = self.tableau.branches_to(&terminals);
+                for (terminal, branch) in terminals.into_iter().zip(branches) {
+                    let frame = self.branch_frame(&branch);
+                    match (0..frame.members.len()).find(|local| self.holds_at(&branch, &frame, &nominal, *local)) {
+                        Some(local) if self.holds_at(&branch, &frame, &formula, local) => {},
+                        Some(local) => { self.tableau.new_node_from(terminal, formula.clone(), frame.world(local)); },
+                        None => {
+                            let named = self.new_world(world, terminal, &[])?;
+                            let parent = self.tableau.new_node_from(terminal, nominal.clone(), named);
+                            self.tableau.new_node_from(parent, formula.clone(), named);
+                        }
+                    }
+                }
+            },
+            Expansion::Bind(var, operand) => {
+                let branches = self.tableau.branches_to(&terminals);
+                for (terminal, branch) in terminals.into_iter().zip(branches) {
+                    let frame = self.branch_frame(&branch);
+                    let local = frame.local(world);
+                    let mut parent = terminal;
+                    let nominal = match self.nominals.iter().find(|nominal| self.holds_at(&branch, &frame, nominal, local)) {
+                        Some(nominal) => nominal.clone(),
+                        None => {
+                            let nominal = self.fresh_nominal();
+                            parent = self.tableau.new_node_from(parent, nominal.clone(), world);
+                            nominal
+                        }
+                    };
+                    self.tableau.new_node_from(parent, instantiate(&operand, &var, &nominal)?, world);
+                }
+            }
+        }
+
+        self.tableau.get_node_mut(node_id).unwrap().deactivate();
         Ok(())
     }
 
     // move implementation details to own file
-    fn implement_instructions(&mut self, instructions: Instructions, node_id: usize) {
-        match instructions.operators().as_str() {
-            "⋀" => todo!(),
-            "⋁" => todo!(),
-            "⊃" => {
-                if instructions.variables().len() > 2 {
-                    tracing::warn!("Too many variables in {}: {}. Ignoring extra.", instructions.operators(), instructions.variables().join(", "));
+    fn implement_instructions(&self, instructions: Instructions) -> Result<Expansion, String> {
+        let variables = instructions.variables();
+        let expansion = match instructions.operators().as_str() {
+            "" => Expansion::Literal,
+            "⋀" => Expansion::Linear(variables.clone()),
+            "⋁" => Expansion::Branching(variables.clone()),
+            "⊃" => Expansion::Branching(vec![negate(&variables[0])?, variables[1].clone()]),
+            "≡" => Expansion::Branches(agreement(&variables[0], &variables[1], true)?),
+            "⊕" => Expansion::Branches(agreement(&variables[0], &variables[1], false)?),
+            "⥽" => Expansion::Linear(vec![strict_implication(&variables[0], &variables[1])?]),
+            "◻" | "[]" | "D_" => Expansion::Necessity,
+            "◇" | "<>" => Expansion::Possibility(relations(&instructions), variables[0].clone()),
+            "∀" => Expansion::Universal,
+            "∃" => Expansion::Existential(instructions.index().clone(), variables[0].clone()),
+            "@" => Expansion::At(instructions.index().clone(), variables[0].clone()),
+            "↓" => Expansion::Bind(instructions.index().clone(), variables[0].clone()),
+            "E_" => Expansion::Linear(instructions.group().iter()
+                .map(|agent| necessity(&knowledge(agent), &variables[0]))
+                .collect::<Result<Vec<String>, String>>()?),
+            "C_" => {
+                // C_G p is p ⋀ E_G C_G p
+                let common = group(GroupModality::Common, &instructions.group(), &variables[0])?;
+                let mut formulas = vec![variables[0].clone()];
+                for agent in instructions.group() {
+                    formulas.push(necessity(&knowledge(&agent), &common)?);
+                }
+                Expansion::Linear(formulas)
+            },
+            "¬" => {
+                let negated = parse_formula(&variables[0])?;
+                let inner = negated.variables();
+                match negated.operators().as_str() {
+                    "" => Expansion::Literal,
+                    "¬" => Expansion::Linear(vec![inner[0].clone()]),
+                    "⋀" => Expansion::Branching(vec![negate(&inner[0])?, negate(&inner[1])?]),
+                    "⋁" => Expansion::Linear(vec![negate(&inner[0])?, negate(&inner[1])?]),
+                    "⊃" => Expansion::Linear(vec![inner[0].clone(), negate(&inner[1])?]),
+                    "≡" => Expansion::Branches(agreement(&inner[0], &inner[1], false)?),
+                    "⊕" => Expansion::Branches(agreement(&inner[0], &inner[1], true)?),
+                    "⥽" => Expansion::Linear(vec![negate(&strict_implication(&inner[0], &inner[1])?)?]),
+                    "◻" => Expansion::Linear(vec![format!("◇{}", negate(&inner[0])?)]),
+                    "◇" => Expansion::Linear(vec![format!("◻{}", negate(&inner[0])?)]),
+                    "[]" => Expansion::Linear(vec![possibility(negated.index(), &negate(&inner[0])?)?]),
+                    "<>" => Expansion::Linear(vec![necessity(negated.index(), &negate(&inner[0])?)?]),
+                    "E_" => Expansion::Branching(negated.group().iter()
+                        .map(|agent| negate(&necessity(&knowledge(agent), &inner[0])?))
+                        .collect::<Result<Vec<String>, String>>()?),
+                    "C_" => {
+                        // an eventuality: ¬p now, or ¬C_G p for some agent's world
+                        let common = group(GroupModality::Common, &negated.group(), &inner[0])?;
+                        let mut formulas = vec![negate(&inner[0])?];
+                        for agent in negated.group() {
+                            formulas.push(negate(&necessity(&knowledge(&agent), &common)?)?);
+                        }
+                        Expansion::Branching(formulas)
+                    },
+                    "D_" => Expansion::Possibility(relations(&negated), negate(&inner[0])?),
+                    "∀" => Expansion::Linear(vec![quantified(Quantifier::Existential, negated.index(), &negate(&inner[0])?)?]),
+                    "∃" => Expansion::Linear(vec![quantified(Quantifier::Universal, negated.index(), &negate(&inner[0])?)?]),
+                    "@" => Expansion::Linear(vec![at(negated.index(), &negate(&inner[0])?)?]),
+                    "↓" => Expansion::Linear(vec![bind(negated.index(), &negate(&inner[0])?)?]),
+                    op => return Err(format!("No instructions found for ¬{}", op))
+                }
+            },
+            op => return Err(format!("No instructions found for {}", op))
+        };
+        Ok(expansion)
+    }
+
+    /// Applies every waiting necessity formula to the worlds accessible on its
+    /// branch. Returns whether anything was added to the tableau.
+    fn apply_necessities(&mut self) -> Result<bool, String> {
+        let mut changed = false;
+        let Some(open_branches) = self.tableau.unclosed_branches() else {
+            return Ok(false);
+        };
+
+        for branch in open_branches {
+            let necessities = self.waiting_necessities(&branch)?;
+            let mut terminal = *branch.last().unwrap();
+
+            // extendable: a world with necessities gets somewhere to go
+            let frame = self.branch_frame(&branch);
+            let dead_ends: BTreeSet<(String, usize)> = necessities.iter()
+                .filter_map(|(indices, _, world)| match indices.as_slice() {
+                    [index] if self.index_options(index).eta() => Some((index.clone(), frame.local(*world))),
+                    _ => None
+                })
+                .filter(|(index, local)| frame.successors(*local, index).is_empty())
+                .map(|(index, local)| (index, frame.world(local)))
+                .collect();
+            // on a transitive frame a world already there will do if the
+            // edge to it owes nothing, else ◻◻A makes new worlds forever
+            let mut reused = false;
+            for (index, world) in dead_ends.iter() {
+                if !self.reuses_worlds(std::slice::from_ref(index)) { continue; }
+                let options: Vec<Vec<(usize, usize)>> = (0..frame.frame.size())
+                    .map(|w| vec![(frame.local(*world), w)])
+                    .collect();
+                if self.add_free_edges(&branch, &frame, options)? {
+                    reused = true;
+                    break;
+                }
+            }
+            if reused {
+                changed = true;
+                continue;
+            }
+            for (index, world) in dead_ends {
+                self.new_world(world, terminal, &[index])?;
+                changed = true;
+            }
+
+            let frame = self.branch_frame(&branch);
+            let mut added: HashSet<(String, usize)> = HashSet::new();
+            for (indices, formula, world) in necessities {
+                for accessible in frame.common_successors(frame.local(world), &indices) {
+                    let target = frame.world(accessible);
+                    if self.holds_at(&branch, &frame, &formula, accessible)
+                        || !added.insert((formula.clone(), target)) {
+                        continue;
+                    }
+                    terminal = self.tableau.new_node_from(terminal, formula.clone(), target);
+                    changed = true;
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// The relations, operand and world of each necessity waiting on the
+    /// branch.
+    fn waiting_necessities(&self, branch: &[usize]) -> Result<Vec<(Vec<String>, String, usize)>, String> {
+        branch.iter()
+            .map(|idx| self.tableau.get_node(*idx).unwrap())
+            .filter(|node| *node.state() == FormulaState::WaitingNewWorlds)
+            .map(|node| {
+                let instructions = parse_formula(node.formula())?;
+                Ok((relations(&instructions), instructions.variables()[0].clone(), node.world()))
+            })
+            .collect()
+    }
+
+    /// Instantiates every waiting universal formula with each constant on
+    /// its branch, or with a new one if the branch has none. Unless domains
+    /// are constant, only constants existing at the formula's world are used
+    /// and none is made up. Returns whether anything was added to the tableau.
+    fn apply_universals(&mut self) -> Result<bool, String> {
+        let mut changed = false;
+        let Some(open_branches) = self.tableau.unclosed_branches() else {
+            return Ok(false);
+        };
+
+        for branch in open_branches {
+            let universals: Vec<(String, String, usize)> = branch.iter()
+                .map(|idx| self.tableau.get_node(*idx).unwrap())
+                .filter(|node| *node.state() == FormulaState::WaitingNewConstants)
+                .map(|node| {
+                    let instructions = parse_formula(node.formula())?;
+                    Ok((instructions.index().clone(), instructions.variables()[0].clone(), node.world()))
+                })
+                .collect::<Result<Vec<(String, String, usize)>, String>>()?;
+            if universals.is_empty() { continue; }
+
+            let mut constants = self.branch_constants(&branch)?;
+            if constants.is_empty() && self.domains == Domains::Constant {
+                constants.insert(self.fresh_constant());
+            }
+            let frame = self.branch_frame(&branch);
+            let mut terminal = *branch.last().unwrap();
+            for (var, operand, world) in universals {
+                let domain: Vec<&String> = constants.iter()
+                    .filter(|constant| self.domains == Domains::Constant
+                        || self.holds_at(&branch, &frame, &existence(constant), frame.local(world)))
+                    .collect();
+                let mut instances = 0;
+                for constant in domain {
+                    let instance = instantiate(&operand, &var, constant)?;
+                    if self.tableau.branch_contains(&branch, &instance, world) {
+                        instances += 1;
+                        continue;
+                    }
+                    if instances >= self.instantiation_bound {
+                        self.limit_reached = true;
+                        return Err(format!("Instantiation bound of {} reached, tableau may be infinite", self.instantiation_bound));
+                    }
+                    terminal = self.tableau.new_node_from(terminal, instance, world);
+                    instances += 1;
+                    changed = true;
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// With increasing domains, whatever exists at a world exists at every
+    /// world accessible from it, by any relation. Returns whether anything
+    /// was added to the tableau.
+    fn apply_existence(&mut self) -> Result<bool, String> {
+        let mut changed = false;
+        if self.domains != Domains::Increasing {
+            return Ok(false);
+        }
+        let Some(open_branches) = self.tableau.unclosed_branches() else {
+            return Ok(false);
+        };
+
+        let labels: Vec<String> = std::iter::once(String::new())
+            .chain(self.indices.iter().cloned())
+            .collect();
+        for branch in open_branches {
+            let mut existents: Vec<(String, usize)> = Vec::new();
+            for idx in branch.iter() {
+                let node = self.tableau.get_node(*idx).unwrap();
+                if node.relation().is_none() && existent(node.formula())?.is_some() {
+                    existents.push((node.formula().clone(), node.world()));
+                }
+            }
+            let frame = self.branch_frame(&branch);
+            let mut terminal = *branch.last().unwrap();
+            let mut added: HashSet<(String, usize)> = HashSet::new();
+            for (formula, world) in existents {
+                for label in labels.iter() {
+                    for accessible in frame.successors(frame.local(world), label) {
+                        let target = frame.world(accessible);
+                        if self.holds_at(&branch, &frame, &formula, accessible)
+                            || !added.insert((formula.clone(), target)) {
+                            continue;
+                        }
+                        terminal = self.tableau.new_node_from(terminal, formula.clone(), target);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// With rigid identity, writes each identity `a = b` on a branch at
+    /// every world of the branch. Returns whether anything was added to the
+    /// tableau.
+    fn apply_rigid_identity(&mut self) -> Result<bool, String> {
+        let mut changed = false;
+        if !self.rigid_identity {
+            return Ok(false);
+        }
+        let Some(open_branches) = self.tableau.unclosed_branches() else {
+            return Ok(false);
+        };
+
+        for branch in open_branches {
+            let mut identities: Vec<String> = Vec::new();
+            for idx in branch.iter() {
+                let node = self.tableau.get_node(*idx).unwrap();
+                if node.relation().is_none() && node.formula().contains(IDENTITY)
+                    && parse_expr(node.formula())?.identity().is_some()
+                    && !identities.contains(node.formula()) {
+                    identities.push(node.formula().clone());
+                }
+            }
+            if identities.is_empty() { continue; }
+            let frame = self.branch_frame(&branch);
+            let mut terminal = *branch.last().unwrap();
+            for formula in identities {
+                for local in 0..frame.members.len() {
+                    if self.holds_at(&branch, &frame, &formula, local) { continue; }
+                    terminal = self.tableau.new_node_from(terminal, formula.clone(), frame.world(local));
+                    changed = true;
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Makes each nominal name exactly one world of each open branch: worlds
+    /// a nominal is true at are identified, and a nominal true nowhere gets
+    /// a world of its own. Each open branch gets at most one step per call,
+    /// and the result is whether anything was added.
+    fn apply_nominals(&mut self) -> Result<bool, String> {
+        let mut changed = false;
+        if self.nominals.is_empty() {
+            return Ok(false);
+        }
+        let Some(open_branches) = self.tableau.unclosed_branches() else {
+            return Ok(false);
+        };
+
+        for branch in open_branches {
+            let terminal = *branch.last().unwrap();
+            let frame = self.branch_frame(&branch);
+            for nominal in self.nominals.clone() {
+                let named: Vec<usize> = (0..frame.members.len())
+                    .filter(|local| self.holds_at(&branch, &frame, &nominal, *local))
+                    .collect();
+                match named.as_slice() {
+                    [] => {
+                        let world = self.new_world(0, terminal, &[])?;
+                        self.tableau.new_node_from(terminal, nominal, world);
+                    },
+                    [v, w, ..] => {
+                        let relation = Relation::Identity(frame.world(*v), frame.world(*w));
+                        self.tableau.new_relation_from(terminal, relation);
+                    },
+                    _ => continue
+                }
+                changed = true;
+                break;
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Names occurring free in the formulas of a branch.
+    fn branch_constants(&self, branch: &[usize]) -> Result<BTreeSet<String>, String> {
+        let mut constants = BTreeSet::new();
+        for idx in branch {
+            let node = self.tableau.get_node(*idx).unwrap();
+            if node.relation().is_none() {
+                constants.extend(parse_expr(node.formula())?.free_names());
+            }
+        }
+        Ok(constants)
+    }
+
+    fn fresh_constant(&mut self) -> String {
+        let constant = (0..).map(|n| format!("c{}", n))
+            .find(|name| !self.names.contains(name))
+            .unwrap();
+        self.names.insert(constant.clone());
+        constant
+    }
+
+    fn fresh_nominal(&mut self) -> String {
+        let nominal = (0..).map(|n| format!("n{}", n))
+            .find(|name| !self.names.contains(name) && !self.nominals.contains(name))
+            .unwrap();
+        self.nominals.insert(nominal.clone());
+        nominal
+    }
+
+    /// Adds a world on the branch ending at `terminal`, accessible from
+    /// `from` by the relation of each of `indices`.
+    fn new_world(&mut self, from: usize, terminal: usize, indices: &[String]) -> Result<usize, String> {
+        if self.worlds.size() >= MAX_WORLDS {
+            self.limit_reached = true;
+            return Err(format!("World limit of {} reached, tableau may be infinite", MAX_WORLDS));
+        }
+        let new_world = self.worlds.add_world();
+        self.worlds.get_node_mut(new_world).unwrap().set_origin(terminal);
+        for index in indices {
+            self.worlds.add_labelled_edge(from, new_world, index);
+        }
+        Ok(new_world)
+    }
+
+    /// Whether ◇ over the relations of `indices` waits until nothing else
+    /// applies, to be witnessed by a world already there where it can: on a
+    /// transitive frame ◻◇A would otherwise make new worlds forever.
+    fn reuses_worlds(&self, indices: &[String]) -> bool {
+        indices == [String::new()] && self.modal_options.tau() && !self.modal_options.functional()
+    }
+
+    /// Witnesses one postponed ◇A on every open branch, once nothing else
+    /// applies: by a world already on the branch where A holds if the edge
+    /// to it owes nothing, which blocks the new world, otherwise by a new
+    /// world. Returns whether anything was added to the tableau.
+    fn apply_possibilities(&mut self) -> Result<bool, String> {
+        let mut changed = false;
+        let Some(open_branches) = self.tableau.unclosed_branches() else {
+            return Ok(false);
+        };
+
+        for branch in open_branches {
+            let terminal = *branch.last().unwrap();
+            let frame = self.branch_frame(&branch);
+            for idx in branch.iter() {
+                let node = self.tableau.get_node(*idx).unwrap();
+                if *node.state() != FormulaState::Postponed { continue; }
+                let world = node.world();
+                let Expansion::Possibility(indices, formula) = self.implement_instructions(parse_formula(node.formula())?)? else {
+                    return Err(format!("Only possibilities are postponed, found {}", node.formula()));
+                };
+                if is_eventuality(&formula)? { continue; }
+                let local = frame.local(world);
+                let witnessed = frame.common_successors(local, &indices).iter()
+                    .any(|w| self.holds_at(&branch, &frame, &formula, *w));
+                if witnessed { continue; }
+
+                let options: Vec<Vec<(usize, usize)>> = (0..frame.frame.size())
+                    .filter(|w| self.holds_at(&branch, &frame, &formula, *w))
+                    .map(|w| vec![(local, w)])
+                    .collect();
+                if !self.add_free_edges(&branch, &frame, options)? {
+                    let new_world = self.new_world(world, terminal, &indices)?;
+                    self.tableau.new_node_from(terminal, formula, new_world);
+                }
+                changed = true;
+                break;
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Takes one step towards each postponed eventuality, ¬C_G p, on every
+    /// open branch, once nothing else applies. Each step gets a fresh world
+    /// rather than reusing one, or an eventuality could be put off forever
+    /// around a loop. A world with the same formulas as a world earlier in
+    /// its chain of steps only repeats that world's choices, and any
+    /// fulfilment from it is one from the earlier world too, so its branch
+    /// closes instead.
+    fn apply_eventualities(&mut self) -> Result<bool, String> {
+        let mut changed = false;
+        let Some(open_branches) = self.tableau.unclosed_branches() else {
+            return Ok(false);
+        };
+
+        for branch in open_branches {
+            let terminal = *branch.last().unwrap();
+            let frame = self.branch_frame(&branch);
+            for idx in branch.iter() {
+                let node = self.tableau.get_node(*idx).unwrap();
+                if *node.state() != FormulaState::Postponed { continue; }
+                let world = node.world();
+                let Expansion::Possibility(indices, formula) = self.implement_instructions(parse_formula(node.formula())?)? else {
+                    return Err(format!("Only possibilities are postponed, found {}", node.formula()));
                 };
-                if instructions.variables().len() < 2 {
-                    tracing::error!("Unable to parse {}: {}. Too few variables", instructions.operators(), instructions.variables().join(", "));
-                    exit(1);
-                }
-                let ant = "¬".to_string() + instructions.variables().first().unwrap();
-                let cons = instructions.variables().first().unwrap();
-                // get all terminals relative to node_id
-                if let Some(terminals) = self.tableau.terminal_unclosed(node_id) {
-                    for terminal in terminals {
-                        let parent_world = self.tableau.get_node(node_id)
-                            .unwrap()
-                            .world();
-                        self.tableau.new_node_from(node_id, ant.clone(), parent_world);
-                        self.tableau.new_node_from(node_id, cons.clone(), parent_world);
+                if !is_eventuality(&formula)? { continue; }
+                let stepped = frame.common_successors(frame.local(world), &indices).iter()
+                    .any(|w| {
+                        frame.members(*w).iter().any(|member| self.eventuality_parent.get(member) == Some(&world))
+                            && self.holds_at(&branch, &frame, &formula, *w)
+                    });
+                if stepped { continue; }
+
+                if self.repeats_ancestor(&branch, world) {
+                    self.tableau.get_node_mut(terminal).unwrap().close();
+                } else {
+                    let new_world = self.new_world(world, terminal, &indices)?;
+                    self.eventuality_parent.insert(new_world, world);
+                    self.tableau.new_node_from(terminal, formula, new_world);
+                }
+                changed = true;
+                break;
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Whether some world earlier in the eventuality chain of `world` has the
+    /// same formulas on the branch.
+    fn repeats_ancestor(&self, branch: &[usize], world: usize) -> bool {
+        let formulas_at = |world: usize| -> BTreeSet<&String> {
+            branch.iter()
+                .map(|idx| self.tableau.get_node(*idx).unwrap())
+                .filter(|node| node.relation().is_none() && node.world() == world)
+                .map(|node| node.formula())
+                .collect()
+        };
+        let formulas = formulas_at(world);
+        let mut ancestor = world;
+        while let Some(parent) = self.eventuality_parent.get(&ancestor) {
+            ancestor = *parent;
+            if formulas_at(ancestor) == formulas {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Whether `formula` is written on the branch at any world standing for
+    /// the frame world `local`.
+    fn holds_at(&self, branch: &[usize], frame: &BranchFrame, formula: &str, local: usize) -> bool {
+        frame.members(local).iter()
+            .any(|world| self.tableau.branch_contains(branch, formula, *world))
+    }
+
+    /// Whether the edges between frame worlds, and those the frame
+    /// conditions then bring, can be added to the branch without owing any
+    /// world a formula: every necessity already holds wherever they take it.
+    /// Adding such edges keeps the branch as it is, true in its model.
+    fn owes_nothing(&self, branch: &[usize], frame: &BranchFrame, edges: &[(usize, usize)]) -> Result<bool, String> {
+        let edges: Vec<(usize, usize)> = edges.iter()
+            .map(|(v, w)| (frame.world(*v), frame.world(*w)))
+            .collect();
+        let extended = self.extended_frame(branch, &edges);
+        for (indices, formula, world) in self.waiting_necessities(branch)? {
+            let owed = extended.common_successors(extended.local(world), &indices).into_iter()
+                .any(|accessible| !self.holds_at(branch, &extended, &formula, accessible));
+            if owed {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Rules for the frame conditions that edges cannot close a frame under.
+    /// Each open branch gets at most one rule per call, and the result is
+    /// whether anything was added.
+    fn apply_frame_rules(&mut self) -> Result<bool, String> {
+        let mut changed = false;
+        let Some(open_branches) = self.tableau.unclosed_branches() else {
+            return Ok(false);
+        };
+
+        for branch in open_branches {
+            let terminal = *branch.last().unwrap();
+            let frame = self.branch_frame(&branch);
+            let forks: Vec<(usize, usize)> = (0..frame.frame.size())
+                .flat_map(|x| {
+                    let successors = frame.successors(x, "");
+                    successors.iter()
+                        .flat_map(|y| successors.iter().map(move |z| (*y, *z)))
+                        .collect::<Vec<(usize, usize)>>()
+                })
+                .collect();
+
+            if self.modal_options.dense() {
+                // ◻◻p ⊃ ◻p: whatever holds two steps on holds one step on,
+                // ¬◇p being ◻¬p
+                let mut dense = None;
+                for idx in branch.iter() {
+                    let node = self.tableau.get_node(*idx).unwrap();
+                    if *node.state() != FormulaState::WaitingNewWorlds || !node.formula().starts_with('◻') { continue; }
+                    let operand = parse_formula(node.formula())?.variables()[0].clone();
+                    if is_necessity(&operand)? && !self.tableau.branch_contains(&branch, &operand, node.world()) {
+                        dense = Some((operand, node.world()));
+                        break;
                     }
-                }; // do something if no terminal unclosed?
-            },
-            "¬" => todo!(),
-            "◻" => todo!(),
-            "◇" => todo!(),
-            "" => todo!(),
-            _ => {
-                tracing::error!("No instructions found for {}", instructions.operators());
-                exit(1);
+                }
+                if let Some((operand, world)) = dense {
+                    self.tableau.new_node_from(terminal, operand, world);
+                    changed = true;
+                    continue;
+                }
+
+                // every edge x → z has a world in between, one already there
+                // if the edges to and from it owe nothing, otherwise a new one
+                let direct = (0..frame.frame.size())
+                    .flat_map(|x| frame.successors(x, "").into_iter().map(move |z| (x, z)))
+                    .find(|(x, z)| !frame.successors(*x, "").iter().any(|y| frame.frame.adj_test(*y, *z).unwrap()));
+                if let Some((x, z)) = direct {
+                    let options: Vec<Vec<(usize, usize)>> = [z, x].into_iter()
+                        .chain((0..frame.frame.size()).filter(|y| *y != x && *y != z))
+                        .map(|y| vec![(x, y), (y, z)])
+                        .collect();
+                    if !self.add_free_edges(&branch, &frame, options)? {
+                        let new_world = self.new_world(frame.world(x), terminal, &[String::new()])?;
+                        self.worlds.add_edge(new_world, frame.world(z));
+                    }
+                    changed = true;
+                    continue;
+                }
+            }
+
+            if self.modal_options.functional() {
+                // two successors of one world are the same world
+                if let Some((y, z)) = forks.iter().find(|(y, z)| y < z) {
+                    let relation = Relation::Identity(frame.world(*y), frame.world(*z));
+                    self.tableau.new_relation_from(terminal, relation);
+                    changed = true;
+                    continue;
+                }
+            }
+
+            if self.modal_options.connected() {
+                // two successors of one world see one another or coincide,
+                // and with reflexivity coinciding is a case of seeing
+                let unconnected = forks.iter().find(|(y, z)| {
+                    y < z && !frame.frame.adj_test(*y, *z).unwrap() && !frame.frame.adj_test(*z, *y).unwrap()
+                });
+                if let Some((y, z)) = unconnected {
+                    let (y, z) = (frame.world(*y), frame.world(*z));
+                    self.tableau.new_relation_from(terminal, Relation::Access(y, z));
+                    self.tableau.new_relation_from(terminal, Relation::Access(z, y));
+                    if !self.modal_options.rho() {
+                        self.tableau.new_relation_from(terminal, Relation::Identity(y, z));
+                    }
+                    changed = true;
+                    continue;
+                }
+            }
+
+            if self.modal_options.convergent() {
+                // two successors of one world, or one twice, share a
+                // successor, one already there if the edges to it owe
+                // nothing, otherwise a new one
+                let diverging = forks.iter().find(|(y, z)| {
+                    !frame.successors(*y, "").iter().any(|w| frame.frame.adj_test(*z, *w).unwrap())
+                });
+                if let Some((y, z)) = diverging {
+                    let options: Vec<Vec<(usize, usize)>> = (0..frame.frame.size())
+                        .map(|w| vec![(*y, w), (*z, w)])
+                        .collect();
+                    if !self.add_free_edges(&branch, &frame, options)? {
+                        let new_world = self.new_world(frame.world(*y), terminal, &[String::new()])?;
+                        self.worlds.add_edge(frame.world(*z), new_world);
+                    }
+                    changed = true;
+                    continue;
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Adds the edges of the first of `options` that owe nothing to the
+    /// branch, those needing fewest new edges first, returning whether one
+    /// did.
+    fn add_free_edges(&mut self, branch: &[usize], frame: &BranchFrame, mut options: Vec<Vec<(usize, usize)>>) -> Result<bool, String> {
+        options.sort_by_key(|edges| edges.iter().filter(|(v, w)| !frame.frame.adj_test(*v, *w).unwrap()).count());
+        for edges in options {
+            if self.owes_nothing(branch, frame, &edges)? {
+                let mut parent = *branch.last().unwrap();
+                for (v, w) in edges {
+                    if !frame.frame.adj_test(v, w).unwrap() {
+                        parent = self.tableau.new_relation_from(parent, Relation::Access(frame.world(v), frame.world(w)));
+                    }
+                }
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn branch_frame(&self, branch: &[usize]) -> BranchFrame {
+        self.extended_frame(branch, &[])
+    }
+
+    /// The frame of the branch with edges between tableau worlds added to
+    /// the relation of ◻ before closing it.
+    fn extended_frame(&self, branch: &[usize], edges: &[(usize, usize)]) -> BranchFrame {
+        let identities = self.tableau.branch_identities(branch);
+        let canonical = |world: usize| *identities.get(&world).unwrap_or(&world);
+
+        let mut members: Vec<Vec<usize>> = Vec::new();
+        let mut local: HashMap<usize, usize> = HashMap::new();
+        for world in self.worlds.node_ids() {
+            if !branch.contains(&self.worlds.get_node(world).unwrap().origin()) {
+                continue;
+            }
+            // canonical worlds are the lowest of their class, so come first
+            let idx = match local.get(&canonical(world)) {
+                Some(idx) => *idx,
+                None => {
+                    members.push(Vec::new());
+                    members.len() - 1
+                }
+            };
+            members[idx].push(world);
+            local.insert(world, idx);
+        }
+
+        let mut frame = Graph::<node::World>::new(members.len());
+        let labels: Vec<String> = std::iter::once(String::new())
+            .chain(self.indices.iter().cloned())
+            .collect();
+        for label in labels.iter() {
+            for (world, idx) in local.iter() {
+                for accessible in self.worlds.labelled_successors(*world, label).unwrap() {
+                    if let Some(idx_prime) = local.get(&accessible) {
+                        frame.add_labelled_edge(*idx, *idx_prime, label);
+                    }
+                }
+            }
+        }
+        for relation in self.tableau.branch_relations(branch) {
+            if let Relation::Access(v, w) = relation {
+                frame.add_edge(local[&v], local[&w]);
+            }
+        }
+        for (v, w) in edges {
+            frame.add_edge(local[v], local[w]);
+        }
+        for label in labels.iter() {
+            frame.implement_labelled_modals(label, &self.index_options(label).edge_closures());
+        }
+        BranchFrame { members, local, frame }
+    }
+
+    fn build_countermodel(&self, branch: &[usize]) -> Result<Countermodel, String> {
+        let frame = self.branch_frame(branch);
+        let mut valuation: Vec<HashSet<String>> = vec![HashSet::new(); frame.members.len()];
+        let mut domains: Vec<BTreeSet<String>> = vec![BTreeSet::new(); frame.members.len()];
+
+        for idx in branch {
+            let node = self.tableau.get_node(*idx).unwrap();
+            if node.relation().is_some() || !parse_formula(node.formula())?.operators().is_empty()
+                || node.formula() == VERUM {
+                continue;
+            }
+            match existent(node.formula())? {
+                Some(name) => { domains[frame.local(node.world())].insert(name); },
+                None => { valuation[frame.local(node.world())].insert(node.formula().clone()); }
+            }
+        }
+
+        // worlds still without successors have no necessities, so a loop on
+        // them changes nothing and makes a serial frame
+        let mut worlds = frame.frame;
+        // each world is labelled with its least nominal, and with ↓ about
+        // every world needs a name for its binders to take
+        let mut fresh = (0..).map(|n| format!("n{}", n))
+            .filter(|name| !self.names.contains(name) && !self.nominals.contains(name));
+        for (local, atoms) in valuation.iter().enumerate() {
+            let name = match self.nominals.iter().find(|nominal| atoms.contains(*nominal)) {
+                Some(nominal) => Some(nominal.clone()),
+                None if self.binding => fresh.next(),
+                None => None
+            };
+            worlds.get_node_mut(local).unwrap().set_name(name);
+        }
+        for label in std::iter::once("").chain(self.indices.iter().map(|index| index.as_str())) {
+            if self.index_options(label).eta() {
+                worlds.implement_labelled_modals(label, &ModalOptions::new(false, false, false, true));
             }
         }
+        let countermodel = Countermodel::new(worlds, valuation).with_frame(self.modal_options.frame_properties());
+        Ok(match self.domains {
+            Domains::Constant => countermodel.with_domain(self.branch_constants(branch)?),
+            _ => countermodel.with_domains(domains)
+        })
     }
 
-    fn build_countermodel(&self, branch: Vec<usize>) {
-        todo!()
+}
+
+/// Whether a normalized formula is a necessity, ◻A or ¬◇A.
+fn is_necessity(formula: &str) -> Result<bool, String> {
+    let instructions = parse_formula(formula)?;
+    Ok(match instructions.operators().as_str() {
+        "◻" => true,
+        "¬" => parse_formula(&instructions.variables()[0])?.operators() == "◇",
+        _ => false
+    })
+}
+
+/// Indices of the relations a modal instruction quantifies over: the
+/// empty index for ◻ and ◇, the index of `[i]` and `<i>`, and each agent's
+/// knowledge for group modalities.
+fn relations(instructions: &Instructions) -> Vec<String> {
+    match instructions.operators().as_str() {
+        "◻" | "◇" => vec![String::new()],
+        "[]" | "<>" => vec![instructions.index().clone()],
+        _ => instructions.group().iter().map(|agent| knowledge(agent)).collect()
     }
+}
 
+/// Whether `formula` is ¬C_G p, which holds only if ¬p is eventually reached.
+fn is_eventuality(formula: &str) -> Result<bool, String> {
+    let instructions = parse_formula(formula)?;
+    Ok(instructions.operators() == "¬" && parse_formula(&instructions.variables()[0])?.operators() == "C_")
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::graphs::{FrameProperties, FrameProperty};
+
+    fn eval(options: ModalOptions, formulas: &[&str]) -> Option<Countermodel> {
+        let formulas = formulas.iter().map(|f| f.to_string()).collect();
+        Model::new(options, formulas).unwrap().eval_tableau().unwrap()
+    }
 
     #[test]
     fn from_file() {
@@ -123,4 +1380,503 @@ mod tests {
     fn from_file_err() {
         let model = Model::from_file("adfasdfa").unwrap();
     }
+
+    #[test]
+    fn limits() {
+        // a world for each ◇ and the root are one too many
+        let wide = (0..MAX_WORLDS).map(|idx| format!("◇p{}", idx)).collect::<Vec<String>>().join(" ⋀ ");
+        let k = ModalOptions::new(false, false, false, false);
+        let mut model = Model::new(k, vec![wide.clone(), "q".to_string()]).unwrap();
+        assert!(model.eval_tableau().is_err());
+        assert!(model.limit_reached());
+        assert_eq!(MAX_WORLDS, model.stats().worlds);
+        // as they are for the provers of the other logics
+        let mut model = Model::new(ModalOptions::new_default(), vec![wide, "q".to_string()]).unwrap();
+        model.set_logic(Logic::GL).unwrap();
+        assert!(matches!(model.decide(Method::Tableau).unwrap(), Verdict::Unknown(reason) if reason.contains("World limit")));
+
+        let mut model = Model::new(ModalOptions::new_default(), vec!["p ⊃ q".to_string(), "q".to_string()]).unwrap();
+        model.set_time_limit(Duration::ZERO);
+        assert!(model.eval_tableau().is_err());
+        assert!(model.limit_reached());
+        let arguments = [
+            (Logic::Intuitionistic, ["p ⊃ q", "q"]),
+            (Logic::LP, ["p ⋀ q", "q"]),
+            (Logic::GL, ["◻p", "p"]),
+            (Logic::Classical, ["p □→ q", "q"]),
+            (Logic::Classical, ["F p", "p"])
+        ];
+        for (logic, formulas) in arguments {
+            let mut model = Model::new(ModalOptions::new_default(), formulas.map(String::from).to_vec()).unwrap();
+            model.set_logic(logic).unwrap();
+            model.set_time_limit(Duration::ZERO);
+            assert!(matches!(model.decide(Method::Tableau).unwrap(), Verdict::Unknown(reason) if reason == TIME_LIMIT));
+        }
+    }
+
+    #[test]
+    fn blocking() {
+        let logic = |name: &str| ModalOptions::for_logic(name).unwrap();
+        // ◻◇ would make new worlds forever on a transitive frame, the worlds
+        // there already witness it instead
+        let countermodel = eval(logic("K4"), &["◇p ⋀ ◻◇p", "q"]).unwrap();
+        assert_eq!(2, countermodel.worlds().size());
+        assert!(eval(logic("S4"), &["◻◇p", "◇◻p"]).is_some());
+        assert!(eval(logic("KD4"), &["◻◇p ⋀ ◻◇¬p", "⊥"]).is_some());
+        // as do those a serial frame asks for
+        assert!(eval(logic("KD4"), &["◇◇p"]).is_some());
+        assert!(eval(logic("KD4"), &["◻p ⊃ ◇p"]).is_none());
+        assert!(eval(logic("S4"), &["◻◇p ⋀ ◻(p ⊃ ◻p)", "◇◻p"]).is_none());
+    }
+
+    #[test]
+    fn stats() {
+        let mut model = Model::from_file("data/basic.txt").unwrap();
+        assert_eq!(Method::Sat, model.resolve_method(Method::Auto).unwrap());
+        model.eval_tableau().unwrap();
+        let stats = model.stats();
+        // p ⊃ q, p, ¬q then ¬p | q
+        assert_eq!(TableauStats { nodes: 5, worlds: 1, branches_closed: 2, max_depth: 4 }, stats);
+        assert!(!model.limit_reached());
+    }
+
+    #[test]
+    fn from_tptp() {
+        let mut model = Model::from_file("data/tptp/modus_ponens.p").unwrap();
+        assert_eq!(&vec!["p ⊃ q".to_string(), "p".to_string()], model.premises());
+        assert!(model.eval_tableau().unwrap().is_none());
+
+        let mut model = Model::from_file("data/qmltp/k_axiom.p").unwrap();
+        assert_eq!(Some(&"◻p ⊃ ◻q".to_string()), model.conclusion());
+        assert!(model.eval_tableau().unwrap().is_none());
+
+        let mut model = Model::from_file("data/tptp/socrates.p").unwrap();
+        assert_eq!(Some(&"¬(¬mortal(socrates) ⋀ ¬mortal(plato))".to_string()), model.conclusion());
+        assert!(model.eval_tableau().unwrap().is_none());
+    }
+
+    #[test]
+    fn propositional() {
+        let mut model = Model::from_file("data/basic.txt").unwrap();
+        assert!(model.eval_tableau().unwrap().is_none());
+
+        let countermodel = eval(ModalOptions::new_default(), &["p ⊃ q", "q", "p"]).unwrap();
+        assert!(countermodel.true_atoms(0).unwrap().contains("q"));
+        assert!(!countermodel.true_atoms(0).unwrap().contains("p"));
+
+        assert!(eval(ModalOptions::new_default(), &["(p ⋀ q) ⋁ ¬(p ⋁ ¬q) ⊃ q"]).is_none());
+    }
+
+    #[test]
+    fn modal() {
+        let k = ModalOptions::new(false, false, false, false);
+        assert!(eval(k, &["◻(p ⊃ q) ⊃ (◻p ⊃ ◻q)"]).is_none());
+
+        let k = ModalOptions::new(false, false, false, false);
+        let countermodel = eval(k, &["◻p ⊃ p"]).unwrap();
+        assert_eq!(1, countermodel.worlds().size());
+
+        let t = ModalOptions::new(true, false, false, false);
+        assert!(eval(t, &["◻p ⊃ p"]).is_none());
+
+        let s4 = ModalOptions::new(true, false, true, false);
+        assert!(eval(s4, &["◻p ⊃ ◻◻p"]).is_none());
+
+        let s4 = ModalOptions::new(true, false, true, false);
+        assert!(eval(s4, &["p ⊃ ◻◇p"]).is_some());
+
+        assert!(eval(ModalOptions::new_default(), &["p ⊃ ◻◇p"]).is_none());
+        assert!(eval(ModalOptions::new_default(), &["◻◇p", "◇◻p"]).is_some());
+
+        let d = ModalOptions::new(false, false, false, true);
+        assert!(eval(d, &["◻p ⊃ ◇p"]).is_none());
+    }
+
+    #[test]
+    fn frame_conditions() {
+        let logic = |name: &str| ModalOptions::for_logic(name).unwrap();
+        let valid = [
+            ("K5", "◇p ⊃ ◻◇p"),
+            ("K5", "◇◻p ⊃ ◻p"),
+            ("KD45", "◻p ⊃ ◇p"),
+            ("KD45", "◻p ⊃ ◻◻p"),
+            ("S4.2", "◇◻p ⊃ ◻◇p"),
+            ("S4.3", "◻(◻p ⊃ q) ⋁ ◻(◻q ⊃ p)"),
+            ("K4.3", "◻(p ⋀ ◻p ⊃ q) ⋁ ◻(q ⋀ ◻q ⊃ p)"),
+            ("K+functional", "◇p ⊃ ◻p"),
+            ("K+functional", "◇p ⋀ ◇q ⊃ ◇(p ⋀ q)"),
+            ("K+dense", "◻◻p ⊃ ◻p"),
+            ("K+dense", "◇p ⊃ ◇◇p"),
+            ("K+dense", "◻(◻p ⋀ q) ⊃ ◻p"),
+        ];
+        for (name, formula) in valid {
+            assert!(eval(logic(name), &[formula]).is_none(), "{} should be valid in {}", formula, name);
+        }
+
+        let invalid = [
+            ("K", "◇p ⊃ ◻◇p"),
+            ("KD45", "◻p ⊃ p"),
+            ("D", "◻p ⊃ p"),
+            ("S4", "◇◻p ⊃ ◻◇p"),
+            ("S4.2", "◻(◻p ⊃ q) ⋁ ◻(◻q ⊃ p)"),
+            ("K4", "◻(p ⋀ ◻p ⊃ q) ⋁ ◻(q ⋀ ◻q ⊃ p)"),
+            ("K", "◇p ⊃ ◻p"),
+            ("K+functional", "◻p ⊃ ◇p"),
+            ("K+dense", "◻p ⊃ ◻◻p"),
+        ];
+        for (name, formula) in invalid {
+            assert!(eval(logic(name), &[formula]).is_some(), "{} should be invalid in {}", formula, name);
+        }
+
+        // countermodels are on frames of the logic, forks meeting even where
+        // no necessity asks them to
+        let frames = [
+            ("S4.2", "◻◇p ⊃ ◻p", FrameProperty::Convergent),
+            ("K.2", "◻◇p ⊃ ◻p", FrameProperty::Convergent),
+            ("KD.2", "¬(◇p ⋀ ◇¬p)", FrameProperty::Convergent),
+            ("K+dense", "◻p ⊃ ◻◻p", FrameProperty::Dense),
+            ("K+dense", "◇p ⋀ ◇q ⊃ ◻r", FrameProperty::Dense)
+        ];
+        for (name, formula, property) in frames {
+            let countermodel = eval(logic(name), &[formula]).unwrap();
+            assert!(FrameProperties::check(countermodel.worlds()).holds(property), "{} in {}", formula, name);
+        }
+    }
+
+    #[test]
+    fn indexed() {
+        let k = ModalOptions::new(false, false, false, false);
+        assert!(eval(k, &["[a](p ⊃ q) ⊃ ([a]p ⊃ [a]q)"]).is_none());
+        assert!(eval(k, &["[a]p ⊃ [b]p"]).is_some());
+        // knowledge is S5 and belief KD45 whatever ◻ is
+        assert!(eval(k, &["K_a p ⊃ p"]).is_none());
+        assert!(eval(k, &["¬K_a p ⊃ K_a ¬K_a p"]).is_none());
+        assert!(eval(k, &["B_a p ⊃ p"]).is_some());
+        assert!(eval(k, &["B_a p ⊃ ¬B_a ¬p"]).is_none());
+        assert!(eval(k, &["¬B_a p ⊃ B_a ¬B_a p"]).is_none());
+        assert!(eval(k, &["K_a p ⊃ K_b K_a p"]).is_some());
+
+        // each relation keeps its own edges in the countermodel
+        let countermodel = eval(ModalOptions::new_default(), &["<a>p ⊃ ◇p"]).unwrap();
+        assert_eq!(Some(vec![0]), countermodel.worlds().labelled_successors(0, ""));
+        // [a] shares the S5 conditions of ◻ but not its edges
+        assert_eq!(Some(vec![0, 1]), countermodel.worlds().labelled_successors(0, "a"));
+        assert_eq!(Some(vec![0, 1]), countermodel.worlds().labelled_successors(1, "a"));
+        assert_eq!(Some(vec![1]), countermodel.worlds().labelled_successors(1, ""));
+
+        let mut model = Model::new(k, vec!["[a]p ⊃ p".to_string()]).unwrap();
+        model.set_index_options("a", ModalOptions::for_logic("T").unwrap()).unwrap();
+        assert!(model.eval_tableau().unwrap().is_none());
+        assert!(model.set_index_options("a", ModalOptions::for_logic("S4.3").unwrap()).is_err());
+    }
+
+    #[test]
+    fn groups() {
+        let k = ModalOptions::new(false, false, false, false);
+        let valid = [
+            "E_{a,b} p ⊃ K_b p",
+            "K_a p ⋀ K_b p ⊃ E_{a,b} p",
+            "C_{a,b} p ⊃ K_a K_b K_a p",
+            "C_{a,b} p ⊃ E_{a,b} C_{a,b} p",
+            "K_a p ⊃ C_a p",
+            "K_a p ⊃ D_{a,b} p",
+            "D_a p ⊃ K_a p",
+            // induction: only true because eventualities must be fulfilled
+            "C_{a,b} (p ⊃ E_{a,b} p) ⊃ (p ⊃ C_{a,b} p)",
+        ];
+        for formula in valid {
+            assert!(eval(k, &[formula]).is_none(), "{} should be valid", formula);
+        }
+
+        let invalid = [
+            "K_a p ⋀ K_b p ⊃ C_{a,b} p",
+            "E_{a,b} p ⊃ E_{a,b} E_{a,b} p",
+            "D_{a,b} p ⊃ K_a p",
+            "C_a p ⊃ C_{a,b} p",
+        ];
+        for formula in invalid {
+            assert!(eval(k, &[formula]).is_some(), "{} should be invalid", formula);
+        }
+
+        // ¬p is two steps away, one for each agent
+        let countermodel = eval(k, &["K_a p ⋀ K_b p ⊃ C_{a,b} p"]).unwrap();
+        assert!(countermodel.satisfies("¬E_{a,b} E_{a,b} p", 0).unwrap());
+    }
+
+    #[test]
+    fn temporal() {
+        let k = ModalOptions::new(false, false, false, false);
+        assert!(eval(k, &["p", "G (p ⊃ X p)", "G p"]).is_none());
+        let countermodel = eval(k, &["G F p ⊃ F G p"]).unwrap();
+        assert!(countermodel.loop_start().is_some());
+
+        let model = Model::new(k, vec!["G p".to_string(), "p".to_string()]).unwrap();
+        assert_eq!(Method::Tableau, model.resolve_method(Method::Auto).unwrap());
+        assert!(Model::new(k, vec!["◻p".to_string(), "G p".to_string()]).is_err());
+    }
+
+    #[test]
+    fn first_order() {
+        let k = ModalOptions::new(false, false, false, false);
+        let valid: [&[&str]; 7] = [
+            &["∀x P(x)", "P(a)"],
+            &["P(a)", "∃x P(x)"],
+            &["∀x (P(x) ⊃ Q(x))", "∀x P(x)", "∀x Q(x)"],
+            &["∃x ∀y R(x, y) ⊃ ∀y ∃x R(x, y)"],
+            &["¬∃x P(x) ⊃ ∀x ¬P(x)"],
+            &["∀x P(x) ⊃ ∃x P(x)"],
+            &["◻∀x P(x) ⊃ ∀x ◻P(x)"]
+        ];
+        for formulas in valid {
+            assert!(eval(k, formulas).is_none(), "{:?} should be valid", formulas);
+        }
+
+        let countermodel = eval(k, &["∃x P(x)", "P(a)"]).unwrap();
+        assert_eq!(Some(&BTreeSet::from(["a".to_string(), "c0".to_string()])), countermodel.domain(0));
+        assert!(countermodel.satisfies("P(c0) ⋀ ¬P(a)", 0).unwrap());
+        assert!(eval(k, &["∃x P(x) ⋀ ∃x Q(x) ⊃ ∃x (P(x) ⋀ Q(x))"]).is_some());
+        assert!(eval(k, &["∀x (P(x) ⋁ Q(x)) ⊃ ∀x P(x) ⋁ ∀x Q(x)"]).is_some());
+
+        // every instance has a successor to instantiate again
+        let mut model = Model::new(k, vec!["∀x ∃y R(x, y)".to_string(), "p".to_string()]).unwrap();
+        model.set_instantiation_bound(4);
+        assert!(model.eval_tableau().is_err_and(|err| err.contains("Instantiation bound of 4")));
+        assert!(model.limit_reached());
+        let formulas = vec!["∀x ∃y R(x, y)".to_string(), "∃y ∀x R(x, y)".to_string()];
+        let verdict = Model::new(k, formulas).unwrap().decide(Method::Auto).unwrap();
+        assert!(matches!(verdict, Verdict::Unknown(reason) if reason.contains("Instantiation bound of 32")));
+        assert_eq!(Method::Tableau, model.resolve_method(Method::Auto).unwrap());
+    }
+
+    #[test]
+    fn connectives() {
+        let k = ModalOptions::new(false, false, false, false);
+        let valid: [&[&str]; 9] = [
+            &["p ≡ p"],
+            &["(p ≡ q) ≡ (q ≡ p)"],
+            &["p ≡ q", "p", "q"],
+            &["p ⊕ q", "¬(p ≡ q)"],
+            &["¬(p ⊕ q)", "p ≡ q"],
+            &["⊤"],
+            &["⊥", "q"],
+            &["¬⊥"],
+            &["◻(p ≡ q)", "◻p ≡ ◻q"]
+        ];
+        for formulas in valid {
+            assert!(eval(k, formulas).is_none(), "{:?} should be valid", formulas);
+        }
+        let invalid: [&[&str]; 4] = [
+            &["p ⊕ q", "p"],
+            &["p ⊕ p ⊕ p"],
+            &["⊥"],
+            &["◻p ≡ ◻q", "◻(p ≡ q)"]
+        ];
+        for formulas in invalid {
+            assert!(eval(k, formulas).is_some(), "{:?} should be invalid", formulas);
+        }
+        let countermodel = eval(k, &["p ⊕ q", "p"]).unwrap();
+        assert_eq!("w0: {q} R {}\n", countermodel.to_string());
+
+        // the same answers without the tableau
+        let model = Model::new(k, vec!["p ⊕ q".to_string(), "¬(p ≡ q) ⋀ ⊤".to_string()]).unwrap();
+        assert_eq!(Method::Sat, model.resolve_method(Method::Auto).unwrap());
+        for method in [Method::Sat, Method::TruthTable, Method::Tableau] {
+            let mut model = Model::new(k, vec!["p ⊕ q".to_string(), "¬(p ≡ q) ⋀ ⊤".to_string()]).unwrap();
+            assert!(model.eval(method).unwrap().is_none(), "{:?}", method);
+            let mut model = Model::new(k, vec!["p ≡ q".to_string(), "p ⊕ ⊥".to_string()]).unwrap();
+            assert!(model.eval(method).unwrap().is_some(), "{:?}", method);
+        }
+    }
+
+    #[test]
+    fn conditionals() {
+        let k = ModalOptions::new(false, false, false, false);
+        let t = ModalOptions::new(true, false, false, false);
+        assert!(eval(k, &["p ⥽ q", "q ⥽ r", "p ⥽ r"]).is_none());
+        assert!(eval(k, &["(p ⥽ q) ≡ ◻(¬p ⋁ q)"]).is_none());
+        assert!(eval(t, &["p ⥽ q", "p ⊃ q"]).is_none());
+        assert!(eval(k, &["p ⥽ q", "p ⊃ q"]).is_some());
+        assert!(eval(k, &["p ⊃ q", "p ⥽ q"]).is_some());
+
+        // counterfactuals go to the tableau for VC, whatever the options
+        assert!(eval(k, &["p □→ q", "p ⊃ q"]).is_none());
+        let countermodel = eval(k, &["p □→ q", "¬q □→ ¬p"]).unwrap();
+        assert!(countermodel.spheres_around(0).is_some());
+        assert!(Model::new(k, vec!["G p □→ p".to_string()]).is_err());
+    }
+
+    #[test]
+    fn deontic() {
+        let kd = ModalOptions::for_logic("KD").unwrap();
+        let eval_file = |filename: &str| {
+            let mut model = Model::deontic(kd, Model::read_file(filename).unwrap()).unwrap();
+            model.eval_tableau().unwrap()
+        };
+        let eval_deontic = |formulas: &[&str]| {
+            let mut model = Model::deontic(kd, formulas.iter().map(|f| f.to_string()).collect()).unwrap();
+            model.eval_tableau().unwrap()
+        };
+        assert!(eval_deontic(&["O p ⊃ P p"]).is_none());
+        assert!(eval_deontic(&["O p ⊃ p"]).is_some());
+        assert!(eval_deontic(&["¬O ⊥"]).is_none());
+
+        // Ross: what is obligatory is no more than its consequences
+        assert!(eval_file("data/deontic/ross.txt").is_none());
+        // Chisholm: the contrary to duty imperatives are inconsistent in
+        // SDL, but not as conditional obligations
+        assert!(eval_file("data/deontic/chisholm_sdl.txt").is_none());
+        let countermodel = eval_file("data/deontic/chisholm.txt").unwrap();
+        assert!(countermodel.preferences(0).is_some());
+        assert!(countermodel.satisfies("O(¬t/¬h)", 0).unwrap());
+
+        // deontic detachment, but no factual detachment
+        assert!(eval_deontic(&["O h", "O(t/h)", "O t"]).is_none());
+        assert!(eval_deontic(&["O(¬t/¬h)", "¬h", "O ¬t"]).is_some());
+        assert!(eval_deontic(&["O(p/⊤) ≡ O p"]).is_none());
+        assert!(Model::new(kd, vec!["O(q/p) ⊃ (p □→ q)".to_string()]).is_err());
+    }
+
+    #[test]
+    fn domains() {
+        let k = ModalOptions::new(false, false, false, false);
+        let eval_in = |domains: Domains, formulas: &[&str]| {
+            let formulas = formulas.iter().map(|f| f.to_string()).collect();
+            let mut model = Model::new(k, formulas).unwrap();
+            model.set_domains(domains);
+            model.eval_tableau().unwrap()
+        };
+        let barcan = ["∀x ◻P(x) ⊃ ◻∀x P(x)"];
+        let converse = ["◻∀x P(x) ⊃ ∀x ◻P(x)"];
+        assert!(eval_in(Domains::Constant, &barcan).is_none());
+        assert!(eval_in(Domains::Increasing, &barcan).is_some());
+        assert!(eval_in(Domains::Varying, &barcan).is_some());
+        assert!(eval_in(Domains::Constant, &converse).is_none());
+        assert!(eval_in(Domains::Increasing, &converse).is_none());
+        assert!(eval_in(Domains::Varying, &converse).is_some());
+
+        // something at the accessible world that does not exist here
+        let countermodel = eval_in(Domains::Increasing, &barcan).unwrap();
+        assert_eq!("D(w0) = {}\nD(w1) = {c0}\nw0: {} R {w1}\nw1: {} R {}\n", countermodel.to_string());
+
+        // names need not exist, nor the domain be non-empty
+        assert!(eval_in(Domains::Varying, &["∀x P(x)", "P(a)"]).is_some());
+        assert!(eval_in(Domains::Varying, &["∀x P(x)", "E!(a)", "P(a)"]).is_none());
+        assert!(eval_in(Domains::Varying, &["∀x P(x) ⊃ ∃x P(x)"]).is_some());
+        assert!(eval_in(Domains::Varying, &["∃x E!(x)"]).is_some());
+        assert!(eval_in(Domains::Varying, &["∀x E!(x)"]).is_none());
+        assert!(eval_in(Domains::Increasing, &["E!(a) ⊃ ◻E!(a)"]).is_none());
+        assert!(eval_in(Domains::Varying, &["E!(a) ⊃ ◻E!(a)"]).is_some());
+        assert!(eval_in(Domains::Constant, &["E!(a)"]).is_none());
+        assert!(eval_in(Domains::Varying, &["E!(a)"]).is_some());
+
+        let model = Model::new(k, vec!["E!(a)".to_string()]).unwrap();
+        assert_eq!(Method::Tableau, model.resolve_method(Method::Auto).unwrap());
+    }
+
+    #[test]
+    fn identity() {
+        let k = ModalOptions::new(false, false, false, false);
+        let valid: [&[&str]; 8] = [
+            &["a = a"],
+            &["∀x (x = x)"],
+            &["a = b", "b = a"],
+            &["a = b", "b = c", "a = c"],
+            &["a = b", "P(a)", "P(b)"],
+            &["P(a)", "¬P(b)", "¬(a = b)"],
+            &["∀x ∀y (x = y ⊃ (R(x, a) ⊃ R(y, a)))"],
+            &["∃x (x = a)"]
+        ];
+        for formulas in valid {
+            assert!(eval(k, formulas).is_none(), "{:?} should be valid", formulas);
+        }
+        let countermodel = eval(k, &["a = b", "P(a, c)", "P(b, d)"]).unwrap();
+        assert!(countermodel.satisfies("P(b, c) ⋀ ¬(c = d)", 0).unwrap());
+        assert!(eval(k, &["P(a)", "P(b)", "a = b"]).is_some());
+
+        // rigid identity is necessary, as is distinctness
+        let eval_with = |rigid: bool, formulas: &[&str]| {
+            let formulas = formulas.iter().map(|f| f.to_string()).collect();
+            let mut model = Model::new(k, formulas).unwrap();
+            model.set_rigid_identity(rigid);
+            model.eval_tableau().unwrap()
+        };
+        assert!(eval_with(true, &["a = b", "◻(a = b)"]).is_none());
+        assert!(eval_with(true, &["¬(a = b)", "◻¬(a = b)"]).is_none());
+        assert!(eval_with(true, &["a = b", "◻P(a)", "◻P(b)"]).is_none());
+        let countermodel = eval_with(false, &["a = b", "◻(a = b)"]).unwrap();
+        assert_eq!("D = {a, b}\nw0: {a = b} R {w1}\nw1: {} R {}\n", countermodel.to_string());
+        assert!(eval_with(false, &["a = b", "◻P(a)", "◻P(b)"]).is_some());
+
+        let model = Model::new(k, vec!["a = a".to_string()]).unwrap();
+        assert_eq!(Method::Tableau, model.resolve_method(Method::Auto).unwrap());
+    }
+
+    #[test]
+    fn hybrid() {
+        let k = ModalOptions::new(false, false, false, false);
+        let t = ModalOptions::new(true, false, false, false);
+        let valid: [&[&str]; 7] = [
+            &["@_i p ⊃ (i ⊃ p)"],
+            &["@_i p ⋁ @_i ¬p"],
+            &["@_i p", "@_j i", "@_j p"],
+            &["i ⋀ ◇(i ⋀ p)", "@_i p"],
+            &["@_i ◇j", "@_i ◇(j ⋀ @_j ⊤)"],
+            &["↓x.◇x", "◇⊤"],
+            &["◇(i ⋀ p) ⋀ ◇(i ⋀ q)", "◇(p ⋀ q) ⋁ @_j ⊤"]
+        ];
+        for formulas in valid {
+            assert!(eval(k, formulas).is_none(), "{:?} should be valid", formulas);
+        }
+        assert!(eval(k, &["@_i ◇j", "@_j ◇i"]).is_some());
+        // irreflexivity is expressible, so reflexive frames refute it
+        assert!(eval(k, &["↓x.◻¬x", "⊥"]).is_some());
+        assert!(eval(t, &["↓x.◻¬x", "⊥"]).is_none());
+
+        // the worlds i is true at merge, and countermodels name worlds
+        let countermodel = eval(k, &["◇(i ⋀ p) ⋀ ◇(i ⋀ q)", "@_i r"]).unwrap();
+        assert_eq!("w0: {} R {w1}\nw1 (i): {p, q} R {}\n", countermodel.to_string());
+        let countermodel = eval(k, &["◻p", "↓x.◇◇x"]).unwrap();
+        assert_eq!("w0 (n0): {} R {}\n", countermodel.to_string());
+    }
+
+    #[test]
+    fn intuitionistic() {
+        let s5 = ModalOptions::new_default();
+        let mut model = Model::new(s5, vec!["¬¬p ⊃ p".to_string()]).unwrap();
+        assert!(model.eval(Method::Auto).unwrap().is_none());
+        model.set_logic(Logic::Intuitionistic).unwrap();
+        assert_eq!(Method::Tableau, model.resolve_method(Method::Auto).unwrap());
+        assert!(model.resolve_method(Method::Sat).is_err());
+        let countermodel = model.eval(Method::Auto).unwrap().unwrap();
+        assert!(countermodel.is_intuitionistic());
+
+        let mut model = Model::new(s5, vec!["◻p ⊃ p".to_string()]).unwrap();
+        assert!(model.set_logic(Logic::Intuitionistic).is_err());
+    }
+
+    #[test]
+    fn many_valued() {
+        let s5 = ModalOptions::new_default();
+        let mut model = Model::new(s5, vec!["p".to_string(), "¬p".to_string(), "q".to_string()]).unwrap();
+        model.set_logic(Logic::K3).unwrap();
+        assert!(model.eval(Method::Auto).unwrap().is_none());
+        model.set_logic(Logic::LP).unwrap();
+        assert!(model.eval(Method::Tableau).unwrap().is_some());
+        assert!(model.resolve_method(Method::TruthTable).is_err());
+    }
+
+    #[test]
+    fn provability() {
+        let s5 = ModalOptions::new_default();
+        let mut model = Model::new(s5, vec!["◻(◻p ⊃ p) ⊃ ◻p".to_string()]).unwrap();
+        assert!(model.eval(Method::Auto).unwrap().is_some());
+        model.set_logic(Logic::GL).unwrap();
+        assert!(model.eval(Method::Auto).unwrap().is_none());
+        model.set_logic(Logic::Grz).unwrap();
+        assert!(model.eval(Method::Auto).unwrap().is_some());
+
+        let mut model = Model::new(s5, vec!["G p ⊃ p".to_string()]).unwrap();
+        model.set_logic(Logic::GL).unwrap();
+        assert!(model.eval(Method::Auto).is_err());
+    }
 }
@@ -0,0 +1,462 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use crate::ast::{Expr, GroupModality, knowledge};
+use crate::configs::NormalForm;
+use crate::parser::parse_expr;
+
+/// Converts a formula to the requested normal form and renders it.
+pub fn convert(formula: &str, form: NormalForm, definitional: bool) -> Result<String, String> {
+    let expr = parse_expr(formula)?;
+    let output = match form {
+        NormalForm::Nnf => nnf(&expr).to_string(),
+        NormalForm::Cnf if definitional => tseitin(&expr).to_string(),
+        NormalForm::Cnf => cnf(&expr).to_string(),
+        NormalForm::Dnf => dnf(&expr).to_string()
+    };
+    Ok(output)
+}
+
+/// Negation normal form: no ⊃, ≡ or ⊕, and ¬ only directly in front of
+/// atoms, ¬⊤ and ¬⊥ becoming ⊥ and ⊤.
+/// Negations are pushed through ◻ and ◇ by their duality, and through E_G
+/// as some agent's ◇, and through the unary temporal operators by their
+/// duals, and through quantifiers likewise. C_G, D_G, U and S have no dual
+/// here and keep their negation. A ⥽ B becomes ◻(¬A ⋁ B), and □→ keeps
+/// its negation too, as does conditional obligation. @_i and ↓x are self-dual
+/// and let negations through.
+pub fn nnf(expr: &Expr) -> Expr {
+    match expr {
+        Expr::Atom(_) | Expr::Predicate(..) | Expr::Verum | Expr::Falsum => expr.clone(),
+        Expr::Quantified(quantifier, var, a) => Expr::Quantified(*quantifier, var.clone(), Box::new(nnf(a))),
+        Expr::At(i, a) => Expr::At(i.clone(), Box::new(nnf(a))),
+        Expr::Bind(x, a) => Expr::Bind(x.clone(), Box::new(nnf(a))),
+        Expr::And(a, b) => nnf(a).and(nnf(b)),
+        Expr::Or(a, b) => nnf(a).or(nnf(b)),
+        Expr::Implies(a, b) => negated_nnf(a).or(nnf(b)),
+        Expr::Iff(a, b) => nnf(a).and(nnf(b)).or(negated_nnf(a).and(negated_nnf(b))),
+        Expr::Xor(a, b) => nnf(a).and(negated_nnf(b)).or(negated_nnf(a).and(nnf(b))),
+        Expr::Strict(a, b) => Expr::Necessarily(Box::new(negated_nnf(a).or(nnf(b)))),
+        Expr::Counterfactual(a, b) => Expr::Counterfactual(Box::new(nnf(a)), Box::new(nnf(b))),
+        Expr::Obligation(a, b) => Expr::Obligation(Box::new(nnf(a)), Box::new(nnf(b))),
+        Expr::Necessarily(a) => Expr::Necessarily(Box::new(nnf(a))),
+        Expr::Possibly(a) => Expr::Possibly(Box::new(nnf(a))),
+        Expr::IndexedNecessarily(i, a) => Expr::IndexedNecessarily(i.clone(), Box::new(nnf(a))),
+        Expr::IndexedPossibly(i, a) => Expr::IndexedPossibly(i.clone(), Box::new(nnf(a))),
+        Expr::Group(modality, agents, a) => Expr::Group(*modality, agents.clone(), Box::new(nnf(a))),
+        Expr::Temporal(op, a) => Expr::Temporal(*op, Box::new(nnf(a))),
+        Expr::Until(a, b) => Expr::Until(Box::new(nnf(a)), Box::new(nnf(b))),
+        Expr::Since(a, b) => Expr::Since(Box::new(nnf(a)), Box::new(nnf(b))),
+        Expr::Not(a) => negated_nnf(a),
+    }
+}
+
+/// Negation normal form of ¬expr.
+fn negated_nnf(expr: &Expr) -> Expr {
+    match expr {
+        Expr::Atom(_) | Expr::Predicate(..) => expr.clone().negated(),
+        Expr::Verum => Expr::Falsum,
+        Expr::Falsum => Expr::Verum,
+        Expr::Quantified(quantifier, var, a) => Expr::Quantified(quantifier.dual(), var.clone(), Box::new(negated_nnf(a))),
+        Expr::Not(a) => nnf(a),
+        Expr::At(i, a) => Expr::At(i.clone(), Box::new(negated_nnf(a))),
+        Expr::Bind(x, a) => Expr::Bind(x.clone(), Box::new(negated_nnf(a))),
+        Expr::And(a, b) => negated_nnf(a).or(negated_nnf(b)),
+        Expr::Or(a, b) => negated_nnf(a).and(negated_nnf(b)),
+        Expr::Implies(a, b) => nnf(a).and(negated_nnf(b)),
+        Expr::Iff(a, b) => nnf(&Expr::Xor(a.clone(), b.clone())),
+        Expr::Xor(a, b) => nnf(&Expr::Iff(a.clone(), b.clone())),
+        Expr::Strict(a, b) => Expr::Possibly(Box::new(nnf(a).and(negated_nnf(b)))),
+        Expr::Necessarily(a) => Expr::Possibly(Box::new(negated_nnf(a))),
+        Expr::Possibly(a) => Expr::Necessarily(Box::new(negated_nnf(a))),
+        Expr::IndexedNecessarily(i, a) => Expr::IndexedPossibly(i.clone(), Box::new(negated_nnf(a))),
+        Expr::IndexedPossibly(i, a) => Expr::IndexedNecessarily(i.clone(), Box::new(negated_nnf(a))),
+        Expr::Group(GroupModality::Everyone, agents, a) => agents.iter()
+            .map(|agent| Expr::IndexedPossibly(knowledge(agent), Box::new(negated_nnf(a))))
+            .reduce(Expr::or)
+            .expect("Groups have at least one agent"),
+        Expr::Group(modality, agents, a) => Expr::Group(*modality, agents.clone(), Box::new(nnf(a))).negated(),
+        Expr::Temporal(op, a) => Expr::Temporal(op.dual(), Box::new(negated_nnf(a))),
+        Expr::Until(..) | Expr::Since(..) | Expr::Counterfactual(..) | Expr::Obligation(..) => nnf(expr).negated(),
+    }
+}
+
+/// Conjunctive normal form by distribution. Modal subformulas are kept whole,
+/// with their insides in negation normal form. Clauses with ⊤ or with a
+/// literal and its negation are dropped, and so is ⊥ from the others.
+pub fn cnf(expr: &Expr) -> Expr {
+    join(&simplify(clauses(&nnf(expr), true), true), true)
+}
+
+/// Disjunctive normal form by distribution. Modal subformulas are kept whole,
+/// with their insides in negation normal form. Conjunctions with ⊥ or with a
+/// literal and its negation are dropped, and so is ⊤ from the others.
+pub fn dnf(expr: &Expr) -> Expr {
+    join(&simplify(clauses(&nnf(expr), false), false), false)
+}
+
+/// Clauses of an NNF formula: disjunctions if `conjunctive`, otherwise conjunctions.
+fn clauses(expr: &Expr, conjunctive: bool) -> Vec<Vec<Expr>> {
+    match (expr, conjunctive) {
+        (Expr::And(a, b), true) | (Expr::Or(a, b), false) => {
+            let mut output = clauses(a, conjunctive);
+            for clause in clauses(b, conjunctive) {
+                if !output.contains(&clause) {
+                    output.push(clause);
+                }
+            }
+            output
+        },
+        (Expr::Or(a, b), true) | (Expr::And(a, b), false) => {
+            let mut output = Vec::new();
+            for left in clauses(a, conjunctive) {
+                for right in clauses(b, conjunctive) {
+                    let mut clause = left.clone();
+                    for literal in right {
+                        if !clause.contains(&literal) {
+                            clause.push(literal);
+                        }
+                    }
+                    if !output.contains(&clause) {
+                        output.push(clause);
+                    }
+                }
+            }
+            output
+        },
+        _ => vec![vec![expr.clone()]]
+    }
+}
+
+/// Drops the clauses decided by a constant or by a literal and its negation,
+/// and the constants deciding nothing from the rest. A clause left empty
+/// decides the whole formula, and is all that remains.
+fn simplify(clauses: Vec<Vec<Expr>>, conjunctive: bool) -> Vec<Vec<Expr>> {
+    // ⊤ satisfies a disjunction and ⊥ adds nothing to it, and the reverse
+    // for a conjunction
+    let (deciding, idle) = if conjunctive { (Expr::Verum, Expr::Falsum) } else { (Expr::Falsum, Expr::Verum) };
+    let clauses: Vec<Vec<Expr>> = clauses.into_iter()
+        .filter(|clause| {
+            !clause.contains(&deciding)
+                && !clause.iter().any(|literal| clause.contains(&literal.clone().negated()))
+        })
+        .map(|clause| clause.into_iter().filter(|literal| *literal != idle).collect())
+        .collect();
+    if clauses.iter().any(|clause| clause.is_empty()) { vec![Vec::new()] } else { clauses }
+}
+
+/// Joins the clauses, no clauses being ⊤ in a CNF and an empty clause ⊥,
+/// and the reverse in a DNF.
+fn join(clauses: &[Vec<Expr>], conjunctive: bool) -> Expr {
+    let connect = |conjoin: bool| move |a: Expr, b: Expr| if conjoin { a.and(b) } else { a.or(b) };
+    let (none, empty) = if conjunctive { (Expr::Verum, Expr::Falsum) } else { (Expr::Falsum, Expr::Verum) };
+    clauses.iter()
+        .map(|clause| clause.iter().cloned().reduce(connect(!conjunctive)).unwrap_or(empty.clone()))
+        .reduce(connect(conjunctive))
+        .unwrap_or(none)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Literal {
+    atom: String,
+    positive: bool
+}
+
+impl Literal {
+    pub fn new(atom: &str, positive: bool) -> Literal {
+        Literal { atom: atom.to_string(), positive }
+    }
+
+    pub fn atom(&self) -> &String {
+        &self.atom
+    }
+
+    pub fn positive(&self) -> bool {
+        self.positive
+    }
+
+    pub fn negated(&self) -> Literal {
+        Literal { atom: self.atom.clone(), positive: !self.positive }
+    }
+
+    fn to_expr(&self) -> Expr {
+        if self.positive {
+            Expr::atom(&self.atom)
+        } else {
+            Expr::atom(&self.atom).negated()
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.positive {
+            write!(f, "{}", self.atom)
+        } else {
+            write!(f, "¬{}", self.atom)
+        }
+    }
+}
+
+/// A set of clauses read as their conjunction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cnf {
+    clauses: Vec<Vec<Literal>>,
+    definitions: Vec<(String, Expr)>
+}
+
+impl Cnf {
+    pub fn new(clauses: Vec<Vec<Literal>>) -> Cnf {
+        Cnf { clauses, definitions: Vec::new() }
+    }
+
+    pub fn clauses(&self) -> &Vec<Vec<Literal>> {
+        &self.clauses
+    }
+
+    /// Fresh atoms introduced by a definitional translation, with the
+    /// subformula each one stands for.
+    pub fn definitions(&self) -> &Vec<(String, Expr)> {
+        &self.definitions
+    }
+
+    pub fn to_expr(&self) -> Option<Expr> {
+        let clauses: Vec<Vec<Expr>> = self.clauses.iter()
+            .map(|clause| clause.iter().map(|literal| literal.to_expr()).collect())
+            .collect();
+        if clauses.is_empty() || clauses.iter().any(|clause| clause.is_empty()) {
+            return None;
+        }
+        Some(join(&clauses, true))
+    }
+}
+
+impl fmt::Display for Cnf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.to_expr() {
+            Some(expr) => write!(f, "{}", expr)?,
+            None => write!(f, "{} clauses", self.clauses.len())?
+        }
+        for (name, definition) in self.definitions.iter() {
+            write!(f, "\n  {} ≔ {}", name, definition)?;
+        }
+        Ok(())
+    }
+}
+
+/// Definitional (Tseitin) CNF: one fresh atom per compound subformula, so the
+/// result grows linearly with the formula. Equisatisfiable with `expr`, not
+/// equivalent. Modal subformulas are treated as atoms.
+pub fn tseitin(expr: &Expr) -> Cnf {
+    let mut translation = Tseitin {
+        taken: expr.atoms(),
+        counter: 0,
+        cnf: Cnf::new(Vec::new())
+    };
+    let root = translation.define(expr);
+    translation.cnf.clauses.push(vec![root]);
+    translation.cnf
+}
+
+struct Tseitin {
+    taken: BTreeSet<String>,
+    counter: usize,
+    cnf: Cnf
+}
+
+impl Tseitin {
+    fn fresh(&mut self) -> String {
+        loop {
+            self.counter += 1;
+            let name = format!("d{}", self.counter);
+            if !self.taken.contains(&name) {
+                return name;
+            }
+        }
+    }
+
+    /// A fresh atom fixed by a unit clause to the value of ⊤ or ⊥.
+    fn constant(&mut self, expr: &Expr) -> Literal {
+        if let Some((name, _)) = self.cnf.definitions.iter().find(|(_, definition)| definition == expr) {
+            return Literal::new(name, true);
+        }
+        let name = self.fresh();
+        self.cnf.clauses.push(vec![Literal::new(&name, *expr == Expr::Verum)]);
+        self.cnf.definitions.push((name.clone(), expr.clone()));
+        Literal::new(&name, true)
+    }
+
+    /// Literal equivalent to `expr` given the clauses added so far.
+    fn define(&mut self, expr: &Expr) -> Literal {
+        let (a, b, op) = match expr {
+            Expr::Atom(name) => return Literal::new(name, true),
+            Expr::Verum | Expr::Falsum => return self.constant(expr),
+            Expr::Predicate(..) | Expr::Quantified(..) => return Literal::new(&expr.to_string(), true),
+            Expr::Not(a) => return self.define(a).negated(),
+            Expr::Necessarily(_) | Expr::Possibly(_)
+            | Expr::IndexedNecessarily(..) | Expr::IndexedPossibly(..)
+            | Expr::Group(..) | Expr::Temporal(..) | Expr::Strict(..) | Expr::Counterfactual(..) | Expr::Obligation(..)
+            | Expr::Until(..) | Expr::Since(..) | Expr::At(..) | Expr::Bind(..) => return Literal::new(&expr.to_string(), true),
+            Expr::And(a, b) => (a, b, '⋀'),
+            Expr::Or(a, b) => (a, b, '⋁'),
+            Expr::Implies(a, b) => (a, b, '⊃'),
+            Expr::Iff(a, b) => (a, b, '≡'),
+            Expr::Xor(a, b) => (a, b, '⊕'),
+        };
+        if let Some((name, _)) = self.cnf.definitions.iter().find(|(_, definition)| definition == expr) {
+            return Literal::new(name, true);
+        }
+
+        let left = self.define(a);
+        let right = self.define(b);
+        let name = self.fresh();
+        let d = Literal::new(&name, true);
+        let new_clauses = match op {
+            // d ↔ a ⋀ b
+            '⋀' => vec![
+                vec![d.negated(), left.clone()],
+                vec![d.negated(), right.clone()],
+                vec![d.clone(), left.negated(), right.negated()]
+            ],
+            // d ↔ a ⋁ b
+            '⋁' => vec![
+                vec![d.negated(), left.clone(), right.clone()],
+                vec![d.clone(), left.negated()],
+                vec![d.clone(), right.negated()]
+            ],
+            // d ↔ (a ≡ b)
+            '≡' => vec![
+                vec![d.negated(), left.negated(), right.clone()],
+                vec![d.negated(), left.clone(), right.negated()],
+                vec![d.clone(), left.clone(), right.clone()],
+                vec![d.clone(), left.negated(), right.negated()]
+            ],
+            // d ↔ (a ⊕ b)
+            '⊕' => vec![
+                vec![d.negated(), left.clone(), right.clone()],
+                vec![d.negated(), left.negated(), right.negated()],
+                vec![d.clone(), left.negated(), right.clone()],
+                vec![d.clone(), left.clone(), right.negated()]
+            ],
+            // d ↔ a ⊃ b
+            _ => vec![
+                vec![d.negated(), left.negated(), right.clone()],
+                vec![d.clone(), left.clone()],
+                vec![d.clone(), right.negated()]
+            ]
+        };
+        self.cnf.clauses.extend(new_clauses);
+        self.cnf.definitions.push((name, expr.clone()));
+        d
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::*;
+
+    fn valuations(atoms: &[String]) -> Vec<HashMap<String, bool>> {
+        (0..1_usize << atoms.len())
+            .map(|row| atoms.iter().enumerate()
+                .map(|(idx, atom)| (atom.clone(), row & (1 << idx) != 0))
+                .collect())
+            .collect()
+    }
+
+    fn equivalent(a: &Expr, b: &Expr) -> bool {
+        let atoms: Vec<String> = a.atoms().union(&b.atoms()).cloned().collect();
+        valuations(&atoms).iter().all(|v| a.eval(v).unwrap() == b.eval(v).unwrap())
+    }
+
+    #[test]
+    fn negation_normal_form() {
+        let expr = parse_expr("¬(p ⊃ ◻(q ⋀ ¬r))").unwrap();
+        assert_eq!("p ⋀ ◇(¬q ⋁ r)", nnf(&expr).to_string());
+        let expr = parse_expr("¬◇¬¬p").unwrap();
+        assert_eq!("◻¬p", nnf(&expr).to_string());
+        let expr = parse_expr("(p ⥽ q) ⋀ ¬(q ⥽ ¬r) ⋀ ¬(p □→ ¬q)").unwrap();
+        assert_eq!("◻(¬p ⋁ q) ⋀ ◇(q ⋀ r) ⋀ ¬(p □→ ¬q)", nnf(&expr).to_string());
+        let expr = parse_expr("¬E_{a,b}(p ⊃ q) ⋀ ¬C_{a,b} ¬¬p").unwrap();
+        assert_eq!("(<K_a>(p ⋀ ¬q) ⋁ <K_b>(p ⋀ ¬q)) ⋀ ¬C_{a,b} p", nnf(&expr).to_string());
+        let expr = parse_expr("¬G (p ⊃ X q) ⋁ ¬Y ¬(p U q)").unwrap();
+        assert_eq!("F (p ⋀ X ¬q) ⋁ Z (p U q)", nnf(&expr).to_string());
+        let expr = parse_expr("¬(p ≡ ¬⊤) ⋀ (q ⊕ r)").unwrap();
+        assert_eq!("((p ⋀ ⊤) ⋁ (¬p ⋀ ⊥)) ⋀ ((q ⋀ ¬r) ⋁ (¬q ⋀ r))", nnf(&expr).to_string());
+    }
+
+    #[test]
+    fn distribution() {
+        let expr = parse_expr("(p ⋀ q) ⋁ (r ⊃ s)").unwrap();
+        assert_eq!("(p ⋁ ¬r ⋁ s) ⋀ (q ⋁ ¬r ⋁ s)", cnf(&expr).to_string());
+        let expr = parse_expr("p ⋀ (q ⋁ ¬(r ⋀ s))").unwrap();
+        assert_eq!("(p ⋀ q) ⋁ (p ⋀ ¬r) ⋁ (p ⋀ ¬s)", dnf(&expr).to_string());
+
+        for formula in ["¬(p ⊃ (q ⋁ ¬r)) ⋁ (q ⋀ ¬p)", "(p ⊃ q) ⊃ (¬q ⊃ ¬p)", "p ⋀ ¬p", "(p ≡ q) ⊕ ¬(r ≡ ⊤)", "¬(p ⊕ ⊥) ≡ q",
+            "(p ⋁ ⊥) ⋀ (q ⋁ ⊤)", "p ⋁ ¬p ⋁ ⊥", "(p ⋀ ⊥) ⋁ (q ⋀ ⊤ ⋀ ¬q)"] {
+            let expr = parse_expr(formula).unwrap();
+            assert!(equivalent(&expr, &nnf(&expr)), "{}", formula);
+            assert!(equivalent(&expr, &cnf(&expr)), "{}", formula);
+            assert!(equivalent(&expr, &dnf(&expr)), "{}", formula);
+        }
+    }
+
+    #[test]
+    fn constants() {
+        let cnf_of = |formula: &str| cnf(&parse_expr(formula).unwrap()).to_string();
+        let dnf_of = |formula: &str| dnf(&parse_expr(formula).unwrap()).to_string();
+        assert_eq!("p ⋀ (q ⋁ r)", cnf_of("(p ⋁ ⊥) ⋀ (q ⋁ ⊤) ⋀ (q ⋁ r) ⋀ (r ⋁ ¬r)"));
+        assert_eq!("⊤", cnf_of("p ⋁ ¬p ⋁ q"));
+        assert_eq!("⊤", cnf_of("⊤"));
+        assert_eq!("⊥", cnf_of("(p ⋁ q) ⋀ ¬⊤"));
+        assert_eq!("p ⋁ (q ⋀ r)", dnf_of("(p ⋀ ⊤) ⋁ (q ⋀ ⊥) ⋁ (q ⋀ r) ⋁ (r ⋀ ¬r)"));
+        assert_eq!("⊥", dnf_of("p ⋀ ¬p"));
+        assert_eq!("⊤", dnf_of("p ⋁ ⊤"));
+        assert_eq!("◻p ⋁ ◻¬q", cnf_of("◻p ⋁ ¬◇q ⋁ ⊥"));
+    }
+
+    #[test]
+    fn definitional() {
+        let expr = parse_expr("(p ⋀ q) ⋁ ¬(p ⋀ q)").unwrap();
+        let cnf = tseitin(&expr);
+        // p ⋀ q is defined once and reused
+        assert_eq!(2, cnf.definitions().len());
+        assert_eq!(7, cnf.clauses().len());
+        assert_eq!(&vec![Literal::new("d2", true)], cnf.clauses().last().unwrap());
+
+        // equisatisfiable: every model of expr extends to the definitions
+        let atoms: Vec<String> = expr.atoms().into_iter().collect();
+        let clauses = cnf.to_expr().unwrap();
+        for mut valuation in valuations(&atoms) {
+            for (name, definition) in cnf.definitions() {
+                let value = definition.eval(&valuation).unwrap();
+                valuation.insert(name.clone(), value);
+            }
+            assert_eq!(expr.eval(&valuation).unwrap(), clauses.eval(&valuation).unwrap());
+        }
+
+        let taken = tseitin(&parse_expr("d1 ⋁ d2").unwrap());
+        assert_eq!("d3", taken.definitions()[0].0);
+
+        for formula in ["(p ≡ q) ⊕ ⊤", "¬(p ⊕ q) ⋁ ⊥"] {
+            let expr = parse_expr(formula).unwrap();
+            let cnf = tseitin(&expr);
+            let clauses = cnf.to_expr().unwrap();
+            for mut valuation in valuations(&expr.atoms().into_iter().collect::<Vec<String>>()) {
+                for (name, definition) in cnf.definitions() {
+                    let value = definition.eval(&valuation).unwrap();
+                    valuation.insert(name.clone(), value);
+                }
+                assert_eq!(expr.eval(&valuation).unwrap(), clauses.eval(&valuation).unwrap(), "{}", formula);
+            }
+        }
+    }
+
+    #[test]
+    fn conversion() {
+        assert_eq!("◻(¬p ⋁ q)", convert("◻(p ⊃ q)", NormalForm::Nnf, false).unwrap());
+        assert_eq!("¬p ⋁ q", convert("p ⊃ q", NormalForm::Dnf, false).unwrap());
+        assert_eq!("(¬d1 ⋁ ¬p ⋁ q) ⋀ (d1 ⋁ p) ⋀ (d1 ⋁ ¬q) ⋀ d1\n  d1 ≔ p ⊃ q",
+            convert("p ⊃ q", NormalForm::Cnf, true).unwrap());
+        assert!(convert("p ⊃", NormalForm::Cnf, false).is_err());
+    }
+}
@@ -1,52 +1,503 @@
-enum ParseState {
-    Open,
-    Parentheses
+use crate::ast::{AT, BINDER, CONDITIONAL_OBLIGATION, COUNTERFACTUAL, EXISTENCE, Expr, FALSUM, IDENTITY, GroupModality, OBLIGATION, Quantifier, TemporalOp, VERUM};
+
+pub const UNARY_OPS: [char; 3] = ['¬', '◻', '◇'];
+// each followed by the variable it binds
+pub const QUANTIFIERS: [char; 2] = ['∀', '∃'];
+// lowest precedence first
+pub const BINARY_OPS: [char; 5] = ['≡', '⊃', '⊕', '⋁', '⋀'];
+// other ways of writing them
+pub const ALIASES: [(char, char); 1] = [('↔', '≡')];
+// strict implication and the first character of □→, with the precedence of ⊃
+pub const CONDITIONALS: [char; 2] = ['⥽', '□'];
+// until and since bind tighter than the connectives and are only operators
+// when standing apart from names
+pub const TEMPORAL_BINARY_OPS: [char; 2] = ['U', 'S'];
+
+pub fn parse_formula(formula: &str) -> Result<Instructions, String> {
+    let int_formula = strip_parentheses(formula)?;
+    if int_formula.is_empty() {
+        return Err(format!("Empty formula in {}", formula));
+    }
+
+    // find the main connective: the lowest precedence binary operator outside parentheses
+    let mut depth: usize = 0;
+    let mut main: Option<(usize, usize, char)> = None;
+    for (idx, ch) in int_formula.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth == 0 => if let Some(rank) = binary_rank(int_formula, idx, ch) {
+                match main {
+                    // ⊃, the other conditionals, U and S associate to the right, so keep the first one
+                    Some((main_rank, _, '⊃' | '⥽' | '□' | 'U' | 'S')) if main_rank <= rank => {},
+                    Some((main_rank, _, _)) if main_rank < rank => {},
+                    _ => main = Some((rank, idx, ch))
+                }
+            },
+            _ => {}
+        }
+    }
+
+    if let Some((_, idx, op)) = main {
+        let operator = if op == '□' { COUNTERFACTUAL.to_string() } else { canonical_op(op).to_string() };
+        let left = strip_parentheses(&int_formula[..idx])?;
+        let right = strip_parentheses(&int_formula[idx + operator.len()..])?;
+        if left.is_empty() || right.is_empty() {
+            return Err(format!("Missing operand for {} in {}", op, formula));
+        }
+        return Ok(Instructions {
+            operators: operator,
+            variables: vec![left.to_string(), right.to_string()],
+            index: String::new()
+        });
+    }
+
+    let first = int_formula.chars().next().unwrap();
+    if UNARY_OPS.contains(&first) {
+        let operand = strip_parentheses(&int_formula[first.len_utf8()..])?;
+        if operand.is_empty() {
+            return Err(format!("Missing operand for {} in {}", first, formula));
+        }
+        return Ok(Instructions {
+            operators: first.to_string(),
+            variables: vec![operand.to_string()],
+            index: String::new()
+        });
+    }
+
+    if QUANTIFIERS.contains(&first) || first == BINDER {
+        let rest = int_formula[first.len_utf8()..].trim_start();
+        let end = rest.find(|ch: char| !is_name_char(ch)).unwrap_or(rest.len());
+        // ↓x.φ has a dot after the variable
+        let body = if first == BINDER { rest[end..].strip_prefix('.').unwrap_or(&rest[end..]) } else { &rest[end..] };
+        let operand = strip_parentheses(body)?;
+        if end == 0 {
+            return Err(format!("Missing variable for {} in {}", first, formula));
+        }
+        if operand.is_empty() {
+            return Err(format!("Missing operand for {} in {}", first, formula));
+        }
+        return Ok(Instructions {
+            operators: first.to_string(),
+            variables: vec![operand.to_string()],
+            index: rest[..end].to_string()
+        });
+    }
+
+    if let Some(rest) = int_formula.strip_prefix(AT) {
+        let end = rest.find(|ch: char| !is_name_char(ch)).unwrap_or(rest.len());
+        let operand = strip_parentheses(&rest[end..])?;
+        if end == 0 {
+            return Err(format!("Missing nominal for {} in {}", AT, formula));
+        }
+        if operand.is_empty() {
+            return Err(format!("Missing operand for {}{} in {}", AT, &rest[..end], formula));
+        }
+        return Ok(Instructions {
+            operators: "@".to_string(),
+            variables: vec![operand.to_string()],
+            index: rest[..end].to_string()
+        });
+    }
+
+    if let Some((obligation, condition)) = conditional_obligation(int_formula, formula)? {
+        return Ok(Instructions {
+            operators: CONDITIONAL_OBLIGATION.to_string(),
+            variables: vec![obligation.to_string(), condition.to_string()],
+            index: String::new()
+        });
+    }
+
+    if let Some((operators, index, rest)) = indexed_modality(int_formula, formula)? {
+        let operand = strip_parentheses(rest)?;
+        if operand.is_empty() {
+            return Err(format!("Missing operand for {} in {}", index, formula));
+        }
+        return Ok(Instructions {
+            operators: operators.to_string(),
+            variables: vec![operand.to_string()],
+            index
+        });
+    }
+
+    if predication(int_formula).is_none() && ![VERUM, FALSUM].contains(&int_formula) {
+        let ch = int_formula.chars().find(|ch| !is_name_char(*ch)).unwrap();
+        return Err(format!("Unable to parse char {} in {}", ch, formula));
+    }
+    Ok(Instructions {
+        operators: String::new(),
+        variables: vec![int_formula.to_string()],
+        index: String::new()
+    })
+}
+
+/// The operator `ch` stands for.
+fn canonical_op(ch: char) -> char {
+    ALIASES.iter().find(|(alias, _)| *alias == ch).map_or(ch, |(_, op)| *op)
+}
+
+/// Precedence of the binary operator `ch` found at `idx`, if it is one.
+fn binary_rank(int_formula: &str, idx: usize, ch: char) -> Option<usize> {
+    if let Some(rank) = BINARY_OPS.iter().position(|op| *op == canonical_op(ch)) {
+        return Some(rank);
+    }
+    if ch == '⥽' || (ch == '□' && int_formula[idx..].starts_with(COUNTERFACTUAL)) {
+        return BINARY_OPS.iter().position(|op| *op == '⊃');
+    }
+    if !TEMPORAL_BINARY_OPS.contains(&ch) {
+        return None;
+    }
+    // with nothing on one side, it is a name, as in S(a, b)
+    let before = int_formula[..idx].chars().next_back()?;
+    let after = int_formula[idx + ch.len_utf8()..].chars().next()?;
+    if is_name_char(before) || is_name_char(after) {
+        return None;
+    }
+    // U and S share a rank
+    Some(BINARY_OPS.len())
+}
+
+fn is_name_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(is_name_char)
 }
 
-pub fn parse_formula(formula: &str) -> Result<Instructions, Box<String>> {
-    let mut int_formula = formula;
-    let mut state = ParseState::Open;
-    let mut temp_var = String::new();
+/// Splits an atom `p` or a predication `R(a, x)` into the name and the
+/// arguments. A name directly followed by names in parentheses is always a
+/// predication unless the name is a modality, so `G(p)` is one while
+/// `G (p)`, `G(¬p)` and `K_a(p)` are not. The existence predicate `E!`
+/// is the one name with a `!`, and an identity `a = b` is a predication of
+/// `=`.
+fn predication(int_formula: &str) -> Option<(&str, Vec<String>)> {
+    if let Some((left, right)) = int_formula.split_once(IDENTITY) {
+        let (left, right) = (left.trim(), right.trim());
+        return (is_name(left) && is_name(right)).then(|| (IDENTITY, vec![left.to_string(), right.to_string()]));
+    }
+    let end = match int_formula.strip_prefix(EXISTENCE) {
+        Some(rest) if rest.starts_with('(') => EXISTENCE.len(),
+        _ => int_formula.find(|ch: char| !is_name_char(ch)).unwrap_or(int_formula.len())
+    };
+    let (name, rest) = int_formula.split_at(end);
+    if !is_name(name) && name != EXISTENCE {
+        return None;
+    }
+    if rest.is_empty() {
+        return Some((name, Vec::new()));
+    }
+    let args: Vec<String> = rest.strip_prefix('(')?.strip_suffix(')')?
+        .split(',')
+        .map(|arg| arg.trim().to_string())
+        .collect();
+    args.iter().all(|arg| is_name(arg)).then_some((name, args))
+}
 
-    let mut variables: Vec<String> = Vec::new();
-    let mut operators = String::new();
-    // let as_bytes = formula.as_bytes();
-    let ops: [char; 6] = ['⊃', '¬', '⋀', '⋁', '◻', '◇'];
+/// Whether `index` names an agent's knowledge or belief, written `K_a p`
+/// and `B_a p` rather than `[K_a]p`.
+pub fn is_epistemic(index: &str) -> bool {
+    ["K_", "B_"].iter().any(|prefix| {
+        index.strip_prefix(prefix).is_some_and(|agent| !agent.is_empty() && agent.chars().all(is_name_char))
+    })
+}
 
-    if formula.starts_with("(") && formula.ends_with(")") {
-        let strlen = formula.len();
-        int_formula = &int_formula[1..strlen];
+/// Splits `[i]φ`, `<i>φ`, `K_a φ` and `B_a φ` into the operator, `[]` or `<>`,
+/// the index and the rest of the formula. Group modalities `E_{a,b} φ`,
+/// `C_{a,b} φ` and `D_{a,b} φ` give `E_`, `C_` or `D_` and the agents,
+/// sorted and separated by commas.
+fn indexed_modality<'a>(int_formula: &'a str, formula: &str) -> Result<Option<(&'static str, String, &'a str)>, String> {
+    let (operators, close) = match int_formula.chars().next() {
+        Some('[') => ("[]", ']'),
+        Some('<') => ("<>", '>'),
+        _ => return named_modality(int_formula, formula)
+    };
+    let end = int_formula.find(close)
+        .ok_or(format!("Unclosed modality {} in {}", &operators[..1], formula))?;
+    let index = int_formula[1..end].trim();
+    if index.is_empty() || !index.chars().all(is_name_char) {
+        return Err(format!("Invalid modal index {} in {}", index, formula));
     }
+    Ok(Some((operators, index.to_string(), &int_formula[end + 1..])))
+}
 
-    for ch in int_formula.chars() {
-        match state {
-            ParseState::Open => {
-                if ch == ' ' { continue; }
-                else if ch == '(' { state = ParseState::Parentheses; }
-                else if ops.contains(&ch) { operators.push(ch); }
-                else if ch.is_alphabetic() { variables.push(ch.to_string()); }
-                else { 
-                    let err_msg = format!("Unable to parse char {} in {}", ch, formula);
-                    return Err(Box::new(err_msg)); 
+/// Modalities and temporal operators written as a name before their
+/// operand. Without an operand the name is an atom.
+fn named_modality<'a>(int_formula: &'a str, formula: &str) -> Result<Option<(&'static str, String, &'a str)>, String> {
+    let group = ["E_", "C_", "D_"].into_iter().find(|prefix| int_formula.starts_with(prefix));
+    if let Some(prefix) = group {
+        if let Some(agents) = int_formula[prefix.len()..].strip_prefix('{') {
+            let end = agents.find('}').ok_or(format!("Unclosed group in {}", formula))?;
+            let mut group: Vec<&str> = agents[..end].split(',').map(|agent| agent.trim()).collect();
+            if group.iter().any(|agent| agent.is_empty() || !agent.chars().all(is_name_char)) {
+                return Err(format!("Invalid group {{{}}} in {}", &agents[..end], formula));
+            }
+            group.sort();
+            group.dedup();
+            return Ok(Some((prefix, group.join(","), &agents[end + 1..])));
+        }
+    }
+
+    let end = int_formula.find(|ch: char| !is_name_char(ch)).unwrap_or(int_formula.len());
+    let (index, rest) = int_formula.split_at(end);
+    if rest.trim().is_empty() {
+        return Ok(None);
+    }
+    match group {
+        Some(prefix) if index.len() > prefix.len() => Ok(Some((prefix, index[prefix.len()..].to_string(), rest))),
+        _ if is_epistemic(index) => Ok(Some(("[]", index.to_string(), rest))),
+        _ if index == OBLIGATION => Ok(Some(("◻", String::new(), rest))),
+        _ if predication(int_formula).is_some() => Ok(None),
+        _ => Ok(TemporalOp::from_symbol(index).map(|op| (op.symbol(), String::new(), rest)))
+    }
+}
+
+/// The obligation and the condition of `O(ψ/φ)`, split at the `/` outside
+/// any further parentheses.
+fn conditional_obligation<'a>(int_formula: &'a str, formula: &str) -> Result<Option<(&'a str, &'a str)>, String> {
+    let Some(rest) = int_formula.strip_prefix(OBLIGATION).map(str::trim_start) else {
+        return Ok(None);
+    };
+    if !rest.starts_with('(') || closing_parenthesis(rest) != Some(rest.len() - 1) {
+        return Ok(None);
+    }
+    let inner = &rest[1..rest.len() - 1];
+    let mut depth: usize = 0;
+    for (idx, ch) in inner.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '/' if depth == 0 => {
+                let (obligation, condition) = (inner[..idx].trim(), inner[idx + 1..].trim());
+                if obligation.is_empty() || condition.is_empty() {
+                    return Err(format!("Missing operand for {}(/) in {}", OBLIGATION, formula));
                 }
+                return Ok(Some((obligation, condition)));
             },
-            ParseState::Parentheses => {
-                if ch == ')' { 
-                    variables.push(temp_var);
-                    temp_var = String::new();
-                    state = ParseState::Open; 
-                }
-                else { temp_var.push(ch); }
-            }
+            _ => {}
         }
     }
-    Ok(Instructions { operators, variables })
+    Ok(None)
+}
+
+/// Trims whitespace and any parentheses wrapping the whole formula.
+fn strip_parentheses(formula: &str) -> Result<&str, String> {
+    let mut int_formula = formula.trim();
+    let mut depth: usize = 0;
+    for ch in int_formula.chars() {
+        match ch {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err(format!("Unbalanced parentheses in {}", formula)),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err(format!("Unbalanced parentheses in {}", formula));
+    }
+
+    while int_formula.starts_with('(') && closing_parenthesis(int_formula) == Some(int_formula.len() - 1) {
+        int_formula = int_formula[1..int_formula.len() - 1].trim();
+    }
+    Ok(int_formula)
+}
+
+/// Index of the parenthesis closing the one opening `formula`.
+fn closing_parenthesis(formula: &str) -> Option<usize> {
+    let mut depth: usize = 0;
+    for (idx, ch) in formula.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 { return Some(idx); }
+            },
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The operands of the left associative `op` joining the formula, in order:
+/// a, b and c for a ⋀ b ⋀ c, which is (a ⋀ b) ⋀ c.
+fn chain_operands(formula: &str, op: char) -> Result<Vec<&str>, String> {
+    let int_formula = strip_parentheses(formula)?;
+    let mut operands = Vec::new();
+    let (mut depth, mut start): (usize, usize) = (0, 0);
+    for (idx, ch) in int_formula.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth == 0 && canonical_op(ch) == op && binary_rank(int_formula, idx, ch).is_some() => {
+                operands.push(&int_formula[start..idx]);
+                start = idx + ch.len_utf8();
+            },
+            _ => {}
+        }
+    }
+    operands.push(&int_formula[start..]);
+    if operands.iter().any(|operand| operand.trim().is_empty()) {
+        return Err(format!("Missing operand for {} in {}", op, formula));
+    }
+    Ok(operands)
+}
+
+/// Parses a formula into a full syntax tree.
+pub fn parse_expr(formula: &str) -> Result<Expr, String> {
+    let instructions = parse_formula(formula)?;
+    let operator = instructions.operators().as_str();
+    let expr = match (operator, instructions.variables().as_slice()) {
+        ("", [atom]) if atom == VERUM => Expr::Verum,
+        ("", [atom]) if atom == FALSUM => Expr::Falsum,
+        ("", [atom]) => match predication(atom) {
+            Some((name, args)) if !args.is_empty() => Expr::Predicate(name.to_string(), args),
+            _ => Expr::Atom(atom.clone())
+        },
+        (op, [operand]) if Quantifier::from_symbol(op).is_some() => Expr::Quantified(
+            Quantifier::from_symbol(op).unwrap(),
+            instructions.index().clone(),
+            Box::new(parse_expr(operand)?)
+        ),
+        ("@", [operand]) => Expr::At(instructions.index().clone(), Box::new(parse_expr(operand)?)),
+        (op, [operand]) if op.starts_with(BINDER) => Expr::Bind(instructions.index().clone(), Box::new(parse_expr(operand)?)),
+        ("¬", [operand]) => Expr::Not(Box::new(parse_expr(operand)?)),
+        ("◻", [operand]) => Expr::Necessarily(Box::new(parse_expr(operand)?)),
+        ("◇", [operand]) => Expr::Possibly(Box::new(parse_expr(operand)?)),
+        ("[]", [operand]) => Expr::IndexedNecessarily(instructions.index().clone(), Box::new(parse_expr(operand)?)),
+        ("<>", [operand]) => Expr::IndexedPossibly(instructions.index().clone(), Box::new(parse_expr(operand)?)),
+        (op, [operand]) if GroupModality::from_prefix(op).is_some() => Expr::Group(
+            GroupModality::from_prefix(op).unwrap(),
+            instructions.group(),
+            Box::new(parse_expr(operand)?)
+        ),
+        // a chain of ⋀ or another left associative connective would recurse
+        // once per operator, so it is split in one pass and folded
+        ("⋀" | "⋁" | "≡" | "⊕", [_, _]) => {
+            let op = operator.chars().next().unwrap();
+            let mut operands = chain_operands(formula, op)?.into_iter().map(parse_expr);
+            let first = operands.next().unwrap()?;
+            operands.try_fold(first, |left, right| Ok::<Expr, String>(match op {
+                '⋀' => left.and(right?),
+                '⋁' => left.or(right?),
+                '≡' => left.iff(right?),
+                _ => left.xor(right?)
+            }))?
+        },
+        ("⊃", [left, right]) => parse_expr(left)?.implies(parse_expr(right)?),
+        ("⥽", [left, right]) => Expr::Strict(Box::new(parse_expr(left)?), Box::new(parse_expr(right)?)),
+        (COUNTERFACTUAL, [left, right]) => Expr::Counterfactual(Box::new(parse_expr(left)?), Box::new(parse_expr(right)?)),
+        (CONDITIONAL_OBLIGATION, [obligation, condition]) => Expr::Obligation(
+            Box::new(parse_expr(obligation)?),
+            Box::new(parse_expr(condition)?)
+        ),
+        ("U", [left, right]) => Expr::Until(Box::new(parse_expr(left)?), Box::new(parse_expr(right)?)),
+        ("S", [left, right]) => Expr::Since(Box::new(parse_expr(left)?), Box::new(parse_expr(right)?)),
+        (op, [operand]) if TemporalOp::from_symbol(op).is_some() => Expr::Temporal(
+            TemporalOp::from_symbol(op).unwrap(),
+            Box::new(parse_expr(operand)?)
+        ),
+        _ => return Err(format!("Unable to parse {}", formula))
+    };
+    Ok(expr)
+}
+
+/// Renders a formula in the canonical spacing and parenthesisation used on the tableau,
+/// so that syntactically equal formulas compare equal as strings.
+pub fn normalize(formula: &str) -> Result<String, String> {
+    Ok(parse_expr(formula)?.to_string())
+}
+
+/// `[index]operand` for a normalized operand, itself normalized.
+pub fn necessity(index: &str, operand: &str) -> Result<String, String> {
+    Ok(Expr::IndexedNecessarily(index.to_string(), Box::new(parse_expr(operand)?)).to_string())
+}
+
+/// The formula with `P φ` read as permission, ◇φ, rather than as the past,
+/// normalized. `O φ` is obligation, ◻φ, in any case.
+pub fn deontic(formula: &str) -> Result<String, String> {
+    Ok(parse_expr(formula)?.deontic().to_string())
+}
+
+/// `◻(antecedent ⊃ consequent)`, which `antecedent ⥽ consequent`
+/// abbreviates, for normalized formulas, itself normalized.
+pub fn strict_implication(antecedent: &str, consequent: &str) -> Result<String, String> {
+    Ok(Expr::Necessarily(Box::new(parse_expr(antecedent)?.implies(parse_expr(consequent)?))).to_string())
+}
+
+/// `<index>operand` for a normalized operand, itself normalized.
+pub fn possibility(index: &str, operand: &str) -> Result<String, String> {
+    Ok(Expr::IndexedPossibly(index.to_string(), Box::new(parse_expr(operand)?)).to_string())
+}
+
+/// `E_G operand`, `C_G operand` or `D_G operand` for a normalized operand,
+/// itself normalized.
+pub fn group(modality: GroupModality, agents: &[String], operand: &str) -> Result<String, String> {
+    Ok(Expr::Group(modality, agents.to_vec(), Box::new(parse_expr(operand)?)).to_string())
+}
+
+/// `op operand` for a normalized operand, itself normalized.
+pub fn temporal(op: TemporalOp, operand: &str) -> Result<String, String> {
+    Ok(Expr::Temporal(op, Box::new(parse_expr(operand)?)).to_string())
+}
+
+/// `q var operand` for a normalized operand, itself normalized.
+pub fn quantified(quantifier: Quantifier, var: &str, operand: &str) -> Result<String, String> {
+    Ok(Expr::Quantified(quantifier, var.to_string(), Box::new(parse_expr(operand)?)).to_string())
+}
+
+/// `operand` with `name` for the free occurrences of `var`, normalized.
+pub fn instantiate(operand: &str, var: &str, name: &str) -> Result<String, String> {
+    Ok(parse_expr(operand)?.substitute(var, name).to_string())
+}
+
+/// `@_nominal operand` for a normalized operand, itself normalized.
+pub fn at(nominal: &str, operand: &str) -> Result<String, String> {
+    Ok(Expr::At(nominal.to_string(), Box::new(parse_expr(operand)?)).to_string())
+}
+
+/// `↓var.operand` for a normalized operand, itself normalized.
+pub fn bind(var: &str, operand: &str) -> Result<String, String> {
+    Ok(Expr::Bind(var.to_string(), Box::new(parse_expr(operand)?)).to_string())
+}
+
+/// `E!(name)`, saying that `name` exists.
+pub fn existence(name: &str) -> String {
+    Expr::Predicate(EXISTENCE.to_string(), vec![name.to_string()]).to_string()
+}
+
+/// The name an existence predication `E!(name)` is about, if the formula
+/// is one.
+pub fn existent(formula: &str) -> Result<Option<String>, String> {
+    Ok(match parse_expr(formula)? {
+        Expr::Predicate(name, args) if name == EXISTENCE => args.into_iter().next(),
+        _ => None
+    })
+}
+
+/// Negates a normalized formula.
+pub fn negate(formula: &str) -> Result<String, String> {
+    Ok(format!("¬{}", wrap(formula)?))
+}
+
+/// Parenthesises a normalized formula if its main connective is binary.
+fn wrap(formula: &str) -> Result<String, String> {
+    let instructions = parse_formula(formula)?;
+    let identity = instructions.operators().is_empty()
+        && predication(formula).is_some_and(|(name, _)| name == IDENTITY);
+    if instructions.variables().len() > 1 || identity {
+        Ok(format!("({})", formula))
+    } else {
+        Ok(formula.to_string())
     }
+}
 
 #[derive(Debug, PartialEq)]
 pub struct Instructions {
     operators: String,
-    variables: Vec<String>
+    variables: Vec<String>,
+    // index of a `[]` or `<>` modality, the agents of a group modality or
+    // the variable of a quantifier or ↓, the nominal of @_, empty otherwise
+    index: String
 }
 
 impl Instructions {
@@ -57,6 +508,15 @@ impl Instructions {
     pub fn variables(&self) -> &Vec<String> {
         &self.variables
     }
+
+    pub fn index(&self) -> &String {
+        &self.index
+    }
+
+    /// Agents of a group modality.
+    pub fn group(&self) -> Vec<String> {
+        self.index.split(',').map(String::from).collect()
+    }
 }
 
 #[cfg(test)]
@@ -68,7 +528,8 @@ mod tests {
         let formula = "p ⊃ q";
         let manual_instructions = Instructions {
             operators: "⊃".to_string(),
-            variables: vec!['p'.to_string(), 'q'.to_string()]
+            variables: vec!['p'.to_string(), 'q'.to_string()],
+            index: String::new()
         };
     assert_eq!(manual_instructions, parse_formula(formula).unwrap())
     }
@@ -78,8 +539,205 @@ mod tests {
         let formula = "(p ⋀ r) ⊃ q";
         let manual_instructions = Instructions {
             operators: "⊃".to_string(),
-            variables: vec!["p ⋀ r".to_string(), "q".to_string()]
+            variables: vec!["p ⋀ r".to_string(), "q".to_string()],
+            index: String::new()
+        };
+    assert_eq!(manual_instructions, parse_formula(formula).unwrap())
+    }
+
+    #[test]
+    fn nesting() {
+        let formula = "((p ⊃ q) ⋀ ¬(q ⋁ r))";
+        let manual_instructions = Instructions {
+            operators: "⋀".to_string(),
+            variables: vec!["p ⊃ q".to_string(), "¬(q ⋁ r)".to_string()],
+            index: String::new()
         };
-    assert_eq!(manual_instructions, parse_formula(formula).unwrap())        
+        assert_eq!(manual_instructions, parse_formula(formula).unwrap());
+
+        let negation = parse_formula("¬(q ⋁ r)").unwrap();
+        assert_eq!("¬", negation.operators());
+        assert_eq!(&vec!["q ⋁ r".to_string()], negation.variables());
+
+        let arrow = parse_formula("p ⊃ q ⊃ r").unwrap();
+        assert_eq!(&vec!["p".to_string(), "q ⊃ r".to_string()], arrow.variables());
+
+        assert!(parse_formula("(p ⊃ q").is_err());
+        assert!(parse_formula("p ⊃").is_err());
+    }
+
+    #[test]
+    fn normalizing() {
+        assert_eq!("¬(p ⊃ q)", normalize(" ¬ ( (p)⊃q )").unwrap());
+        assert_eq!("◻¬p ⋀ q", normalize("(◻(¬p)) ⋀ q").unwrap());
+        assert_eq!("¬(p ⋀ q)", negate("p ⋀ q").unwrap());
+        assert_eq!("¬¬p", negate("¬p").unwrap());
+    }
+
+    #[test]
+    fn expressions() {
+        let expr = parse_expr("¬(p ⊃ ◇q) ⋁ r").unwrap();
+        let manual = Expr::Not(Box::new(
+            Expr::atom("p").implies(Expr::Possibly(Box::new(Expr::atom("q"))))
+        )).or(Expr::atom("r"));
+        assert_eq!(manual, expr);
+        assert!(parse_expr("p ⋀ (q").is_err());
+    }
+
+    #[test]
+    fn chains() {
+        // a thousand clauses are split in one pass, not by a thousand calls,
+        // and written back the same way
+        let clauses: Vec<String> = (0..1000).map(|idx| format!("(p{} ⋁ ¬q{})", idx, idx)).collect();
+        let cnf = clauses.join(" ⋀ ");
+        assert_eq!(cnf, normalize(&cnf).unwrap());
+        let expected = parse_expr("p ⋀ q").unwrap().and(Expr::atom("r")).and(Expr::atom("s"));
+        assert_eq!(expected, parse_expr("p ⋀ (q) ⋀ r ⋀ s").unwrap());
+        assert_eq!("p ⋀ q ⋀ r ⋀ s", normalize("((p ⋀ q) ⋀ r) ⋀ s").unwrap());
+        assert_eq!("p ⋀ (q ⋀ r)", normalize("p ⋀ (q ⋀ r)").unwrap());
+        assert_eq!("(p ⊕ q) ⊕ (r ⋁ s)", normalize("p ⊕ q ⊕ r ⋁ s").unwrap());
+        assert!(parse_expr("p ⋀ ⋀ q").is_err());
+        assert!(parse_expr("p ⋁ q ⋁").is_err());
+    }
+
+    #[test]
+    fn connectives() {
+        let iff = parse_formula("p ≡ q ⊃ r").unwrap();
+        assert_eq!(("≡", &vec!["p".to_string(), "q ⊃ r".to_string()]), (iff.operators().as_str(), iff.variables()));
+        assert_eq!("≡", parse_formula("p ↔ q").unwrap().operators());
+        assert_eq!("⊃", parse_formula("p ⊃ q ⊕ r").unwrap().operators());
+        assert_eq!("⊕", parse_formula("q ⊕ r ⋁ s").unwrap().operators());
+        assert_eq!("(p ≡ q) ≡ r", normalize("p ↔ q ↔ r").unwrap());
+        assert_eq!("p ⊕ (q ⋁ r)", normalize("p⊕q⋁r").unwrap());
+        assert_eq!("⊤ ⋀ ¬⊥", normalize("(⊤) ⋀ ¬ ⊥").unwrap());
+        assert_eq!(Expr::Verum.iff(Expr::Falsum.negated()), parse_expr("⊤ ≡ ¬⊥").unwrap());
+        assert!(parse_expr("p ≡").is_err());
+        assert!(parse_expr("⊤(a)").is_err());
+    }
+
+    #[test]
+    fn conditionals() {
+        let would = parse_formula("p ⋀ q □→ r ⊃ s").unwrap();
+        assert_eq!(("□→", &vec!["p ⋀ q".to_string(), "r ⊃ s".to_string()]), (would.operators().as_str(), would.variables()));
+        assert_eq!("⥽", parse_formula("p ⥽ q ⥽ r").unwrap().operators());
+        assert_eq!("p ⥽ (q □→ r)", normalize("p⥽q□→r").unwrap());
+        assert_eq!("(p □→ q) ⋀ ◻p", normalize("(p □→ q) ⋀ ◻p").unwrap());
+        assert_eq!("◻(p ⊃ q)", strict_implication("p", "q").unwrap());
+        assert!(parse_expr("p □→").is_err());
+        assert!(parse_expr("p □ q").is_err());
+    }
+
+    #[test]
+    fn obligations() {
+        let obligation = parse_formula("O(t ⋀ (p ⊃ q) / ¬h)").unwrap();
+        assert_eq!(("O/", &vec!["t ⋀ (p ⊃ q)".to_string(), "¬h".to_string()]), (obligation.operators().as_str(), obligation.variables()));
+        assert_eq!("O(t/h) ⋀ O(¬t/¬h)", normalize("O (t / h) ⋀ O(¬t/¬h)").unwrap());
+        assert_eq!("◻(m ⋁ b) ⊃ ◻h", normalize("O(m ⋁ b) ⊃ O h").unwrap());
+        assert_eq!("O", normalize("O").unwrap());
+        assert_eq!("P h ⊃ ◇h", normalize("P h ⊃ ◇h").unwrap());
+        assert_eq!("◇h ⊃ (◇◇h ⋀ G p)", deontic("P h ⊃ P P h ⋀ G p").unwrap());
+        assert!(parse_expr("O(/h)").is_err());
+        assert!(parse_expr("O(t/)").is_err());
+    }
+
+    #[test]
+    fn indexed() {
+        let boxed = parse_formula("[a](p ⋀ q)").unwrap();
+        assert_eq!(("[]", "a"), (boxed.operators().as_str(), boxed.index().as_str()));
+        assert_eq!(&vec!["p ⋀ q".to_string()], boxed.variables());
+
+        let knows = parse_formula("K_a ¬p").unwrap();
+        assert_eq!(("[]", "K_a"), (knows.operators().as_str(), knows.index().as_str()));
+        assert_eq!("<>", parse_formula("<B_b>p").unwrap().operators());
+        // without an operand K_a is an atom
+        assert_eq!("", parse_formula("K_a").unwrap().operators());
+
+        assert_eq!("K_a p ⋀ [b]<c>q", normalize("K_a(p) ⋀ [ b ]< c >q").unwrap());
+        assert_eq!("B_a (p ⊃ q)", necessity("B_a", "p ⊃ q").unwrap());
+        assert_eq!("<a>¬p", possibility("a", "¬p").unwrap());
+        assert!(parse_formula("[a p").is_err());
+        assert!(parse_formula("[a b]p").is_err());
+        assert!(parse_formula("<a>").is_err());
+    }
+
+    #[test]
+    fn groups() {
+        let common = parse_formula("C_{b, a}(p ⋁ q)").unwrap();
+        assert_eq!(("C_", "a,b"), (common.operators().as_str(), common.index().as_str()));
+        assert_eq!(vec!["a", "b"], common.group());
+        assert_eq!("C_{a,b} (p ⋁ q)", normalize("C_{b,a,b}(p ⋁ q)").unwrap());
+        assert_eq!("E_a p ⋀ D_{a,c} ¬K_a p", normalize("E_{a} p ⋀ D_{c,a}¬K_a p").unwrap());
+        // without an operand the name is an atom
+        assert_eq!("", parse_formula("E_a").unwrap().operators());
+        assert!(parse_formula("C_{a,} p").is_err());
+        assert!(parse_formula("D_{a p").is_err());
+    }
+
+    #[test]
+    fn temporal_operators() {
+        let until = parse_formula("G p U q ⋀ r").unwrap();
+        assert_eq!("⋀", until.operators());
+        assert_eq!(&vec!["G p U q".to_string(), "r".to_string()], until.variables());
+        let until = parse_formula("G p U q").unwrap();
+        assert_eq!(("U", &vec!["G p".to_string(), "q".to_string()]), (until.operators().as_str(), until.variables()));
+        assert_eq!(&vec!["p".to_string(), "q S r".to_string()], parse_formula("p S q S r").unwrap().variables());
+
+        assert_eq!("G", parse_formula("G(p ⊃ F q)").unwrap().operators());
+        assert_eq!("G (p ⊃ F q) ⋀ X Y ¬p", normalize("G(p ⊃ F q) ⋀ X(Y¬p)").unwrap());
+        assert_eq!("(p U q) ⋁ (p S q)", normalize("p U q ⋁ p S q").unwrap());
+        // operators only stand apart from names: these are atoms
+        assert_eq!("", parse_formula("G").unwrap().operators());
+        assert_eq!("", parse_formula("SUM").unwrap().operators());
+        assert_eq!("Gp ⋀ US", normalize("Gp ⋀ US").unwrap());
+        assert_eq!("F ¬p", temporal(TemporalOp::Eventually, "¬p").unwrap());
+        assert!(parse_formula("p U").is_err());
+    }
+
+    #[test]
+    fn first_order() {
+        let all = parse_formula("∀x (P(x) ⊃ ∃y R(x,y))").unwrap();
+        assert_eq!(("∀", "x"), (all.operators().as_str(), all.index().as_str()));
+        assert_eq!(&vec!["P(x) ⊃ ∃y R(x,y)".to_string()], all.variables());
+        assert_eq!("⊃", parse_formula("∀x P(x) ⊃ Q(a)").unwrap().operators());
+
+        let atom = parse_formula("R(a, x)").unwrap();
+        assert_eq!(("", &vec!["R(a, x)".to_string()]), (atom.operators().as_str(), atom.variables()));
+        assert_eq!("∀x (P(x) ⊃ ∃y R(x, y)) ⋀ Loves(a, b)", normalize("∀x(P(x) ⊃ ∃y R(x,y)) ⋀ Loves( a,b )").unwrap());
+        // one letter predicates are not temporal operators, nor S a binary one
+        assert_eq!("P(a) ⋀ S(a, b) ⋀ G p", normalize("P(a) ⋀ S(a, b) ⋀ G (p)").unwrap());
+        assert!(parse_formula("∀(P(x))").is_err());
+        assert!(parse_formula("∃x").is_err());
+        assert!(parse_expr("P(a, )").is_err());
+        assert!(parse_expr("Q(a ⋀ b)").is_err());
+
+        assert_eq!(Expr::Predicate(EXISTENCE.to_string(), vec!["a".to_string()]), parse_expr("E!(a)").unwrap());
+        assert_eq!("∀x (E!(x) ⊃ ◻E!(x))", normalize("∀x(E!(x) ⊃ ◻E!(x))").unwrap());
+        assert!(parse_expr("P!(a)").is_err());
+
+        assert_eq!(Expr::Predicate(IDENTITY.to_string(), vec!["a".to_string(), "b".to_string()]), parse_expr("a=b").unwrap());
+        assert_eq!("¬(a = a) ⋀ ∀x (x = x)", normalize("¬(a=a) ⋀ ∀x x = x").unwrap());
+        assert_eq!("¬(a = b)", negate("a = b").unwrap());
+        assert!(parse_expr("a = b = c").is_err());
+        assert!(parse_expr("a = ").is_err());
+    }
+
+    #[test]
+    fn hybrid() {
+        let satisfaction = parse_formula("@_i (p ⋀ ◇i)").unwrap();
+        assert_eq!(("@", "i"), (satisfaction.operators().as_str(), satisfaction.index().as_str()));
+        assert_eq!(&vec!["p ⋀ ◇i".to_string()], satisfaction.variables());
+        let bind = parse_formula("↓x.◻(x ⊃ p)").unwrap();
+        assert_eq!(("↓", "x"), (bind.operators().as_str(), bind.index().as_str()));
+        assert_eq!(&vec!["◻(x ⊃ p)".to_string()], bind.variables());
+
+        assert_eq!("@_i p ⋀ q", normalize("@_i p ⋀ q").unwrap());
+        assert_eq!("↓x.◇(x ⋀ @_i ¬x)", normalize("↓x.◇(x ⋀ @_i¬x)").unwrap());
+        assert_eq!("↓x.◇↓y.@_x ◇y", normalize("↓x ◇↓y.@_x ◇y").unwrap());
+        assert_eq!("@_i ◇p", at("i", "◇p").unwrap());
+        // a name the variable is replaced by is not captured
+        assert_eq!("↓x_.◇(x ⋀ x_)", instantiate("↓x.◇(y ⋀ x)", "y", "x").unwrap());
+        assert_eq!("↓x.◇x", instantiate("↓x.◇x", "x", "i").unwrap());
+        assert!(parse_expr("@_ p").is_err());
+        assert!(parse_expr("↓x.").is_err());
     }
 }
@@ -0,0 +1,364 @@
+use std::time::Instant;
+use crate::ast::Expr;
+use crate::configs::Logic;
+use crate::countermodel::Countermodel;
+use crate::graphs::{Graph, node::{Closure, Formula, World}};
+use crate::model::MAX_WORLDS;
+use crate::parser::{negate, parse_expr, parse_formula};
+use crate::prover::{agreement, normalize_argument, branch_worlds, check_deadline, open_countermodel, valuation, world_limit};
+
+/// Tableau for the provability logic GL, over finite transitive irreflexive
+/// frames, and Grzegorczyk's logic Grz, over finite partial orders. Neither
+/// converse well-foundedness nor its reflexive counterpart is a first-order
+/// frame condition, so instead of copying formulas around a loop the rule
+/// for ◇A makes a last world where A holds. In GL that world also has ◻¬A
+/// (the Löb rule), and in Grz ◻(¬A ⊃ ◻¬A), saying A fails at every world
+/// strictly above it.
+///
+/// ◻B at a world puts B and ◻B at each world it makes, and in Grz B at the
+/// world itself, so the world made for ◇A passes its diagonal formula to
+/// every world above it. In GL a second world made for ◇A above it gets A
+/// and ¬A and closes; in Grz ◇A above it is met at the world itself. Each
+/// ◇A, of which there are finitely many up to the diagonal formulas, thus
+/// makes at most one open world along a chain, and the tableau terminates.
+struct Prover {
+    logic: Logic,
+    tableau: Graph<Formula>,
+    worlds: Graph<World>,
+    // the node of each ◻B expanded, its world, B and ◻B
+    necessities: Vec<(usize, usize, String, String)>
+}
+
+enum Expansion {
+    Literal,
+    Linear(Vec<String>),
+    Branching(Vec<String>),
+    Branches(Vec<Vec<String>>),
+    // the operand at every world above
+    Necessity(String),
+    // the operand at some world above
+    Possibility(String)
+}
+
+impl Prover {
+    fn new(premises: &[String], conclusion: &str, logic: Logic) -> Result<Prover, String> {
+        let mut lines = premises.to_vec();
+        lines.push(negate(conclusion)?);
+        Ok(Prover {
+            logic,
+            tableau: Graph::<Formula>::new(lines),
+            worlds: Graph::<World>::new(1),
+            necessities: Vec::new()
+        })
+    }
+
+    fn run(&mut self, deadline: Option<Instant>) -> Result<(), String> {
+        loop {
+            check_deadline(deadline)?;
+            // the formulas owed to the worlds so far may close branches or
+            // witness a ◇ before another world is made
+            let next = self.next_node()?;
+            if next.is_none_or(|(possibility, _)| possibility) && self.apply_necessities() {
+                self.tableau.find_contradictions(Closure::NoGluts);
+                continue;
+            }
+            let Some((_, node_id)) = next else { break };
+            self.expand(node_id)?;
+            self.tableau.find_contradictions_below(node_id, Closure::NoGluts);
+        }
+        Ok(())
+    }
+
+    /// The first active node, preferring any to those of ◇, with whether
+    /// it is one.
+    fn next_node(&self) -> Result<Option<(bool, usize)>, String> {
+        let mut possibility = None;
+        for node_id in self.tableau.active_nodes().unwrap_or_default() {
+            match rules(self.tableau.get_node(node_id).unwrap().formula(), self.logic)? {
+                Expansion::Possibility(_) => { possibility.get_or_insert((true, node_id)); },
+                _ => return Ok(Some((false, node_id)))
+            }
+        }
+        Ok(possibility)
+    }
+
+    fn expand(&mut self, node_id: usize) -> Result<(), String> {
+        let node = self.tableau.get_node(node_id)
+            .expect("Calling function should make sure node_id is valid");
+        let world = node.world();
+        let expansion = rules(node.formula(), self.logic)?;
+
+        if let Expansion::Necessity(formula) = &expansion {
+            self.necessities.push((node_id, world, formula.clone(), necessity(formula)?));
+        }
+        let terminals = self.tableau.terminal_unclosed(node_id).unwrap_or_default();
+        let branches: Vec<Vec<usize>> = self.tableau.unclosed_branches().unwrap_or_default()
+            .into_iter()
+            .filter(|branch| terminals.contains(branch.last().unwrap()))
+            .collect();
+        for branch in branches {
+            let terminal = *branch.last().unwrap();
+            let options: Vec<Vec<String>> = match &expansion {
+                Expansion::Literal | Expansion::Necessity(_) => continue,
+                Expansion::Linear(formulas) => vec![formulas.clone()],
+                Expansion::Branching(formulas) => formulas.iter().map(|formula| vec![formula.clone()]).collect(),
+                Expansion::Branches(branches) => branches.clone(),
+                Expansion::Possibility(formula) => {
+                    self.witness(world, terminal, &branch, formula)?;
+                    continue;
+                }
+            };
+            if options.iter().any(|formulas| self.tableau.branch_contains_all(&branch, formulas, world)) { continue; }
+            for formulas in options {
+                let missing: Vec<&String> = formulas.iter()
+                    .filter(|formula| !self.tableau.branch_contains(&branch, formula, world))
+                    .collect();
+                let mut parent = terminal;
+                for formula in missing {
+                    parent = self.tableau.new_node_from(parent, formula.clone(), world);
+                }
+            }
+        }
+
+        self.tableau.get_node_mut(node_id).unwrap().deactivate();
+        Ok(())
+    }
+
+    /// Makes `formula` true at some world above `world` on the branch, unless
+    /// one already has it: in Grz at `world` itself if it has the diagonal
+    /// formula, otherwise at a new last world where `formula` holds.
+    fn witness(&mut self, world: usize, terminal: usize, branch: &[usize], formula: &str) -> Result<(), String> {
+        let formula = formula.to_string();
+        if self.above(branch, world).into_iter().any(|w| self.tableau.branch_contains(branch, &formula, w)) {
+            return Ok(());
+        }
+        let diagonal = diagonal(&formula, self.logic)?;
+        if self.logic == Logic::Grz && self.tableau.branch_contains(branch, &diagonal, world) {
+            self.tableau.new_node_from(terminal, formula, world);
+            return Ok(());
+        }
+
+        if branch_worlds(&self.worlds, branch).len() >= MAX_WORLDS {
+            return Err(world_limit());
+        }
+        let new_world = self.worlds.add_world();
+        self.worlds.add_edge(world, new_world);
+        let first = self.tableau.new_node_from(terminal, formula, new_world);
+        self.worlds.get_node_mut(new_world).unwrap().set_origin(first);
+        self.tableau.new_node_from(first, diagonal, new_world);
+        Ok(())
+    }
+
+    /// Writes B, and ◻B, at the worlds made from the world of each ◻B on
+    /// every open branch, and in Grz B at that world too. Returns whether
+    /// anything was added.
+    fn apply_necessities(&mut self) -> bool {
+        let mut changed = false;
+        for branch in self.tableau.unclosed_branches().unwrap_or_default() {
+            let mut owed: Vec<(String, usize)> = Vec::new();
+            for (_, world, formula, boxed) in self.necessities.iter().filter(|(node, ..)| branch.contains(node)) {
+                let mut targets: Vec<(String, usize)> = self.made(&branch, *world).into_iter()
+                    .flat_map(|w| [(formula.clone(), w), (boxed.clone(), w)])
+                    .collect();
+                if self.logic == Logic::Grz {
+                    targets.push((formula.clone(), *world));
+                }
+                for target in targets {
+                    if !self.tableau.branch_contains(&branch, &target.0, target.1) && !owed.contains(&target) {
+                        owed.push(target);
+                    }
+                }
+            }
+            let mut parent = *branch.last().unwrap();
+            for (formula, world) in owed {
+                parent = self.tableau.new_node_from(parent, formula, world);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// The worlds made from `world` on the branch.
+    fn made(&self, branch: &[usize], world: usize) -> Vec<usize> {
+        let mut made: Vec<usize> = self.worlds.adj_to(world).unwrap_or_default()
+            .into_iter()
+            .filter(|w| branch.contains(&self.worlds.get_node(*w).unwrap().origin()))
+            .collect();
+        made.sort();
+        made
+    }
+
+    /// The worlds of the branch `world` sees: those above it, and in Grz
+    /// itself.
+    fn above(&self, branch: &[usize], world: usize) -> Vec<usize> {
+        self.worlds.reachable_by_any(world, &[String::new()]).unwrap_or_default()
+            .into_iter()
+            .filter(|w| *w != world || self.logic == Logic::Grz)
+            .filter(|w| branch.contains(&self.worlds.get_node(*w).unwrap().origin()))
+            .collect()
+    }
+
+    /// The worlds of an open branch, each seeing the worlds above it, true
+    /// atoms being those on the branch.
+    fn countermodel(&self, branch: &[usize]) -> Countermodel {
+        let members = branch_worlds(&self.worlds, branch);
+        let local = |world: usize| members.iter().position(|w| *w == world).unwrap();
+        let mut worlds = Graph::<World>::new(members.len());
+        for world in members.iter() {
+            for w in self.above(branch, *world) {
+                worlds.add_edge(local(*world), local(w));
+            }
+        }
+        let valuation = valuation(&self.tableau, branch, &members, |node| node.relation().is_none());
+        Countermodel::new(worlds, valuation)
+    }
+}
+
+/// The rules, classical but for ◻ and ◇.
+fn rules(formula: &str, logic: Logic) -> Result<Expansion, String> {
+    let instructions = parse_formula(formula)?;
+    let variables = instructions.variables();
+    let expansion = match instructions.operators().as_str() {
+        "" => Expansion::Literal,
+        "⋀" => Expansion::Linear(variables.clone()),
+        "⋁" => Expansion::Branching(variables.clone()),
+        "⊃" => Expansion::Branching(vec![negate(&variables[0])?, variables[1].clone()]),
+        "≡" => Expansion::Branches(agreement(&variables[0], &variables[1], true)?),
+        "⊕" => Expansion::Branches(agreement(&variables[0], &variables[1], false)?),
+        "◻" => Expansion::Necessity(variables[0].clone()),
+        "◇" => Expansion::Possibility(variables[0].clone()),
+        "¬" => {
+            let negated = parse_formula(&variables[0])?;
+            let inner = negated.variables();
+            match negated.operators().as_str() {
+                "" => Expansion::Literal,
+                "¬" => Expansion::Linear(vec![inner[0].clone()]),
+                "⋀" => Expansion::Branching(vec![negate(&inner[0])?, negate(&inner[1])?]),
+                "⋁" => Expansion::Linear(vec![negate(&inner[0])?, negate(&inner[1])?]),
+                "⊃" => Expansion::Linear(vec![inner[0].clone(), negate(&inner[1])?]),
+                "≡" => Expansion::Branches(agreement(&inner[0], &inner[1], false)?),
+                "⊕" => Expansion::Branches(agreement(&inner[0], &inner[1], true)?),
+                "◻" => Expansion::Possibility(negate(&inner[0])?),
+                "◇" => Expansion::Necessity(negate(&inner[0])?),
+                op => return Err(format!("¬{} is not a formula of {:?}", op, logic))
+            }
+        },
+        op => return Err(format!("{} is not an operator of {:?}", op, logic))
+    };
+    Ok(expansion)
+}
+
+/// What a last world where `formula` holds has besides: ◻¬A in GL and
+/// ◻(¬A ⊃ ◻¬A) in Grz.
+fn diagonal(formula: &str, logic: Logic) -> Result<String, String> {
+    let absent = necessity(&negate(formula)?)?;
+    if logic == Logic::Grz {
+        necessity(&parse_expr(&negate(formula)?)?.implies(parse_expr(&absent)?).to_string())
+    } else {
+        Ok(absent)
+    }
+}
+
+/// ◻ of a normalized formula, itself normalized.
+fn necessity(formula: &str) -> Result<String, String> {
+    Ok(Expr::Necessarily(Box::new(parse_expr(formula)?)).to_string())
+}
+
+/// Decides whether the premises entail the conclusion in GL or Grz. Returns
+/// `None` if they do, otherwise a verified countermodel. Each expansion
+/// checks `deadline`.
+pub fn eval_argument(premises: &[String], conclusion: &str, logic: Logic, deadline: Option<Instant>) -> Result<Option<Countermodel>, String> {
+    if !logic.is_provability() {
+        return Err(format!("{:?} is not a provability logic", logic));
+    }
+    let (premises, conclusion) = normalize_argument(premises, conclusion)?;
+
+    let mut prover = Prover::new(&premises, &conclusion, logic)?;
+    prover.run(deadline)?;
+    open_countermodel(&prover.tableau, &premises, &conclusion, |branch| Ok(prover.countermodel(branch)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::eval_with;
+    use crate::graphs::search::GraphSearch;
+
+    fn eval(logic: Logic, formulas: &[&str]) -> Option<Countermodel> {
+        eval_with(formulas, |premises, conclusion| eval_argument(premises, conclusion, logic, None))
+    }
+
+    #[test]
+    fn gl() {
+        let valid: [&[&str]; 7] = [
+            &["◻(◻p ⊃ p) ⊃ ◻p"],
+            &["◻p ⊃ ◻◻p"],
+            &["◻(p ⊃ q) ⊃ (◻p ⊃ ◻q)"],
+            &["¬◻⊥ ⊃ ¬◻¬◻⊥"],
+            &["◇p ⊃ ◇(p ⋀ ◻¬p)"],
+            &["◻(◻p ⊃ p)", "◻p"],
+            &["◻(p ≡ ¬◻p) ⊃ ◻(p ≡ ¬◻⊥)"]
+        ];
+        for formulas in valid {
+            assert!(eval(Logic::GL, formulas).is_none(), "{:?} should be valid in GL", formulas);
+        }
+        let invalid: [&[&str]; 6] = [
+            &["◻p ⊃ p"],
+            &["¬◻⊥"],
+            &["◻(◻p ⊃ p) ⊃ p"],
+            &["◇p ⊃ ◻◇p"],
+            &["◻(◻p ⊃ q) ⋁ ◻(◻q ⊃ p)"],
+            &["◇⊤ ⊃ ◇◇⊤"]
+        ];
+        for formulas in invalid {
+            let countermodel = eval(Logic::GL, formulas)
+                .unwrap_or_else(|| panic!("{:?} should be invalid in GL", formulas));
+            // transitive and irreflexive, so finite and without cycles
+            let worlds = countermodel.worlds();
+            for w in worlds.node_ids() {
+                assert!(!worlds.adj_test(w, w).unwrap());
+                for v in GraphSearch::dfs(worlds, w).marked() {
+                    assert!(v == w || worlds.adj_test(w, v).unwrap());
+                }
+            }
+        }
+        assert_eq!("w0: {} R {}\n", eval(Logic::GL, &["¬◻⊥"]).unwrap().to_string());
+    }
+
+    #[test]
+    fn grz() {
+        let valid: [&[&str]; 6] = [
+            &["◻(◻(p ⊃ ◻p) ⊃ p) ⊃ p"],
+            &["◻p ⊃ p"],
+            &["◻p ⊃ ◻◻p"],
+            &["◻(◻(p ⊃ ◻p) ⊃ p) ⊃ ◻p"],
+            &["◇p ⊃ ◇(p ⋀ ◻(¬p ⊃ ◻¬p))"],
+            &["◻(◻(p ⊃ ◻p) ⊃ p)", "p"]
+        ];
+        for formulas in valid {
+            assert!(eval(Logic::Grz, formulas).is_none(), "{:?} should be valid in Grz", formulas);
+        }
+        let invalid: [&[&str]; 5] = [
+            &["◻(◻p ⊃ p) ⊃ ◻p"],
+            &["p ⊃ ◻p"],
+            &["◇◻p ⊃ ◻◇p"],
+            &["◇p ⊃ ◻◇p"],
+            &["◻(◻p ⊃ q) ⋁ ◻(◻q ⊃ p)"]
+        ];
+        for formulas in invalid {
+            let countermodel = eval(Logic::Grz, formulas)
+                .unwrap_or_else(|| panic!("{:?} should be invalid in Grz", formulas));
+            let worlds = countermodel.worlds();
+            for w in worlds.node_ids() {
+                assert!(worlds.adj_test(w, w).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(eval_argument(&[], "[a]p ⊃ ◻p", Logic::GL, None).is_err());
+        assert!(eval_argument(&[], "G p ⊃ p", Logic::Grz, None).is_err());
+        assert!(eval_argument(&[], "◻p ⊃ p", Logic::K3, None).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+use std::time::Instant;
+use crate::ast::VERUM;
+use crate::countermodel::Countermodel;
+use crate::graphs::{Graph, node::{Formula, World}};
+use crate::model::MAX_WORLDS;
+use crate::parser::{negate, normalize, parse_formula};
+
+/// Branches making `a` and `b` both true or both false, or exactly one of
+/// them true.
+pub(crate) fn agreement(a: &str, b: &str, agree: bool) -> Result<Vec<Vec<String>>, String> {
+    let (not_a, not_b) = (negate(a)?, negate(b)?);
+    Ok(if agree {
+        vec![vec![a.to_string(), b.to_string()], vec![not_a, not_b]]
+    } else {
+        vec![vec![a.to_string(), not_b], vec![not_a, b.to_string()]]
+    })
+}
+
+/// The premises and conclusion of an argument in normal form.
+pub(crate) fn normalize_argument(premises: &[String], conclusion: &str) -> Result<(Vec<String>, String), String> {
+    let premises = premises.iter()
+        .map(|premise| normalize(premise))
+        .collect::<Result<Vec<String>, String>>()?;
+    Ok((premises, normalize(conclusion)?))
+}
+
+/// The error of a prover whose branch has as many worlds as it may.
+pub(crate) fn world_limit() -> String {
+    format!("World limit of {} reached on a branch", MAX_WORLDS)
+}
+
+/// The error of a prover still running at its deadline.
+pub(crate) const TIME_LIMIT: &str = "Time limit reached";
+
+/// Fails once `deadline` has passed.
+pub(crate) fn check_deadline(deadline: Option<Instant>) -> Result<(), String> {
+    if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+        return Err(TIME_LIMIT.to_string());
+    }
+    Ok(())
+}
+
+/// Whether `err` is a prover running into a limit rather than failing.
+pub(crate) fn is_limit(err: &str) -> bool {
+    err == world_limit() || err == TIME_LIMIT
+}
+
+/// The worlds on the branch, those whose first line is on it.
+pub(crate) fn branch_worlds(worlds: &Graph<World>, branch: &[usize]) -> Vec<usize> {
+    worlds.node_ids().into_iter()
+        .filter(|w| branch.contains(&worlds.get_node(*w).unwrap().origin()))
+        .collect()
+}
+
+/// Reads a countermodel off the first open branch of a finished tableau and
+/// checks it against the argument. Returns `None` if every branch closed.
+pub(crate) fn open_countermodel<F>(tableau: &Graph<Formula>, premises: &[String], conclusion: &str, countermodel: F) -> Result<Option<Countermodel>, String>
+    where F: FnOnce(&[usize]) -> Result<Countermodel, String>
+{
+    let Some(branches) = tableau.unclosed_branches() else {
+        return Ok(None);
+    };
+    let countermodel = countermodel(&branches[0])?;
+    countermodel.verify(premises, Some(&conclusion.to_string()))?;
+    Ok(Some(countermodel))
+}
+
+/// The atoms true at each of `worlds`, being those written at it on the
+/// branch by the lines `keep` accepts.
+pub(crate) fn valuation<F>(tableau: &Graph<Formula>, branch: &[usize], worlds: &[usize], keep: F) -> Vec<HashSet<String>>
+    where F: Fn(&Formula) -> bool
+{
+    worlds.iter()
+        .map(|world| {
+            branch.iter()
+                .map(|idx| tableau.get_node(*idx).unwrap())
+                .filter(|node| node.world() == *world && node.formula() != VERUM && keep(node))
+                .filter(|node| parse_formula(node.formula()).is_ok_and(|i| i.operators().is_empty()))
+                .map(|node| node.formula().clone())
+                .collect::<HashSet<String>>()
+        })
+        .collect()
+}
+
+/// Runs a decision procedure on an argument written as its premises followed
+/// by its conclusion.
+#[cfg(test)]
+pub(crate) fn eval_with<F>(formulas: &[&str], eval_argument: F) -> Option<Countermodel>
+    where F: FnOnce(&[String], &str) -> Result<Option<Countermodel>, String>
+{
+    let (conclusion, premises) = formulas.split_last().unwrap();
+    let premises: Vec<String> = premises.iter().map(|premise| premise.to_string()).collect();
+    eval_argument(&premises, conclusion).unwrap()
+}
@@ -0,0 +1,418 @@
+use std::collections::{HashMap, HashSet};
+use crate::countermodel::Countermodel;
+use crate::graphs::{Graph, node::World};
+use crate::normal_forms::{Cnf, tseitin};
+use crate::parser::parse_expr;
+
+/// Variable `v` appears as literal `2v` and its negation as `2v + 1`.
+type Lit = usize;
+
+fn var(lit: Lit) -> usize {
+    lit >> 1
+}
+
+fn lit(var: usize, positive: bool) -> Lit {
+    2 * var + usize::from(!positive)
+}
+
+/// CDCL solver: unit propagation over two watched literals per clause,
+/// first-UIP clause learning with non-chronological backtracking, and
+/// activity-based branching.
+pub struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    // clauses watching each literal, visited when it becomes false
+    watches: Vec<Vec<usize>>,
+    units: Vec<Lit>,
+    assigns: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>,
+    qhead: usize,
+    activity: Vec<f64>,
+    var_inc: f64,
+    empty_clause: bool,
+    conflicts: usize
+}
+
+impl Solver {
+    pub fn new(num_vars: usize) -> Solver {
+        Solver {
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2 * num_vars],
+            units: Vec::new(),
+            assigns: vec![None; num_vars],
+            level: vec![0; num_vars],
+            reason: vec![None; num_vars],
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            qhead: 0,
+            activity: vec![0.0; num_vars],
+            var_inc: 1.0,
+            empty_clause: false,
+            conflicts: 0
+        }
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.assigns.len()
+    }
+
+    pub fn conflicts(&self) -> usize {
+        self.conflicts
+    }
+
+    /// Adds a clause given as (variable, polarity) pairs.
+    pub fn add_clause(&mut self, clause: &[(usize, bool)]) {
+        let mut lits: Vec<Lit> = Vec::with_capacity(clause.len());
+        for (v, positive) in clause {
+            let l = lit(*v, *positive);
+            if lits.contains(&(l ^ 1)) { return; }
+            if !lits.contains(&l) { lits.push(l); }
+        }
+
+        match lits.len() {
+            0 => self.empty_clause = true,
+            1 => self.units.push(lits[0]),
+            _ => { self.attach(lits); }
+        }
+    }
+
+    fn attach(&mut self, clause: Vec<Lit>) -> usize {
+        let idx = self.clauses.len();
+        self.watches[clause[0]].push(idx);
+        self.watches[clause[1]].push(idx);
+        self.clauses.push(clause);
+        idx
+    }
+
+    fn value(&self, l: Lit) -> Option<bool> {
+        self.assigns[var(l)].map(|value| value == (l & 1 == 0))
+    }
+
+    fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    fn enqueue(&mut self, l: Lit, reason: Option<usize>) {
+        let v = var(l);
+        self.assigns[v] = Some(l & 1 == 0);
+        self.level[v] = self.decision_level();
+        self.reason[v] = reason;
+        self.trail.push(l);
+    }
+
+    /// Propagates the trail, returning a conflicting clause if one is found.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.qhead < self.trail.len() {
+            let false_lit = self.trail[self.qhead] ^ 1;
+            self.qhead += 1;
+
+            let mut watching = std::mem::take(&mut self.watches[false_lit]);
+            let mut i = 0;
+            while i < watching.len() {
+                let ci = watching[i];
+                if self.clauses[ci][0] == false_lit {
+                    self.clauses[ci].swap(0, 1);
+                }
+                let first = self.clauses[ci][0];
+                if self.value(first) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+
+                let replacement = (2..self.clauses[ci].len())
+                    .find(|k| self.value(self.clauses[ci][*k]) != Some(false));
+                if let Some(k) = replacement {
+                    self.clauses[ci].swap(1, k);
+                    let new_watch = self.clauses[ci][1];
+                    self.watches[new_watch].push(ci);
+                    watching.swap_remove(i);
+                    continue;
+                }
+
+                if self.value(first) == Some(false) {
+                    self.watches[false_lit] = watching;
+                    self.qhead = self.trail.len();
+                    return Some(ci);
+                }
+                self.enqueue(first, Some(ci));
+                i += 1;
+            }
+            self.watches[false_lit] = watching;
+        }
+        None
+    }
+
+    /// First-UIP conflict analysis. Returns the learnt clause, asserting
+    /// literal first, and the level to backtrack to.
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let mut learnt: Vec<Lit> = vec![0];
+        let mut seen = vec![false; self.num_vars()];
+        let mut counter = 0;
+        let mut clause = conflict;
+        let mut skip_first = false;
+        let mut idx = self.trail.len();
+
+        loop {
+            let start = usize::from(skip_first);
+            for k in start..self.clauses[clause].len() {
+                let q = self.clauses[clause][k];
+                let v = var(q);
+                if seen[v] || self.level[v] == 0 { continue; }
+                seen[v] = true;
+                self.bump(v);
+                if self.level[v] == self.decision_level() {
+                    counter += 1;
+                } else {
+                    learnt.push(q);
+                }
+            }
+
+            loop {
+                idx -= 1;
+                if seen[var(self.trail[idx])] { break; }
+            }
+            let p = self.trail[idx];
+            seen[var(p)] = false;
+            counter -= 1;
+            if counter == 0 {
+                learnt[0] = p ^ 1;
+                break;
+            }
+            clause = self.reason[var(p)].expect("Implied literals at the conflict level have reasons");
+            skip_first = true;
+        }
+
+        let mut backtrack_level = 0;
+        if learnt.len() > 1 {
+            let (max_idx, _) = learnt.iter().enumerate().skip(1)
+                .max_by_key(|(_, l)| self.level[var(**l)])
+                .unwrap();
+            learnt.swap(1, max_idx);
+            backtrack_level = self.level[var(learnt[1])];
+        }
+        (learnt, backtrack_level)
+    }
+
+    fn bump(&mut self, v: usize) {
+        self.activity[v] += self.var_inc;
+        if self.activity[v] > 1e100 {
+            for activity in self.activity.iter_mut() { *activity *= 1e-100; }
+            self.var_inc *= 1e-100;
+        }
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.decision_level() <= level { return; }
+        let limit = self.trail_lim[level];
+        for l in self.trail.drain(limit..) {
+            self.assigns[var(l)] = None;
+            self.reason[var(l)] = None;
+        }
+        self.trail_lim.truncate(level);
+        self.qhead = self.trail.len();
+    }
+
+    fn pick_branch(&self) -> Option<usize> {
+        (0..self.num_vars())
+            .filter(|v| self.assigns[*v].is_none())
+            .max_by(|a, b| self.activity[*a].total_cmp(&self.activity[*b]).then(b.cmp(a)))
+    }
+
+    /// Returns a satisfying assignment, one value per variable, if there is one.
+    pub fn solve(&mut self) -> Option<Vec<bool>> {
+        if self.empty_clause { return None; }
+        self.backtrack(0);
+        for unit in self.units.clone() {
+            match self.value(unit) {
+                Some(false) => return None,
+                Some(true) => {},
+                None => self.enqueue(unit, None)
+            }
+        }
+
+        loop {
+            if let Some(conflict) = self.propagate() {
+                self.conflicts += 1;
+                if self.decision_level() == 0 { return None; }
+                let (learnt, backtrack_level) = self.analyze(conflict);
+                self.backtrack(backtrack_level);
+                if learnt.len() == 1 {
+                    self.units.push(learnt[0]);
+                    self.enqueue(learnt[0], None);
+                } else {
+                    let asserting = learnt[0];
+                    let ci = self.attach(learnt);
+                    self.enqueue(asserting, Some(ci));
+                }
+                self.var_inc *= 1.05;
+            } else {
+                match self.pick_branch() {
+                    None => {
+                        return Some(self.assigns.iter().map(|value| value.unwrap()).collect());
+                    },
+                    Some(v) => {
+                        self.trail_lim.push(self.trail.len());
+                        self.enqueue(lit(v, false), None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Solves a CNF over named atoms, returning the value of every atom in it.
+pub fn solve_cnf(cnf: &Cnf) -> Option<HashMap<String, bool>> {
+    let mut names: Vec<&String> = Vec::new();
+    let mut index: HashMap<&String, usize> = HashMap::new();
+    for literal in cnf.clauses().iter().flatten() {
+        if !index.contains_key(literal.atom()) {
+            index.insert(literal.atom(), names.len());
+            names.push(literal.atom());
+        }
+    }
+
+    let mut solver = Solver::new(names.len());
+    for clause in cnf.clauses() {
+        let clause: Vec<(usize, bool)> = clause.iter()
+            .map(|literal| (index[literal.atom()], literal.positive()))
+            .collect();
+        solver.add_clause(&clause);
+    }
+
+    let assignment = solver.solve()?;
+    Some(names.into_iter().cloned().zip(assignment).collect())
+}
+
+/// Decides a propositional argument by checking premises ⋀ ¬conclusion for
+/// satisfiability. Returns a verified single world countermodel if the
+/// argument is invalid.
+pub fn eval_argument(premises: &[String], conclusion: &str) -> Result<Option<Countermodel>, String> {
+    let mut formula = parse_expr(conclusion)?.negated();
+    for premise in premises.iter().rev() {
+        formula = parse_expr(premise)?.and(formula);
+    }
+    if formula.is_modal() || formula.is_temporal() || formula.is_quantified() {
+        return Err(format!("SAT needs propositional formulas, found {}", formula));
+    }
+
+    let Some(assignment) = solve_cnf(&tseitin(&formula)) else {
+        return Ok(None);
+    };
+    let true_atoms: HashSet<String> = formula.atoms().into_iter()
+        .filter(|atom| assignment.get(atom).copied().unwrap_or(false))
+        .collect();
+
+    let countermodel = Countermodel::new(Graph::<World>::new(1), vec![true_atoms]);
+    countermodel.verify(premises, Some(&conclusion.to_string()))?;
+    Ok(Some(countermodel))
+}
+
+/// Whether every formula is free of modal and temporal operators and of
+/// quantifiers.
+pub fn is_propositional(formulas: &[String]) -> Result<bool, String> {
+    for formula in formulas {
+        let expr = parse_expr(formula)?;
+        if expr.is_modal() || expr.is_temporal() || expr.is_quantified() { return Ok(false); }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(num_vars: usize, clauses: &[Vec<(usize, bool)>]) -> bool {
+        (0..1_usize << num_vars).any(|row| {
+            clauses.iter().all(|clause| {
+                clause.iter().any(|(v, positive)| (row & (1 << v) != 0) == *positive)
+            })
+        })
+    }
+
+    fn satisfies(assignment: &[bool], clauses: &[Vec<(usize, bool)>]) -> bool {
+        clauses.iter().all(|clause| clause.iter().any(|(v, positive)| assignment[*v] == *positive))
+    }
+
+    #[test]
+    fn small() {
+        let mut solver = Solver::new(2);
+        solver.add_clause(&[(0, true), (1, true)]);
+        solver.add_clause(&[(0, false)]);
+        assert_eq!(Some(vec![false, true]), solver.solve());
+
+        let mut solver = Solver::new(1);
+        solver.add_clause(&[(0, true)]);
+        solver.add_clause(&[(0, false)]);
+        assert_eq!(None, solver.solve());
+
+        let mut solver = Solver::new(1);
+        solver.add_clause(&[]);
+        assert_eq!(None, solver.solve());
+    }
+
+    #[test]
+    fn pigeonhole() {
+        // four pigeons, three holes: p(i, h) = 3i + h
+        let mut clauses: Vec<Vec<(usize, bool)>> = Vec::new();
+        for i in 0..4 {
+            clauses.push((0..3).map(|h| (3 * i + h, true)).collect());
+        }
+        for h in 0..3 {
+            for i in 0..4 {
+                for j in i + 1..4 {
+                    clauses.push(vec![(3 * i + h, false), (3 * j + h, false)]);
+                }
+            }
+        }
+        let mut solver = Solver::new(12);
+        for clause in clauses.iter() {
+            solver.add_clause(clause);
+        }
+        assert_eq!(None, solver.solve());
+        assert!(solver.conflicts() > 0);
+    }
+
+    #[test]
+    fn random_agrees_with_brute_force() {
+        let mut seed: u64 = 0x2545F4914F6CDD1D;
+        let mut next = |bound: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % bound as u64) as usize
+        };
+
+        for _ in 0..200 {
+            let num_vars = 3 + next(6);
+            let clauses: Vec<Vec<(usize, bool)>> = (0..next(4 * num_vars) + 1)
+                .map(|_| (0..3).map(|_| (next(num_vars), next(2) == 0)).collect())
+                .collect();
+
+            let mut solver = Solver::new(num_vars);
+            for clause in clauses.iter() {
+                solver.add_clause(clause);
+            }
+            match solver.solve() {
+                Some(assignment) => assert!(satisfies(&assignment, &clauses)),
+                None => assert!(!brute_force(num_vars, &clauses), "{:?}", clauses)
+            }
+        }
+    }
+
+    #[test]
+    fn arguments() {
+        let premises = vec!["p ⊃ q".to_string(), "q ⊃ r".to_string()];
+        assert!(eval_argument(&premises, "p ⊃ r").unwrap().is_none());
+
+        let premises = vec!["p ⋁ q".to_string(), "q ⊃ r".to_string()];
+        let countermodel = eval_argument(&premises, "r").unwrap().unwrap();
+        assert_eq!(&HashSet::from(["p".to_string()]), countermodel.true_atoms(0).unwrap());
+
+        assert!(eval_argument(&[], "◻p ⊃ p").is_err());
+        assert!(is_propositional(&premises).unwrap());
+        assert!(!is_propositional(&["◇p".to_string()]).unwrap());
+        assert!(!is_propositional(&["∀x P(x)".to_string()]).unwrap());
+    }
+}
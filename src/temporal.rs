@@ -0,0 +1,370 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Instant;
+use crate::ast::{Expr, TemporalOp};
+use crate::countermodel::Countermodel;
+use crate::graphs::Graph;
+use crate::parser::parse_expr;
+use crate::prover::check_deadline;
+
+/// Beyond this many elementary formulas the states of a time are too many
+/// to enumerate.
+pub const MAX_ELEMENTARY: usize = 16;
+
+/// Tableau for linear time in the style of Lichtenstein and Pnueli. A state
+/// fixes the truth of every elementary formula of the closure, the atoms and
+/// the X, Y and Z formulas, which decides every other formula of the closure
+/// by unfolding: G p is p ⋀ X G p, p U q is q ⋁ (p ⋀ X(p U q)), H p is
+/// p ⋀ Z H p, and so on. Equal states are one node, so the tableau is a
+/// graph, and a model is a path from an initial state into a strongly
+/// connected part of it that fulfils its own eventualities.
+struct Tableau {
+    elementary: Vec<Expr>,
+    index: HashMap<Expr, usize>,
+    // F p, p U q and ¬G p, each with what fulfils it
+    eventualities: Vec<(Expr, Expr)>,
+    states: Vec<Vec<bool>>,
+    known: HashMap<Vec<bool>, usize>,
+    successors: Vec<Vec<usize>>,
+    initial: Vec<usize>
+}
+
+impl Tableau {
+    fn new(formulas: &[Expr], deadline: Option<Instant>) -> Result<Tableau, String> {
+        let mut elementary: Vec<Expr> = Vec::new();
+        let mut eventualities = Vec::new();
+        for formula in formulas {
+            for sub in formula.subformulas() {
+                let next = |expr: &Expr| Expr::Temporal(TemporalOp::Next, Box::new(expr.clone()));
+                let unfolding = match &sub {
+                    Expr::Atom(_) => sub.clone(),
+                    Expr::Temporal(TemporalOp::Next | TemporalOp::Yesterday | TemporalOp::WeakYesterday, _) => sub.clone(),
+                    Expr::Temporal(TemporalOp::Always, a) => {
+                        eventualities.push((sub.clone().negated(), a.clone().negated()));
+                        next(&sub)
+                    },
+                    Expr::Temporal(TemporalOp::Eventually, a) => {
+                        eventualities.push((sub.clone(), (**a).clone()));
+                        next(&sub)
+                    },
+                    Expr::Until(_, b) => {
+                        eventualities.push((sub.clone(), (**b).clone()));
+                        next(&sub)
+                    },
+                    Expr::Temporal(TemporalOp::Historically, _) => Expr::Temporal(TemporalOp::WeakYesterday, Box::new(sub.clone())),
+                    Expr::Temporal(TemporalOp::Once, _) | Expr::Since(..) => Expr::Temporal(TemporalOp::Yesterday, Box::new(sub.clone())),
+                    Expr::Not(_) | Expr::And(..) | Expr::Or(..) | Expr::Implies(..) => continue,
+                    Expr::Iff(..) | Expr::Xor(..) | Expr::Verum | Expr::Falsum => continue,
+                    _ => return Err(format!("{} has no linear time semantics", sub))
+                };
+                if !elementary.contains(&unfolding) {
+                    elementary.push(unfolding);
+                }
+            }
+        }
+        if elementary.len() > MAX_ELEMENTARY {
+            return Err(format!("{} elementary formulas, at most {} are supported", elementary.len(), MAX_ELEMENTARY));
+        }
+
+        let index = elementary.iter().enumerate().map(|(idx, expr)| (expr.clone(), idx)).collect();
+        let mut tableau = Tableau {
+            elementary,
+            index,
+            eventualities,
+            states: Vec::new(),
+            known: HashMap::new(),
+            successors: Vec::new(),
+            initial: Vec::new()
+        };
+        tableau.expand(formulas, deadline)?;
+        Ok(tableau)
+    }
+
+    /// Truth of a formula of the closure in `state`.
+    fn holds(&self, expr: &Expr, state: &[bool]) -> bool {
+        let lookup = |op: TemporalOp, expr: &Expr| state[self.index[&Expr::Temporal(op, Box::new(expr.clone()))]];
+        match expr {
+            Expr::Not(a) => !self.holds(a, state),
+            Expr::And(a, b) => self.holds(a, state) && self.holds(b, state),
+            Expr::Or(a, b) => self.holds(a, state) || self.holds(b, state),
+            Expr::Implies(a, b) => !self.holds(a, state) || self.holds(b, state),
+            Expr::Iff(a, b) => self.holds(a, state) == self.holds(b, state),
+            Expr::Xor(a, b) => self.holds(a, state) != self.holds(b, state),
+            Expr::Verum => true,
+            Expr::Falsum => false,
+            Expr::Temporal(TemporalOp::Always, a) => self.holds(a, state) && lookup(TemporalOp::Next, expr),
+            Expr::Temporal(TemporalOp::Eventually, a) => self.holds(a, state) || lookup(TemporalOp::Next, expr),
+            Expr::Until(a, b) => self.holds(b, state) || (self.holds(a, state) && lookup(TemporalOp::Next, expr)),
+            Expr::Temporal(TemporalOp::Historically, a) => self.holds(a, state) && lookup(TemporalOp::WeakYesterday, expr),
+            Expr::Temporal(TemporalOp::Once, a) => self.holds(a, state) || lookup(TemporalOp::Yesterday, expr),
+            Expr::Since(a, b) => self.holds(b, state) || (self.holds(a, state) && lookup(TemporalOp::Yesterday, expr)),
+            _ => state[self.index[expr]]
+        }
+    }
+
+    /// Every state after `previous`, or every initial state making
+    /// `formulas` true. Y and Z formulas are fixed by the previous state,
+    /// false and true at time 0, and each X p of the previous state fixes p.
+    fn states_after(&self, previous: Option<&[bool]>, formulas: &[Expr]) -> Vec<Vec<bool>> {
+        let mut fixed: Vec<Option<bool>> = vec![None; self.elementary.len()];
+        let mut required: Vec<(&Expr, bool)> = formulas.iter().map(|formula| (formula, true)).collect();
+        for (idx, expr) in self.elementary.iter().enumerate() {
+            match (expr, previous) {
+                (Expr::Temporal(TemporalOp::Yesterday, _), None) => fixed[idx] = Some(false),
+                (Expr::Temporal(TemporalOp::WeakYesterday, _), None) => fixed[idx] = Some(true),
+                (Expr::Temporal(TemporalOp::Yesterday | TemporalOp::WeakYesterday, a), Some(previous)) => {
+                    fixed[idx] = Some(self.holds(a, previous));
+                },
+                (Expr::Temporal(TemporalOp::Next, a), Some(previous)) => required.push((a, previous[idx])),
+                _ => {}
+            }
+        }
+
+        let free: Vec<usize> = (0..fixed.len()).filter(|idx| fixed[*idx].is_none()).collect();
+        let mut states = Vec::new();
+        for bits in 0..1usize << free.len() {
+            let mut state: Vec<bool> = fixed.iter().map(|value| value.unwrap_or(false)).collect();
+            for (bit, idx) in free.iter().enumerate() {
+                state[*idx] = bits >> bit & 1 == 1;
+            }
+            if required.iter().all(|(formula, value)| self.holds(formula, &state) == *value) {
+                states.push(state);
+            }
+        }
+        states
+    }
+
+    fn add_state(&mut self, state: Vec<bool>, queue: &mut VecDeque<usize>) -> usize {
+        if let Some(id) = self.known.get(&state) {
+            return *id;
+        }
+        let id = self.states.len();
+        self.known.insert(state.clone(), id);
+        self.states.push(state);
+        self.successors.push(Vec::new());
+        queue.push_back(id);
+        id
+    }
+
+    /// Builds every state reachable from the initial states, failing at
+    /// `deadline`.
+    fn expand(&mut self, formulas: &[Expr], deadline: Option<Instant>) -> Result<(), String> {
+        let mut queue = VecDeque::new();
+        for state in self.states_after(None, formulas) {
+            let id = self.add_state(state, &mut queue);
+            self.initial.push(id);
+        }
+        while let Some(id) = queue.pop_front() {
+            check_deadline(deadline)?;
+            let previous = self.states[id].clone();
+            for state in self.states_after(Some(&previous), &[]) {
+                let next = self.add_state(state, &mut queue);
+                self.successors[id].push(next);
+            }
+        }
+        Ok(())
+    }
+
+    /// States that can be visited infinitely often: repeatedly drops states
+    /// outside any cycle, and states with an eventuality nothing in their
+    /// strongly connected component fulfils. Returns the component of each
+    /// remaining state.
+    fn fulfilling_components(&self) -> HashMap<usize, Vec<usize>> {
+        let mut alive: Vec<usize> = (0..self.states.len()).collect();
+        loop {
+            let local: HashMap<usize, usize> = alive.iter().enumerate().map(|(idx, id)| (*id, idx)).collect();
+            let mut graph: Graph<usize> = Graph::with_nodes(alive.clone());
+            for id in alive.iter() {
+                for next in self.successors[*id].iter() {
+                    if let Some(next) = local.get(next) {
+                        graph.add_edge(local[id], *next);
+                    }
+                }
+            }
+
+            let components = graph.strongly_connected_components();
+            let mut survivors: HashMap<usize, Vec<usize>> = HashMap::new();
+            for members in components.components() {
+                let ids: Vec<usize> = members.iter().map(|idx| alive[*idx]).collect();
+                let cyclic = members.len() > 1 || graph.adj_test(members[0], members[0]).unwrap();
+                if !cyclic { continue; }
+                for id in ids.iter() {
+                    let fulfilled = self.eventualities.iter().all(|(promise, goal)| {
+                        !self.holds(promise, &self.states[*id])
+                            || ids.iter().any(|other| self.holds(goal, &self.states[*other]))
+                    });
+                    if fulfilled {
+                        survivors.insert(*id, ids.clone());
+                    }
+                }
+            }
+
+            if survivors.len() == alive.len() {
+                return survivors;
+            }
+            alive = (0..self.states.len()).filter(|id| survivors.contains_key(id)).collect();
+        }
+    }
+
+    /// Shortest path of at least one step from `from` to a state meeting
+    /// `goal`, through `within` if given, without `from` itself.
+    fn path(&self, from: usize, goal: impl Fn(usize) -> bool, within: Option<&HashSet<usize>>) -> Option<Vec<usize>> {
+        let mut parent: HashMap<usize, usize> = HashMap::new();
+        let mut queue: VecDeque<usize> = VecDeque::from([from]);
+        while let Some(id) = queue.pop_front() {
+            for next in self.successors[id].iter().copied() {
+                if parent.contains_key(&next) || within.is_some_and(|within| !within.contains(&next)) {
+                    continue;
+                }
+                parent.insert(next, id);
+                if goal(next) {
+                    let mut path = vec![next];
+                    let mut step = id;
+                    while step != from {
+                        path.push(step);
+                        step = parent[&step];
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                queue.push_back(next);
+            }
+        }
+        None
+    }
+
+    /// What fulfils the first eventuality promised on a cycle through `states`
+    /// that none of them fulfils.
+    fn unfulfilled(&self, states: &[usize]) -> Option<&Expr> {
+        self.eventualities.iter()
+            .find(|(promise, goal)| {
+                states.iter().any(|id| self.holds(promise, &self.states[*id]))
+                    && !states.iter().any(|id| self.holds(goal, &self.states[*id]))
+            })
+            .map(|(_, goal)| goal)
+    }
+
+    /// A lasso through the tableau: the states up to a fulfilling component
+    /// and a cycle in it fulfilling its eventualities, with the time the
+    /// cycle starts at.
+    fn lasso(&self) -> Option<(Vec<usize>, usize)> {
+        let components = self.fulfilling_components();
+        let (mut prefix, entry) = self.initial.iter()
+            .filter_map(|start| {
+                if components.contains_key(start) {
+                    return Some((Vec::new(), *start));
+                }
+                let mut path = self.path(*start, |id| components.contains_key(&id), None)?;
+                let entry = path.pop().unwrap();
+                path.insert(0, *start);
+                Some((path, entry))
+            })
+            .min_by_key(|(prefix, _)| prefix.len())?;
+
+        // go on to a state fulfilling the first eventuality the cycle leaves
+        // unfulfilled until there is none, each time trying the way back
+        let component: HashSet<usize> = components[&entry].iter().copied().collect();
+        let mut cycle = vec![entry];
+        loop {
+            let mut back = self.path(*cycle.last().unwrap(), |id| id == entry, Some(&component))?;
+            back.pop();
+            let closed: Vec<usize> = cycle.iter().chain(back.iter()).copied().collect();
+            let Some(goal) = self.unfulfilled(&closed) else {
+                cycle.extend(back);
+                break;
+            };
+            cycle.extend(self.path(*cycle.last().unwrap(), |id| self.holds(goal, &self.states[id]), Some(&component))?);
+        }
+
+        let loop_start = prefix.len();
+        prefix.extend(cycle);
+        Some((prefix, loop_start))
+    }
+}
+
+/// Decides an argument over linear time, the premises and conclusion being
+/// evaluated at time 0. Returns a verified lasso shaped trace if the
+/// argument is invalid. Building the states stops at `deadline`.
+pub fn eval_argument(premises: &[String], conclusion: &str, deadline: Option<Instant>) -> Result<Option<Countermodel>, String> {
+    let mut formulas = premises.iter()
+        .map(|premise| parse_expr(premise))
+        .collect::<Result<Vec<Expr>, String>>()?;
+    formulas.push(parse_expr(conclusion)?.negated());
+
+    let tableau = Tableau::new(&formulas, deadline)?;
+    let Some((states, loop_start)) = tableau.lasso() else {
+        return Ok(None);
+    };
+    let valuation = states.iter()
+        .map(|id| {
+            tableau.elementary.iter().enumerate()
+                .filter(|(idx, expr)| expr.is_atom() && tableau.states[*id][*idx])
+                .map(|(_, expr)| expr.to_string())
+                .collect::<HashSet<String>>()
+        })
+        .collect();
+
+    let countermodel = Countermodel::trace(valuation, loop_start);
+    countermodel.verify(premises, Some(&conclusion.to_string()))?;
+    Ok(Some(countermodel))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::eval_with;
+
+    fn eval(formulas: &[&str]) -> Option<Countermodel> {
+        eval_with(formulas, |premises, conclusion| eval_argument(premises, conclusion, None))
+    }
+
+    #[test]
+    fn valid() {
+        let valid: [&[&str]; 11] = [
+            &["G (p ⊃ q)", "G p", "G q"],
+            &["G p ⊃ p ⋀ X G p"],
+            &["F p ⊃ p ⋁ X F p"],
+            &["p U q ⊃ F q"],
+            &["G F p ⊃ F p"],
+            &["G (p ⊃ F q) ⋀ G F p ⊃ G F q"],
+            // induction: ¬p can only be put off forever by never reaching it
+            &["p", "G (p ⊃ X p)", "G p"],
+            &["p", "X Y p"],
+            &["¬Y p ⋀ (H p ⊃ p) ⋀ Z p"],
+            &["X X (p S q) ⊃ X X P q"],
+            &["G (p ⊃ X q) ⋀ G (q ⊃ X p) ⋀ p ⊃ G (p ⋁ q)"],
+        ];
+        for formulas in valid {
+            assert!(eval(formulas).is_none(), "{:?} should be valid", formulas);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let invalid: [&[&str]; 6] = [
+            &["G F p ⊃ F G p"],
+            &["F q ⊃ p U q"],
+            &["F p"],
+            &["X P q ⊃ q"],
+            &["G (p ⊃ F q)", "G F q"],
+            &["G (q ⊃ Y p)", "F q ⊃ F (p ⋀ q)"],
+        ];
+        for formulas in invalid {
+            assert!(eval(formulas).is_some(), "{:?} should be invalid", formulas);
+        }
+
+        // p infinitely often, but never for good: the loop has both p and ¬p
+        let countermodel = eval(&["G F p ⊃ F G p"]).unwrap();
+        assert!(countermodel.loop_start().is_some());
+        assert!(countermodel.satisfies("G F p ⋀ G F ¬p", 0).unwrap());
+
+        let countermodel = eval(&["F p"]).unwrap();
+        assert_eq!("w0: {} → w0\n", countermodel.to_string());
+    }
+
+    #[test]
+    fn limits() {
+        assert!(eval_argument(&[], "◻p ⋀ G p", None).is_err());
+        let wide = (0..=MAX_ELEMENTARY).map(|n| format!("p{}", n)).collect::<Vec<String>>().join(" ⋀ ");
+        assert!(eval_argument(&[], &format!("G ({})", wide), None).is_err());
+    }
+}
@@ -0,0 +1,464 @@
+use std::env;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use crate::ast::{Expr, IDENTITY, Quantifier};
+
+/// Premises and conclusion read from a TPTP or QMLTP problem.
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    premises: Vec<Expr>,
+    conclusion: Option<Expr>,
+    // clauses of a CNF problem's negated conjecture, together the negation
+    // of the conclusion
+    negated_conjectures: Vec<Expr>
+}
+
+impl Problem {
+    pub fn premises(&self) -> &Vec<Expr> {
+        &self.premises
+    }
+
+    pub fn conclusion(&self) -> Option<&Expr> {
+        self.conclusion.as_ref()
+    }
+
+    /// Formulas in the one-per-line order `Model::new` expects, conclusion last.
+    pub fn formulas(&self) -> Result<Vec<String>, String> {
+        let conclusion = self.conclusion.as_ref()
+            .ok_or("Problem has no conjecture".to_string())?;
+        Ok(self.premises.iter().chain([conclusion]).map(|f| f.to_string()).collect())
+    }
+
+    /// Makes the conclusion the negation of the negated conjecture's
+    /// clauses taken together, once every file is read.
+    fn conclude(mut self) -> Result<Problem, String> {
+        let negated_conjectures = std::mem::take(&mut self.negated_conjectures);
+        if negated_conjectures.is_empty() {
+            return Ok(self);
+        }
+        if self.conclusion.is_some() {
+            return Err("Both a conjecture and a negated conjecture".to_string());
+        }
+        self.conclusion = Some(match negated_conjectures.into_iter().reduce(Expr::and).unwrap() {
+            Expr::Not(inner) => *inner,
+            formula => formula.negated()
+        });
+        Ok(self)
+    }
+}
+
+/// Whether a file looks like TPTP rather than one formula per line.
+pub fn is_tptp(contents: &str) -> bool {
+    contents.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('%'))
+        .any(|line| ["fof(", "cnf(", "qmf(", "include("].iter().any(|start| line.starts_with(start)))
+}
+
+pub fn read_problem(filename: &str) -> Result<Problem, String> {
+    let mut problem = Problem { premises: Vec::new(), conclusion: None, negated_conjectures: Vec::new() };
+    read_into(Path::new(filename), &mut problem)?;
+    problem.conclude()
+}
+
+pub fn parse_problem(contents: &str) -> Result<Problem, String> {
+    let mut problem = Problem { premises: Vec::new(), conclusion: None, negated_conjectures: Vec::new() };
+    parse_into(contents, None, &mut problem)?;
+    problem.conclude()
+}
+
+fn read_into(path: &Path, problem: &mut Problem) -> Result<(), String> {
+    let contents = read_to_string(path)
+        .map_err(|err| format!("{} ({})", err, path.display()))?;
+    parse_into(&contents, path.parent(), problem)
+}
+
+/// Includes are looked up next to the including file, then under $TPTP.
+fn resolve_include(name: &str, dir: Option<&Path>) -> Result<PathBuf, String> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if let Some(dir) = dir {
+        candidates.push(dir.join(name));
+    }
+    if let Ok(root) = env::var("TPTP") {
+        candidates.push(Path::new(&root).join(name));
+    }
+    candidates.push(PathBuf::from(name));
+    candidates.into_iter()
+        .find(|candidate| candidate.exists())
+        .ok_or(format!("Unable to find included file {}", name))
+}
+
+fn parse_into(contents: &str, dir: Option<&Path>, problem: &mut Problem) -> Result<(), String> {
+    let tokens = tokenize(contents)?;
+    let mut parser = TptpParser { tokens, pos: 0 };
+
+    while !parser.at_end() {
+        let kind = parser.word()?;
+        parser.expect("(")?;
+        if kind == "include" {
+            let name = parser.next()?;
+            parser.skip_to_close()?;
+            parser.expect(".")?;
+            let name = name.trim_matches('\'');
+            read_into(&resolve_include(name, dir)?, problem)?;
+            continue;
+        }
+        if !["fof", "cnf", "qmf"].contains(&kind.as_str()) {
+            return Err(format!("Unsupported TPTP statement {}", kind));
+        }
+
+        let name = parser.next()?;
+        parser.expect(",")?;
+        let role = parser.word()?;
+        parser.expect(",")?;
+        let mut formula = parser.formula()?;
+        if kind == "cnf" {
+            // the variables of a clause are universally quantified
+            let variables: Vec<String> = formula.free_names().into_iter().filter(|name| is_variable(name)).collect();
+            for var in variables.into_iter().rev() {
+                formula = Expr::Quantified(Quantifier::Universal, var, Box::new(formula));
+            }
+        }
+        if parser.peek() == Some(",") {
+            parser.skip_to_close()?;
+        } else {
+            parser.expect(")")?;
+        }
+        parser.expect(".")?;
+
+        match role.as_str() {
+            "axiom" | "hypothesis" | "definition" | "assumption" | "lemma" | "theorem" => {
+                problem.premises.push(formula);
+            },
+            "conjecture" => {
+                if problem.conclusion.is_some() {
+                    return Err(format!("More than one conjecture, second is {}", name));
+                }
+                problem.conclusion = Some(formula);
+            },
+            "negated_conjecture" => problem.negated_conjectures.push(formula),
+            _ => tracing::warn!("Ignoring {} with role {}", name, role)
+        }
+    }
+    Ok(())
+}
+
+fn tokenize(contents: &str) -> Result<Vec<String>, String> {
+    const SYMBOLS: [&str; 20] = [
+        "<=>", "<~>", "=>", "<=", "~|", "~&", "!=", "~", "&", "|", "!", "?", "=", "(", ")", ",", ".", ":", "[", "]"
+    ];
+    let mut tokens = Vec::new();
+    let mut rest = contents;
+
+    while let Some(ch) = rest.chars().next() {
+        if ch.is_whitespace() {
+            rest = &rest[ch.len_utf8()..];
+        } else if ch == '%' {
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+        } else if rest.starts_with("/*") {
+            let end = rest.find("*/").ok_or("Unterminated comment".to_string())?;
+            rest = &rest[end + 2..];
+        } else if ch == '\'' || ch == '"' {
+            let end = rest[1..].find(ch).ok_or("Unterminated quote".to_string())?;
+            tokens.push(rest[..end + 2].to_string());
+            rest = &rest[end + 2..];
+        } else if ch.is_alphanumeric() || ch == '_' || ch == '$' || ch == '#' {
+            let end = rest.char_indices().skip(1)
+                .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+                .map_or(rest.len(), |(idx, _)| idx);
+            tokens.push(rest[..end].to_string());
+            rest = &rest[end..];
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) {
+            tokens.push(symbol.to_string());
+            rest = &rest[symbol.len()..];
+        } else {
+            return Err(format!("Unexpected character {} in TPTP input", ch));
+        }
+    }
+    Ok(tokens)
+}
+
+struct TptpParser {
+    tokens: Vec<String>,
+    pos: usize
+}
+
+impl TptpParser {
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|token| token.as_str())
+    }
+
+    fn next(&mut self) -> Result<String, String> {
+        let token = self.tokens.get(self.pos).cloned()
+            .ok_or("Unexpected end of TPTP input".to_string())?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(format!("Expected {} but found {}", expected, token));
+        }
+        Ok(())
+    }
+
+    fn word(&mut self) -> Result<String, String> {
+        let token = self.next()?;
+        if !token.starts_with(|ch: char| ch.is_alphabetic()) {
+            return Err(format!("Expected a name but found {}", token));
+        }
+        Ok(token)
+    }
+
+    /// Skips optional annotations up to and including the closing parenthesis.
+    fn skip_to_close(&mut self) -> Result<(), String> {
+        let mut depth = 0;
+        loop {
+            match self.next()?.as_str() {
+                "(" => depth += 1,
+                ")" if depth == 0 => return Ok(()),
+                ")" => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    fn formula(&mut self) -> Result<Expr, String> {
+        let left = self.unitary()?;
+        let Some(op) = self.peek().map(String::from) else {
+            return Ok(left);
+        };
+
+        match op.as_str() {
+            "&" | "|" => {
+                let mut output = left;
+                while self.peek() == Some(op.as_str()) {
+                    self.pos += 1;
+                    let right = self.unitary()?;
+                    output = if op == "&" { output.and(right) } else { output.or(right) };
+                }
+                Ok(output)
+            },
+            "=>" | "<=" | "<=>" | "<~>" | "~|" | "~&" => {
+                self.pos += 1;
+                let right = self.unitary()?;
+                Ok(match op.as_str() {
+                    "=>" => left.implies(right),
+                    "<=" => right.implies(left),
+                    "<=>" => left.iff(right),
+                    "<~>" => left.xor(right),
+                    "~|" => left.or(right).negated(),
+                    _ => left.and(right).negated()
+                })
+            },
+            _ => Ok(left)
+        }
+    }
+
+    fn unitary(&mut self) -> Result<Expr, String> {
+        let token = self.next()?;
+        match token.as_str() {
+            "(" => {
+                let formula = self.formula()?;
+                self.expect(")")?;
+                Ok(formula)
+            },
+            "~" => Ok(self.unitary()?.negated()),
+            "#box" | "#dia" => {
+                // multi-modal problems index the operators, #box(a):
+                let index = if self.peek() == Some("(") {
+                    self.pos += 1;
+                    let index = self.next()?;
+                    if !index.chars().all(|ch| ch.is_alphanumeric() || ch == '_') {
+                        return Err(format!("Invalid index {} for {}", index, token));
+                    }
+                    self.expect(")")?;
+                    Some(index)
+                } else {
+                    None
+                };
+                self.expect(":")?;
+                let operand = Box::new(self.unitary()?);
+                Ok(match (token == "#box", index) {
+                    (true, None) => Expr::Necessarily(operand),
+                    (false, None) => Expr::Possibly(operand),
+                    (true, Some(index)) => Expr::IndexedNecessarily(index, operand),
+                    (false, Some(index)) => Expr::IndexedPossibly(index, operand)
+                })
+            },
+            "!" | "?" => {
+                let quantifier = if token == "!" { Quantifier::Universal } else { Quantifier::Existential };
+                self.expect("[")?;
+                let mut variables = vec![self.variable()?];
+                while self.peek() == Some(",") {
+                    self.pos += 1;
+                    variables.push(self.variable()?);
+                }
+                self.expect("]")?;
+                self.expect(":")?;
+                let mut formula = self.unitary()?;
+                for var in variables.into_iter().rev() {
+                    formula = Expr::Quantified(quantifier, var, Box::new(formula));
+                }
+                Ok(formula)
+            },
+            "$true" => Ok(Expr::Verum),
+            "$false" => Ok(Expr::Falsum),
+            _ if token.starts_with('$') => Err(format!("Unsupported TPTP constant {}", token)),
+            _ if matches!(self.peek(), Some("=" | "!=")) => {
+                let left = self.term(token)?;
+                let negated = self.next()? == "!=";
+                let right = self.next()?;
+                let identity = Expr::Predicate(IDENTITY.to_string(), vec![left, self.term(right)?]);
+                Ok(if negated { identity.negated() } else { identity })
+            },
+            _ if token.starts_with(|ch: char| ch.is_lowercase()) => {
+                if self.peek() != Some("(") {
+                    return Ok(Expr::Atom(token));
+                }
+                self.pos += 1;
+                let mut args = Vec::new();
+                loop {
+                    let arg = self.next()?;
+                    args.push(self.term(arg)?);
+                    match self.next()?.as_str() {
+                        "," => continue,
+                        ")" => break,
+                        other => return Err(format!("Expected , or ) but found {} in arguments of {}", other, token))
+                    }
+                }
+                Ok(Expr::Predicate(token, args))
+            },
+            _ => Err(format!("Unexpected {} in TPTP formula", token))
+        }
+    }
+
+    fn variable(&mut self) -> Result<String, String> {
+        let token = self.next()?;
+        if !is_variable(&token) {
+            return Err(format!("Expected a variable but found {}", token));
+        }
+        Ok(token)
+    }
+
+    /// A constant or variable, which is all predicates here apply to.
+    fn term(&self, token: String) -> Result<String, String> {
+        if self.peek() == Some("(") {
+            return Err(format!("Function term {}(...) is not supported", token));
+        }
+        if !token.starts_with(|ch: char| ch.is_alphabetic()) {
+            return Err(format!("Expected a term but found {}", token));
+        }
+        Ok(token)
+    }
+}
+
+/// TPTP variables start with a capital letter.
+fn is_variable(name: &str) -> bool {
+    name.starts_with(|ch: char| ch.is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_expr;
+
+    #[test]
+    fn propositional() {
+        let problem = parse_problem("
+            % modus ponens
+            fof(ax1, axiom, (p => q)).
+            fof(ax2, axiom, p, file('x', y)).
+            fof(goal, conjecture, q).
+        ").unwrap();
+        assert_eq!(vec![parse_expr("p ⊃ q").unwrap(), parse_expr("p").unwrap()], *problem.premises());
+        assert_eq!(Some(&parse_expr("q").unwrap()), problem.conclusion());
+        assert_eq!(vec!["p ⊃ q", "p", "q"], problem.formulas().unwrap());
+
+        let problem = parse_problem("fof(a, axiom, ~ (p_1 & q & r) <=> s).").unwrap();
+        assert_eq!(
+            "¬(p_1 ⋀ q ⋀ r) ≡ s",
+            problem.premises()[0].to_string()
+        );
+        assert!(problem.formulas().is_err());
+
+        let problem = parse_problem("cnf(c, negated_conjecture, ~ p | q).").unwrap();
+        assert_eq!("¬(¬p ⋁ q)", problem.conclusion().unwrap().to_string());
+    }
+
+    #[test]
+    fn modal() {
+        let problem = parse_problem("
+            qmf(k, axiom, (#box:(p => q))).
+            qmf(con, conjecture, (#box:p => #dia:~ q)).
+        ").unwrap();
+        assert_eq!("◻(p ⊃ q)", problem.premises()[0].to_string());
+        assert_eq!("◻p ⊃ ◇¬q", problem.conclusion().unwrap().to_string());
+
+        let problem = parse_problem("qmf(a, conjecture, #box(a):p => #dia(b):#box(K_c):p).").unwrap();
+        assert_eq!("[a]p ⊃ <b>K_c p", problem.conclusion().unwrap().to_string());
+    }
+
+    #[test]
+    fn first_order() {
+        let problem = parse_problem("
+            fof(men, axiom, ! [X] : (man(X) => mortal(X))).
+            fof(socrates, axiom, man(socrates)).
+            fof(goal, conjecture, ? [X, Y] : (mortal(X) & X = Y & loves(Y, plato))).
+        ").unwrap();
+        assert_eq!(
+            vec!["∀X (man(X) ⊃ mortal(X))", "man(socrates)", "∃X ∃Y (mortal(X) ⋀ (X = Y) ⋀ loves(Y, plato))"],
+            problem.formulas().unwrap()
+        );
+        let problem = parse_problem("fof(a, axiom, a != b & ~ p).").unwrap();
+        assert_eq!("¬(a = b) ⋀ ¬p", problem.premises()[0].to_string());
+
+        let problem = parse_problem("qmf(a, conjecture, ! [X] : #box:p(X) => #box:! [X] : p(X)).").unwrap();
+        assert_eq!("∀X ◻p(X) ⊃ ◻∀X p(X)", problem.conclusion().unwrap().to_string());
+    }
+
+    #[test]
+    fn clauses() {
+        // variables of clauses are universal, and the clauses of the
+        // negated conjecture together deny the conclusion
+        let problem = parse_problem("
+            cnf(men, axiom, ~ man(X) | mortal(X)).
+            cnf(socrates, axiom, man(socrates)).
+            cnf(goal_1, negated_conjecture, ~ mortal(socrates)).
+            cnf(goal_2, negated_conjecture, ~ p | q).
+        ").unwrap();
+        assert_eq!("∀X (¬man(X) ⋁ mortal(X))", problem.premises()[0].to_string());
+        assert_eq!("¬(¬mortal(socrates) ⋀ (¬p ⋁ q))", problem.conclusion().unwrap().to_string());
+
+        assert!(parse_problem("fof(a, conjecture, p). cnf(b, negated_conjecture, q).").is_err());
+        assert!(parse_problem("fof(a, conjecture, p). fof(b, conjecture, q).").is_err());
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(parse_problem("fof(a, axiom, ! [x] : p(x)).").is_err());
+        assert!(parse_problem("fof(a, axiom, p(f(a))).").is_err());
+        assert!(parse_problem("fof(a, axiom, X).").is_err());
+        assert!(parse_problem("qmf(a, axiom, #box(~):p).").is_err());
+        assert!(parse_problem("fof(a, axiom, p").is_err());
+        assert!(parse_problem("fof(a, axiom, p & q | r).").is_err());
+        assert!(parse_problem("tff(a, axiom, p).").is_err());
+    }
+
+    #[test]
+    fn files() {
+        assert!(is_tptp(&read_to_string("data/tptp/modus_ponens.p").unwrap()));
+        assert!(!is_tptp(&read_to_string("data/basic.txt").unwrap()));
+
+        let problem = read_problem("data/tptp/modus_ponens.p").unwrap();
+        assert_eq!(vec!["p ⊃ q", "p", "q"], problem.formulas().unwrap());
+
+        let problem = read_problem("data/qmltp/k_axiom.p").unwrap();
+        assert_eq!(vec!["◻(p ⊃ q)", "◻p ⊃ ◻q"], problem.formulas().unwrap());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::ast::{Expr, GroupModality, knowledge};
+use crate::fol::{Fol, Term, tptp_name};
+use crate::modal_config::ModalOptions;
+use crate::parser::parse_expr;
+
+/// Name of the accessibility predicate in translated formulas.
+pub const ACCESSIBILITY: &str = "R";
+/// Constant naming the world at which premises and conclusion are evaluated.
+pub const ROOT_WORLD: &str = "w0";
+
+/// Standard translation ST_x: the first-order formula true of world `world`
+/// exactly when `expr` is true there. Atoms become unary predicates. Common
+/// knowledge is not first-order definable and has no translation, and
+/// neither do the temporal operators, counterfactuals, conditional
+/// obligations, hybrid formulas or formulas already first-order.
+pub fn standard_translation(expr: &Expr, world: &Term) -> Result<Fol, String> {
+    let mut counter = 0;
+    translate(expr, world, &mut counter)
+}
+
+fn translate(expr: &Expr, world: &Term, counter: &mut usize) -> Result<Fol, String> {
+    let fol = match expr {
+        Expr::Atom(name) => Fol::pred(name, std::slice::from_ref(world)),
+        Expr::Verum => Fol::Verum,
+        Expr::Falsum => Fol::Falsum,
+        Expr::Not(a) => translate(a, world, counter)?.negated(),
+        Expr::And(a, b) => translate(a, world, counter)?.and(translate(b, world, counter)?),
+        Expr::Or(a, b) => translate(a, world, counter)?.or(translate(b, world, counter)?),
+        Expr::Implies(a, b) => translate(a, world, counter)?.implies(translate(b, world, counter)?),
+        Expr::Iff(a, b) | Expr::Xor(a, b) => {
+            let (a, b) = (translate(a, world, counter)?, translate(b, world, counter)?);
+            let iff = a.clone().implies(b.clone()).and(b.implies(a));
+            if matches!(expr, Expr::Xor(..)) { iff.negated() } else { iff }
+        },
+        Expr::Strict(a, b) => modality(&[ACCESSIBILITY.to_string()], true, &a.clone().implies(*b.clone()), world, counter)?,
+        Expr::Necessarily(a) => modality(&[ACCESSIBILITY.to_string()], true, a, world, counter)?,
+        Expr::Possibly(a) => modality(&[ACCESSIBILITY.to_string()], false, a, world, counter)?,
+        Expr::IndexedNecessarily(i, a) => modality(&[accessibility(i)], true, a, world, counter)?,
+        Expr::IndexedPossibly(i, a) => modality(&[accessibility(i)], false, a, world, counter)?,
+        Expr::Group(GroupModality::Everyone, agents, a) => {
+            let mut conjuncts = Vec::new();
+            for agent in agents {
+                conjuncts.push(modality(&[accessibility(&knowledge(agent))], true, a, world, counter)?);
+            }
+            conjuncts.into_iter().reduce(Fol::and).expect("Groups have at least one agent")
+        },
+        Expr::Group(GroupModality::Distributed, agents, a) => {
+            let relations: Vec<String> = agents.iter().map(|agent| accessibility(&knowledge(agent))).collect();
+            modality(&relations, true, a, world, counter)?
+        },
+        Expr::Group(GroupModality::Common, ..) | Expr::Temporal(..) | Expr::Until(..) | Expr::Since(..)
+        | Expr::Counterfactual(..) | Expr::Obligation(..) | Expr::At(..) | Expr::Bind(..)
+        | Expr::Predicate(..) | Expr::Quantified(..) => {
+            return Err(format!("{} has no first-order translation", expr));
+        }
+    };
+    Ok(fol)
+}
+
+/// Name of the accessibility predicate of an indexed modality, e.g. `R_a`.
+pub fn accessibility(index: &str) -> String {
+    format!("{}_{}", ACCESSIBILITY, index)
+}
+
+/// ST of ◻a or ◇a over the intersection of `relations`.
+fn modality(relations: &[String], necessity: bool, a: &Expr, world: &Term, counter: &mut usize) -> Result<Fol, String> {
+    let var = format!("x{}", counter);
+    *counter += 1;
+    let next = Term::Var(var.clone());
+    let access = relations.iter()
+        .map(|relation| Fol::pred(relation, &[world.clone(), next.clone()]))
+        .reduce(Fol::and)
+        .expect("A modality has at least one relation");
+    let body = translate(a, &next, counter)?;
+    if necessity {
+        Ok(Fol::forall(&var, access.implies(body)))
+    } else {
+        Ok(Fol::exists(&var, access.and(body)))
+    }
+}
+
+/// First-order frame conditions the modal options impose on the
+/// accessibility predicate `relation`, by name.
+pub fn frame_axioms(relation: &str, options: &ModalOptions) -> Vec<(&'static str, Fol)> {
+    let x = Term::Var("x".to_string());
+    let y = Term::Var("y".to_string());
+    let z = Term::Var("z".to_string());
+    let r = |a: &Term, b: &Term| Fol::pred(relation, &[a.clone(), b.clone()]);
+
+    let mut axioms = Vec::new();
+    if options.rho() {
+        axioms.push(("reflexivity", Fol::forall("x", r(&x, &x))));
+    }
+    if options.sigma() {
+        axioms.push(("symmetry", Fol::forall("x", Fol::forall("y", r(&x, &y).implies(r(&y, &x))))));
+    }
+    if options.tau() {
+        axioms.push(("transitivity", Fol::forall("x", Fol::forall("y", Fol::forall("z",
+            r(&x, &y).and(r(&y, &z)).implies(r(&x, &z))
+        )))));
+    }
+    if options.eta() {
+        axioms.push(("seriality", Fol::forall("x", Fol::exists("y", r(&x, &y)))));
+    }
+
+    // the remaining conditions all start from a fork Rxy ⋀ Rxz
+    let fork = |then: Fol| Fol::forall("x", Fol::forall("y", Fol::forall("z",
+        r(&x, &y).and(r(&x, &z)).implies(then)
+    )));
+    if options.euclid() {
+        axioms.push(("euclideanness", fork(r(&y, &z))));
+    }
+    if options.convergent() {
+        let w = Term::Var("w".to_string());
+        axioms.push(("convergence", fork(Fol::exists("w", r(&y, &w).and(r(&z, &w))))));
+    }
+    if options.connected() {
+        axioms.push(("connectedness", fork(r(&y, &z).or(Fol::equals(&y, &z)).or(r(&z, &y)))));
+    }
+    if options.functional() {
+        axioms.push(("functionality", fork(Fol::equals(&y, &z))));
+    }
+    if options.dense() {
+        axioms.push(("density", Fol::forall("x", Fol::forall("z",
+            r(&x, &z).implies(Fol::exists("y", r(&x, &y).and(r(&y, &z))))
+        ))));
+    }
+    axioms
+}
+
+/// A TPTP FOF problem stating that the conclusion holds at a world wherever
+/// the premises do, over frames meeting the modal options of each relation.
+/// Relations are given by index, the empty index being ◻'s, and the axioms
+/// of an indexed relation have its predicate in their names.
+pub fn tptp_problem(premises: &[String], conclusion: &str, relations: &[(String, ModalOptions)]) -> Result<String, String> {
+    let root = Term::Const(ROOT_WORLD.to_string());
+    let mut lines: Vec<String> = Vec::new();
+    for (index, options) in relations {
+        let relation = if index.is_empty() { ACCESSIBILITY.to_string() } else { accessibility(index) };
+        for (name, axiom) in frame_axioms(&relation, options) {
+            let name = if index.is_empty() { name.to_string() } else { tptp_name(&format!("{}_{}", name, relation)) };
+            lines.push(format!("fof({}, axiom, {}).", name, axiom.to_tptp()));
+        }
+    }
+
+    for (idx, premise) in premises.iter().enumerate() {
+        let translated = standard_translation(&parse_expr(premise)?, &root)?;
+        lines.push(format!("fof(premise_{}, axiom, {}).", idx + 1, translated.to_tptp()));
+    }
+    let translated = standard_translation(&parse_expr(conclusion)?, &root)?;
+    lines.push(format!("fof(conclusion, conjecture, {}).", translated.to_tptp()));
+
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Model;
+
+    #[test]
+    fn translation() {
+        let x = Term::Var("x".to_string());
+        let expr = parse_expr("◻p ⊃ ◇(q ⋀ ◻p)").unwrap();
+        assert_eq!(
+            "∀x0(R(x, x0) ⊃ p(x0)) ⊃ ∃x1(R(x, x1) ⋀ (q(x1) ⋀ ∀x2(R(x1, x2) ⊃ p(x2))))",
+            standard_translation(&expr, &x).unwrap().to_string()
+        );
+
+        let expr = parse_expr("D_{a,b} p ⋀ E_{a,b} q").unwrap();
+        assert_eq!(
+            "∀x0((R_K_a(x, x0) ⋀ R_K_b(x, x0)) ⊃ p(x0)) ⋀ (∀x1(R_K_a(x, x1) ⊃ q(x1)) ⋀ ∀x2(R_K_b(x, x2) ⊃ q(x2)))",
+            standard_translation(&expr, &x).unwrap().to_string()
+        );
+        assert!(standard_translation(&parse_expr("C_{a,b} p").unwrap(), &x).is_err());
+    }
+
+    #[test]
+    fn axioms() {
+        let axioms = frame_axioms(ACCESSIBILITY, &ModalOptions::new(true, false, true, true));
+        let names: Vec<&str> = axioms.iter().map(|(name, _)| *name).collect();
+        assert_eq!(vec!["reflexivity", "transitivity", "seriality"], names);
+        assert_eq!("∀x∃yR(x, y)", axioms[2].1.to_string());
+
+        let axioms = frame_axioms(ACCESSIBILITY, &ModalOptions::for_logic("K.3+dense").unwrap());
+        let names: Vec<&str> = axioms.iter().map(|(name, _)| *name).collect();
+        assert_eq!(vec!["connectedness", "density"], names);
+        assert_eq!("∀x∀y∀z((R(x, y) ⋀ R(x, z)) ⊃ ((R(y, z) ⋁ y = z) ⋁ R(z, y)))", axioms[0].1.to_string());
+    }
+
+    #[test]
+    fn tptp() {
+        let t = ModalOptions::new(true, false, false, false);
+        let problem = tptp_problem(&["◻p".to_string()], "p", &[(String::new(), t)]).unwrap();
+        assert_eq!(
+            "fof(reflexivity, axiom, ! [X] : a_R(X,X)).\n\
+             fof(premise_1, axiom, ! [X0] : (a_R(w0,X0) => p(X0))).\n\
+             fof(conclusion, conjecture, p(w0)).",
+            problem
+        );
+
+        // knowledge is reflexive, and --index sets the conditions of [a]
+        let formulas = vec!["K_a p ⊃ p".to_string(), "[a]q ⊃ q".to_string()];
+        let mut model = Model::new(ModalOptions::for_logic("K").unwrap(), formulas).unwrap();
+        model.set_index_options("a", t).unwrap();
+        let problem = tptp_problem(model.premises(), model.conclusion().unwrap(), &model.relation_options()).unwrap();
+        assert!(problem.contains("fof(reflexivity_R_K_a, axiom, ! [X] : a_R_K_a(X,X))."), "{}", problem);
+        assert!(problem.contains("fof(reflexivity_R_a, axiom, ! [X] : a_R_a(X,X))."), "{}", problem);
+        assert!(!problem.contains("fof(reflexivity, axiom"), "{}", problem);
+    }
+}
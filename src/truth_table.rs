@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use crate::ast::Expr;
+use crate::countermodel::Countermodel;
+use crate::graphs::{Graph, node::World};
+use crate::parser::parse_expr;
+
+/// Beyond this the table has more rows than anyone wants to read.
+pub const MAX_ATOMS: usize = 16;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Classification {
+    Tautology,
+    Contradiction,
+    Contingent
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Classification::Tautology => write!(f, "tautology"),
+            Classification::Contradiction => write!(f, "contradiction"),
+            Classification::Contingent => write!(f, "contingent")
+        }
+    }
+}
+
+/// Every valuation of the atoms of some propositional formulas, with the value
+/// of each of their subformulas.
+pub struct TruthTable {
+    atoms: Vec<String>,
+    columns: Vec<Expr>,
+    rows: Vec<Vec<bool>>
+}
+
+impl TruthTable {
+    pub fn new(formulas: &[Expr]) -> Result<TruthTable, String> {
+        if let Some(modal) = formulas.iter().find(|formula| formula.is_modal() || formula.is_temporal() || formula.is_quantified()) {
+            return Err(format!("Truth tables need propositional formulas, found {}", modal));
+        }
+
+        let atoms: Vec<String> = formulas.iter()
+            .flat_map(|formula| formula.atoms())
+            .collect::<std::collections::BTreeSet<String>>()
+            .into_iter()
+            .collect();
+        if atoms.len() > MAX_ATOMS {
+            return Err(format!("Too many atoms for a truth table: {} (max {})", atoms.len(), MAX_ATOMS));
+        }
+
+        // atoms first, then compound subformulas in order of construction
+        let mut columns: Vec<Expr> = atoms.iter().map(|atom| Expr::atom(atom)).collect();
+        for subformula in formulas.iter().flat_map(|formula| formula.subformulas()) {
+            if !columns.contains(&subformula) {
+                columns.push(subformula);
+            }
+        }
+
+        let rows = (0..1_usize << atoms.len())
+            .map(|row| {
+                let valuation = Self::valuation(&atoms, row);
+                columns.iter()
+                    .map(|column| column.eval(&valuation))
+                    .collect::<Result<Vec<bool>, String>>()
+            })
+            .collect::<Result<Vec<Vec<bool>>, String>>()?;
+
+        Ok(TruthTable { atoms, columns, rows })
+    }
+
+    /// The valuation on a given row: the first row makes every atom true.
+    fn valuation(atoms: &[String], row: usize) -> HashMap<String, bool> {
+        atoms.iter().enumerate()
+            .map(|(idx, atom)| (atom.clone(), row & (1 << (atoms.len() - 1 - idx)) == 0))
+            .collect()
+    }
+
+    pub fn atoms(&self) -> &Vec<String> {
+        &self.atoms
+    }
+
+    pub fn columns(&self) -> &Vec<Expr> {
+        &self.columns
+    }
+
+    pub fn rows(&self) -> &Vec<Vec<bool>> {
+        &self.rows
+    }
+
+    pub fn column(&self, formula: &Expr) -> Option<Vec<bool>> {
+        let idx = self.columns.iter().position(|column| column == formula)?;
+        Some(self.rows.iter().map(|row| row[idx]).collect())
+    }
+
+    pub fn classify(&self, formula: &Expr) -> Option<Classification> {
+        let column = self.column(formula)?;
+        if column.iter().all(|value| *value) {
+            Some(Classification::Tautology)
+        } else if column.iter().all(|value| !*value) {
+            Some(Classification::Contradiction)
+        } else {
+            Some(Classification::Contingent)
+        }
+    }
+
+    pub fn classify_formula(&self, formula: &str) -> Result<Classification, String> {
+        self.classify(&parse_expr(formula)?)
+            .ok_or(format!("{} is not in the table", formula))
+    }
+
+    /// First row making every premise true and the conclusion false, as a
+    /// single world countermodel.
+    pub fn countermodel(&self, premises: &[Expr], conclusion: &Expr) -> Option<Countermodel> {
+        let premise_columns: Vec<Vec<bool>> = premises.iter()
+            .map(|premise| self.column(premise))
+            .collect::<Option<Vec<Vec<bool>>>>()?;
+        let conclusion_column = self.column(conclusion)?;
+
+        let row = (0..self.rows.len())
+            .find(|row| premise_columns.iter().all(|column| column[*row]) && !conclusion_column[*row])?;
+        let true_atoms: HashSet<String> = self.atoms.iter().enumerate()
+            .filter(|(idx, _)| self.rows[row][*idx])
+            .map(|(_, atom)| atom.clone())
+            .collect();
+
+        Some(Countermodel::new(Graph::<World>::new(1), vec![true_atoms]))
+    }
+}
+
+impl fmt::Display for TruthTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let headers: Vec<String> = self.columns.iter().map(|column| column.to_string()).collect();
+        writeln!(f, "{}", headers.join(" | "))?;
+        for row in self.rows.iter() {
+            let cells: Vec<String> = row.iter().zip(headers.iter())
+                .map(|(value, header)| {
+                    let width = header.chars().count();
+                    format!("{:^width$}", if *value { "T" } else { "F" }, width = width)
+                })
+                .collect();
+            writeln!(f, "{}", cells.join(" | "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Decides an argument by truth table. Returns the table with a verified
+/// countermodel if the argument is invalid.
+pub fn eval_argument(premises: &[String], conclusion: &str) -> Result<(TruthTable, Option<Countermodel>), String> {
+    let premise_exprs = premises.iter()
+        .map(|premise| parse_expr(premise))
+        .collect::<Result<Vec<Expr>, String>>()?;
+    let conclusion_expr = parse_expr(conclusion)?;
+
+    let mut formulas = premise_exprs.clone();
+    formulas.push(conclusion_expr.clone());
+    let table = TruthTable::new(&formulas)?;
+
+    let countermodel = table.countermodel(&premise_exprs, &conclusion_expr);
+    if let Some(countermodel) = countermodel.as_ref() {
+        countermodel.verify(premises, Some(&conclusion.to_string()))?;
+    }
+    Ok((table, countermodel))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Model;
+    use crate::modal_config::ModalOptions;
+
+    #[test]
+    fn classification() {
+        let formulas: Vec<Expr> = ["p ⋁ ¬p", "p ⋀ ¬p", "p ⊃ q"].iter()
+            .map(|formula| parse_expr(formula).unwrap())
+            .collect();
+        let table = TruthTable::new(&formulas).unwrap();
+
+        assert_eq!(vec!["p", "q"], *table.atoms());
+        assert_eq!(4, table.rows().len());
+        assert_eq!(Some(Classification::Tautology), table.classify(&formulas[0]));
+        assert_eq!(Some(Classification::Contradiction), table.classify(&formulas[1]));
+        assert_eq!(Some(Classification::Contingent), table.classify(&formulas[2]));
+        assert_eq!(Some(vec![true, false, true, true]), table.column(&formulas[2]));
+
+        assert!(TruthTable::new(&[parse_expr("◻p").unwrap()]).is_err());
+    }
+
+    #[test]
+    fn display() {
+        let table = TruthTable::new(&[parse_expr("¬p").unwrap()]).unwrap();
+        assert_eq!("p | ¬p\nT | F \nF | T \n", table.to_string());
+    }
+
+    #[test]
+    fn arguments() {
+        let premises = vec!["p ⊃ q".to_string(), "p".to_string()];
+        let (_, countermodel) = eval_argument(&premises, "q").unwrap();
+        assert!(countermodel.is_none());
+
+        let premises = vec!["p ⊃ q".to_string(), "q".to_string()];
+        let (_, countermodel) = eval_argument(&premises, "p").unwrap();
+        let countermodel = countermodel.unwrap();
+        assert!(countermodel.true_atoms(0).unwrap().contains("q"));
+        assert!(!countermodel.true_atoms(0).unwrap().contains("p"));
+    }
+
+    #[test]
+    fn agrees_with_tableau() {
+        let formulas = [
+            "p ⊃ (q ⊃ p)",
+            "(p ⊃ (q ⊃ r)) ⊃ ((p ⊃ q) ⊃ (p ⊃ r))",
+            "((p ⊃ q) ⊃ p) ⊃ p",
+            "(p ⋁ q) ⊃ (p ⋀ q)",
+            "¬(p ⋀ q) ⊃ (¬p ⋁ ¬q)",
+            "(p ⊃ q) ⊃ (q ⊃ p)",
+            "(p ⋀ (q ⋁ r)) ⊃ ((p ⋀ q) ⋁ r)",
+            "¬¬p ⊃ ¬(¬p ⋁ q)",
+        ];
+        for formula in formulas {
+            let (table, _) = eval_argument(&[], formula).unwrap();
+            let tautology = table.classify(&parse_expr(formula).unwrap()) == Some(Classification::Tautology);
+
+            let mut model = Model::new(ModalOptions::new_default(), vec![formula.to_string()]).unwrap();
+            let valid = model.eval_tableau().unwrap().is_none();
+            assert_eq!(tautology, valid, "{}", formula);
+        }
+    }
+}